@@ -0,0 +1,167 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::Url;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+/// Keeps the raw html of every changed page, so parsing bugs can be reproduced
+/// against exactly the markup that triggered them.
+///
+/// Snapshots are stored as `<dir>/<YYYY-MM-DD>/<HH-MM-SS>_<page>.gz`.
+pub(crate) struct Archive {
+  dir: PathBuf,
+}
+
+impl Archive {
+  pub(crate) fn new(dir: PathBuf) -> Self {
+    Self { dir }
+  }
+
+  pub(crate) async fn store(
+    &self,
+    url: &Url,
+    crawled: OffsetDateTime,
+    html: &str,
+  ) -> anyhow::Result<PathBuf> {
+    let dir = self.dir.join(
+      crawled
+        .date()
+        .format(format_description!("[year]-[month]-[day]"))?,
+    );
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let path = dir.join(format!(
+      "{}_{}.gz",
+      crawled.format(format_description!("[hour]-[minute]-[second]"))?,
+      page_name(url)
+    ));
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(html.as_bytes())?;
+    write_atomic(&path, &encoder.finish()?).await?;
+
+    Ok(path)
+  }
+}
+
+/// Serves pages from the snapshots of a single day instead of the upstream
+/// server, e.g. to find out what would have been sent on that day.
+pub(crate) struct Replay {
+  dir: PathBuf,
+  until: Option<Time>,
+}
+
+impl Replay {
+  pub(crate) fn new(dir: PathBuf, until: Option<Time>) -> Self {
+    Self { dir, until }
+  }
+
+  /// Latest snapshot of the page that isn't newer than `until`, together with
+  /// the time it was crawled.
+  pub(crate) async fn load(&self, url: &Url) -> anyhow::Result<(String, OffsetDateTime)> {
+    let date = self
+      .dir
+      .file_name()
+      .and_then(|name| name.to_str())
+      .and_then(|name| Date::parse(name, format_description!("[year]-[month]-[day]")).ok())
+      .ok_or_else(|| anyhow!("{} isn't an archived day", self.dir.display()))?;
+
+    let suffix = format!("_{}.gz", page_name(url));
+    let mut latest: Option<(Time, PathBuf)> = None;
+
+    let mut entries = tokio::fs::read_dir(&self.dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+      let name = entry.file_name();
+      let Some(time) = name
+        .to_str()
+        .and_then(|name| name.strip_suffix(&suffix))
+        .and_then(|time| Time::parse(time, format_description!("[hour]-[minute]-[second]")).ok())
+      else {
+        continue;
+      };
+
+      if self.until.is_some_and(|until| time > until) {
+        continue;
+      }
+      if latest.as_ref().is_none_or(|(latest, _)| time > *latest) {
+        latest = Some((time, entry.path()));
+      }
+    }
+
+    let (time, path) = latest.ok_or_else(|| anyhow!("No snapshot of {} archived", url))?;
+
+    let mut html = String::new();
+    GzDecoder::new(tokio::fs::read(&path).await?.as_slice()).read_to_string(&mut html)?;
+
+    Ok((html, PrimitiveDateTime::new(date, time).assume_utc()))
+  }
+}
+
+/// File name of a page, including its directory so equally named pages of
+/// different plans don't collide.
+pub(crate) fn page_name(url: &Url) -> String {
+  url.path().trim_start_matches('/').replace('/', "_")
+}
+
+async fn write_atomic(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+  let tmp = path.with_extension("tmp");
+  tokio::fs::write(&tmp, content).await?;
+  tokio::fs::rename(&tmp, path).await?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use reqwest::Url;
+  use time::macros::{datetime, time};
+
+  use crate::archive::{page_name, Archive, Replay};
+
+  #[test]
+  fn test_page_name() -> anyhow::Result<()> {
+    assert_eq!(
+      "V_PlanBGy_V_DC_001.html",
+      page_name(&Url::parse("https://example.org/V_PlanBGy/V_DC_001.html")?)
+    );
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn test_replay() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("bszet-replay-{}", std::process::id()));
+    let url = Url::parse("https://example.org/V_DC_001.html")?;
+
+    let archive = Archive::new(dir.clone());
+    archive
+      .store(&url, datetime!(2024-05-06 06:00 UTC), "early")
+      .await?;
+    archive
+      .store(&url, datetime!(2024-05-06 09:30 UTC), "late")
+      .await?;
+
+    let day = dir.join("2024-05-06");
+    let (html, crawled) = Replay::new(day.clone(), Some(time!(08:00)))
+      .load(&url)
+      .await?;
+    assert_eq!("early", html);
+    assert_eq!(datetime!(2024-05-06 06:00 UTC), crawled);
+
+    let (html, _) = Replay::new(day.clone(), None).load(&url).await?;
+    assert_eq!("late", html);
+
+    assert!(Replay::new(day, Some(time!(05:00)))
+      .load(&url)
+      .await
+      .is_err());
+
+    tokio::fs::remove_dir_all(dir).await?;
+    Ok(())
+  }
+}
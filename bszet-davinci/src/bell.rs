@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::RwLock;
+
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use time::macros::format_description;
+use time::{Date, Month, OffsetDateTime, Time, UtcOffset, Weekday};
+
+use crate::extractor::parse_lesson;
+
+static BELL_SCHEDULE: Lazy<RwLock<BellSchedule>> =
+  Lazy::new(|| RwLock::new(BellSchedule::default()));
+
+/// Start and end time of each block, loaded from a TOML file like
+/// `1 = ["07:30", "09:00"]`.
+#[derive(Default)]
+pub struct BellSchedule(BTreeMap<u8, (Time, Time)>);
+
+impl BellSchedule {
+  pub fn load(path: &Path) -> anyhow::Result<Self> {
+    Self::parse(&std::fs::read_to_string(path)?)
+  }
+
+  fn parse(raw: &str) -> anyhow::Result<Self> {
+    let format = format_description!("[hour]:[minute]");
+    let raw: HashMap<String, [String; 2]> = toml::from_str(raw)?;
+
+    let mut lessons = BTreeMap::new();
+    for (lesson, [start, end]) in raw {
+      lessons.insert(
+        lesson
+          .parse()
+          .map_err(|err| anyhow!("Invalid lesson {lesson}: {err}"))?,
+        (Time::parse(&start, format)?, Time::parse(&end, format)?),
+      );
+    }
+
+    Ok(Self(lessons))
+  }
+
+  /// Replaces the schedule used by the functions of this module.
+  pub fn install(self) {
+    *BELL_SCHEDULE.write().unwrap() = self;
+  }
+
+  /// Start and end time of the block, `None` if it isn't in the schedule.
+  pub fn lesson_times(&self, lesson: u8) -> Option<(Time, Time)> {
+    self.0.get(&lesson).copied()
+  }
+
+  /// Formatted like `07:30–09:00`.
+  pub fn format_lesson_times(&self, lesson: u8) -> Option<String> {
+    self
+      .lesson_times(lesson)
+      .map(|(start, end)| format_times(start, end))
+  }
+
+  /// Times of a raw lesson cell of the plan, counted in hours, e.g. `3.` or a
+  /// range like `3./4.` or `3. - 6.`.
+  pub fn raw_lesson_times(&self, cell: &str) -> Option<String> {
+    let mut hours = cell
+      .split(['-', '–', '/'])
+      .map(|hour| hour.trim().trim_end_matches('.'))
+      .filter(|hour| !hour.is_empty());
+
+    let first = hours.next()?;
+    let last = hours.next_back().unwrap_or(first);
+
+    let (start, _) = self.lesson_times(parse_lesson(&format!("{first}.")).ok()?)?;
+    let (_, end) = self.lesson_times(parse_lesson(&format!("{last}.")).ok()?)?;
+
+    Some(format_times(start, end))
+  }
+}
+
+pub(crate) fn hash_installed(state: &mut impl Hasher) {
+  BELL_SCHEDULE.read().unwrap().0.hash(state);
+}
+
+pub fn lesson_times(lesson: u8) -> Option<(Time, Time)> {
+  BELL_SCHEDULE.read().unwrap().lesson_times(lesson)
+}
+
+pub fn format_lesson_times(lesson: u8) -> Option<String> {
+  BELL_SCHEDULE.read().unwrap().format_lesson_times(lesson)
+}
+
+pub(crate) fn raw_lesson_times(cell: &str) -> Option<String> {
+  BELL_SCHEDULE.read().unwrap().raw_lesson_times(cell)
+}
+
+fn format_times(start: Time, end: Time) -> String {
+  format!(
+    "{:0>2}:{:0>2}–{:0>2}:{:0>2}",
+    start.hour(),
+    start.minute(),
+    end.hour(),
+    end.minute()
+  )
+}
+
+/// The time in Germany, where the bell schedule applies. Summer time starts
+/// and ends at 01:00 UTC on the last sundays of march and october.
+pub fn school_time(now: OffsetDateTime) -> OffsetDateTime {
+  let now = now.to_offset(UtcOffset::UTC);
+  let summer_start = last_sunday(now.year(), Month::March)
+    .with_hms(1, 0, 0)
+    .unwrap();
+  let summer_end = last_sunday(now.year(), Month::October)
+    .with_hms(1, 0, 0)
+    .unwrap();
+
+  let hours = match (summer_start..summer_end).contains(&now.date().with_time(now.time())) {
+    true => 2,
+    false => 1,
+  };
+  now.to_offset(UtcOffset::from_hms(hours, 0, 0).unwrap())
+}
+
+/// The instant of a local time in Germany, e.g. the start of a lesson.
+pub fn from_school_time(date: Date, time: Time) -> OffsetDateTime {
+  let winter = date
+    .with_time(time)
+    .assume_offset(UtcOffset::from_hms(1, 0, 0).unwrap());
+  match school_time(winter).offset().whole_hours() {
+    1 => winter,
+    _ => date
+      .with_time(time)
+      .assume_offset(UtcOffset::from_hms(2, 0, 0).unwrap()),
+  }
+}
+
+fn last_sunday(year: i32, month: Month) -> Date {
+  let mut date = Date::from_calendar_date(year, month, month.length(year)).unwrap();
+  while date.weekday() != Weekday::Sunday {
+    date = date.previous_day().unwrap();
+  }
+  date
+}
+
+#[cfg(test)]
+mod test {
+  use time::macros::{date, datetime, time};
+
+  use crate::bell::{from_school_time, school_time, BellSchedule};
+
+  #[test]
+  fn test_school_time() {
+    assert_eq!(
+      datetime!(2024-01-15 08:00 +1),
+      school_time(datetime!(2024-01-15 07:00 UTC))
+    );
+    assert_eq!(
+      datetime!(2024-06-03 08:00 +2),
+      school_time(datetime!(2024-06-03 06:00 UTC))
+    );
+    // summer time starts on 2024-03-31 and ends on 2024-10-27
+    assert_eq!(
+      datetime!(2024-03-31 01:59 +1),
+      school_time(datetime!(2024-03-31 00:59 UTC))
+    );
+    assert_eq!(
+      datetime!(2024-03-31 03:00 +2),
+      school_time(datetime!(2024-03-31 01:00 UTC))
+    );
+    assert_eq!(
+      datetime!(2024-10-27 02:00 +1),
+      school_time(datetime!(2024-10-27 01:00 UTC))
+    );
+    // the date changes before midnight in utc
+    assert_eq!(
+      datetime!(2024-03-07 00:30 +1),
+      school_time(datetime!(2024-03-06 23:30 UTC))
+    );
+
+    assert_eq!(
+      datetime!(2024-01-15 06:30 UTC),
+      from_school_time(date!(2024 - 01 - 15), time!(7:30))
+    );
+    assert_eq!(
+      datetime!(2024-06-03 05:30 UTC),
+      from_school_time(date!(2024 - 06 - 03), time!(7:30))
+    );
+  }
+
+  #[test]
+  fn test_bell_schedule() -> anyhow::Result<()> {
+    let schedule = BellSchedule::parse(
+      r#"
+        1 = ["07:30", "09:00"]
+        2 = ["09:20", "10:50"]
+      "#,
+    )?;
+
+    assert_eq!(Some((time!(9:20), time!(10:50))), schedule.lesson_times(2));
+    assert_eq!(
+      Some("07:30–09:00".to_string()),
+      schedule.format_lesson_times(1)
+    );
+    assert_eq!(None, schedule.lesson_times(3));
+
+    assert_eq!(
+      Some("09:20–10:50".to_string()),
+      schedule.raw_lesson_times("3.")
+    );
+    assert_eq!(
+      Some("07:30–10:50".to_string()),
+      schedule.raw_lesson_times("2./3.")
+    );
+    assert_eq!(
+      Some("07:30–10:50".to_string()),
+      schedule.raw_lesson_times("1 - 4")
+    );
+    assert_eq!(None, schedule.raw_lesson_times("5."));
+    assert_eq!(None, schedule.raw_lesson_times(""));
+    assert_eq!(None, BellSchedule::default().format_lesson_times(1));
+
+    Ok(())
+  }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock as SyncRwLock;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::Client;
+use time::Time;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::archive::{Archive, Replay};
+use crate::changelog::ChangeLog;
+use crate::timetable::TimetableRegistry;
+use crate::{Davinci, Entrypoint};
+
+pub struct DavinciBuilder {
+  entrypoints: Vec<Entrypoint>,
+  username: String,
+  password: String,
+  client: Option<Client>,
+  headers: HeaderMap,
+  archive: Option<PathBuf>,
+  replay: Option<Replay>,
+}
+
+impl DavinciBuilder {
+  pub(crate) fn new(username: String, password: String) -> Self {
+    Self {
+      entrypoints: Vec::new(),
+      username,
+      password,
+      client: None,
+      headers: HeaderMap::new(),
+      archive: None,
+      replay: None,
+    }
+  }
+
+  /// Adds a plan to crawl, the rows of all plans are merged.
+  pub fn entrypoint(mut self, entrypoint: Entrypoint) -> Self {
+    self.entrypoints.push(entrypoint);
+    self
+  }
+
+  /// Uses a preconfigured client, e.g. with timeouts or a proxy.
+  pub fn client(mut self, client: Client) -> Self {
+    self.client = Some(client);
+    self
+  }
+
+  pub fn user_agent(self, user_agent: HeaderValue) -> Self {
+    self.header(USER_AGENT, user_agent)
+  }
+
+  /// Sent with every request, overriding the defaults of the client.
+  pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+    self.headers.insert(name, value);
+    self
+  }
+
+  /// Stores the raw html of every changed page in the directory.
+  pub fn archive(mut self, dir: PathBuf) -> Self {
+    self.archive = Some(dir);
+    self
+  }
+
+  /// Reads the pages from the snapshots of an archived day (a directory below
+  /// the archive) instead of the upstream server, optionally only considering
+  /// snapshots until the given time.
+  pub fn replay(mut self, dir: PathBuf, until: Option<Time>) -> Self {
+    self.replay = Some(Replay::new(dir, until));
+    self
+  }
+
+  pub fn build(self) -> Davinci {
+    Davinci {
+      client: self.client.unwrap_or_default(),
+      headers: self.headers,
+      username: SyncRwLock::new(self.username),
+      password: SyncRwLock::new(self.password),
+      entrypoints: self.entrypoints,
+      data: RwLock::new(None),
+      pages: Mutex::new(HashMap::new()),
+      chains: Mutex::new(HashMap::new()),
+      archive: self.archive.map(Archive::new),
+      replay: self.replay,
+      timetables: TimetableRegistry::builtin(),
+      changes: Mutex::new(ChangeLog::default()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use reqwest::header::{HeaderValue, USER_AGENT};
+  use reqwest::Url;
+
+  use crate::Davinci;
+
+  #[test]
+  fn test_builder() -> anyhow::Result<()> {
+    let davinci = Davinci::builder("user".to_string(), "pass".to_string())
+      .entrypoint(Url::parse("http://localhost/V_DC_001.html")?.into())
+      .user_agent(HeaderValue::from_static("bszet-mind"))
+      .build();
+
+    let request = davinci
+      .get(Url::parse("http://localhost/V_DC_002.html")?)
+      .build()?;
+
+    assert_eq!("bszet-mind", request.headers()[USER_AGENT]);
+    assert_eq!("Basic dXNlcjpwYXNz", request.headers()["authorization"]);
+
+    Ok(())
+  }
+}
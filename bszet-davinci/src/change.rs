@@ -1,14 +1,20 @@
+use std::fmt::{Display, Formatter};
+use std::iter::once;
+
 use anyhow::anyhow;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::info;
 
-use crate::timetable::{Lesson, Subject};
+use crate::teachers::teacher_names;
+use crate::timetable::{Lesson, LessonChange, Subject};
 use crate::REPLACEMENT_REGEX;
 
 static MOVED_FROM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("Von .+ verschoben").unwrap());
 static MOVED_TO_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("Auf .+ verschoben").unwrap());
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Change {
   Cancel {
     lesson: u8,
@@ -17,7 +23,8 @@ pub enum Change {
     place: String,
     notice: String,
   },
-  PlaceChange {
+  #[serde(alias = "PlaceChange")]
+  RoomChange {
     lesson: u8,
     subject: Subject,
     teachers: Vec<String>,
@@ -38,6 +45,27 @@ pub enum Change {
     place: Replacement<String>,
     notice: String,
   },
+  Exam {
+    lesson: u8,
+    subject: Subject,
+    teachers: Vec<String>,
+    place: String,
+    notice: String,
+  },
+  /// Break supervision, `lesson` is `0` if the row has no lesson number.
+  Supervision {
+    lesson: u8,
+    teachers: Replacement<Vec<String>>,
+    place: String,
+    notice: String,
+  },
+  TeacherChange {
+    lesson: u8,
+    subject: Subject,
+    teachers: Replacement<Vec<String>>,
+    place: String,
+    notice: String,
+  },
   Other {
     lesson: u8,
     value: String,
@@ -48,7 +76,7 @@ pub enum Change {
   },
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Replacement<T> {
   pub from: Option<T>,
   pub to: T,
@@ -64,6 +92,12 @@ impl Change {
     notice: Option<String>,
   ) -> anyhow::Result<Self> {
     Ok(match value {
+      toc if is_supervision(toc, subject) => Self::Supervision {
+        lesson,
+        teachers: teacher.try_into()?,
+        place,
+        notice: notice.unwrap_or(value.to_string()),
+      },
       toc
         if ["Fällt aus", "Klasse fehlt", "Kurs fehlt"].contains(&toc)
           || MOVED_TO_REGEX.is_match(toc) =>
@@ -72,31 +106,22 @@ impl Change {
           lesson,
           subject: subject.into(),
           place,
-          teachers: teacher
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .collect::<Vec<String>>(),
+          teachers: parse_teachers(teacher),
           notice: notice.unwrap_or(value.to_string()),
         }
       }
-      "Raumänderung" => Self::PlaceChange {
+      "Raumänderung" | "Raumtausch" => Self::RoomChange {
         lesson,
         subject: subject.into(),
         place: place.as_str().try_into()?,
-        teachers: teacher
-          .split(',')
-          .map(|s| s.trim().to_string())
-          .collect::<Vec<String>>(),
+        teachers: parse_teachers(teacher),
         notice: notice.unwrap_or(value.to_string()),
       },
       "Zusatzunterricht" => Self::Addition {
         lesson,
         subject: subject.into(),
         place: if place.is_empty() { None } else { Some(place) },
-        teachers: teacher
-          .split(',')
-          .map(|s| s.trim().to_string())
-          .collect::<Vec<String>>(),
+        teachers: parse_teachers(teacher),
         notice: notice.unwrap_or(value.to_string()),
       },
       toc if toc == "Vertreten" || MOVED_FROM_REGEX.is_match(toc) => Self::Replacement {
@@ -106,15 +131,26 @@ impl Change {
         teachers: teacher.try_into()?,
         notice: notice.unwrap_or(value.to_string()),
       },
+      "Klausur" | "Prüfung" => Self::Exam {
+        lesson,
+        subject: subject.into(),
+        teachers: parse_teachers(teacher),
+        place,
+        notice: notice.unwrap_or(value.to_string()),
+      },
+      "anderer Lehrer" | "Lehrertausch" => Self::TeacherChange {
+        lesson,
+        subject: subject.into(),
+        teachers: teacher.try_into()?,
+        place,
+        notice: notice.unwrap_or(value.to_string()),
+      },
       toc => Self::Other {
         lesson,
         value: toc.to_string(),
         subject: subject.into(),
         place,
-        teachers: teacher
-          .split(',')
-          .map(|s| s.trim().to_string())
-          .collect::<Vec<String>>(),
+        teachers: parse_teachers(teacher),
         notice: notice.unwrap_or(value.to_string()),
       },
     })
@@ -126,32 +162,45 @@ impl Change {
       Change::Cancel {
         lesson,
         subject,
+        teachers,
         notice,
         ..
       } => {
         match find_lesson(lessons, lesson, Some(subject), false)? {
           None => false,
           Some(lesson) => {
-            // TODO: place, teachers
+            // TODO: place
             lesson.subject = Subject::Cancel(Box::new(subject.clone()));
+            lesson.teachers.clone_from(teachers);
             lesson.notice = Some(notice.to_string());
+            lesson.change = Some(LessonChange::Cancel);
             true
           }
         }
       }
-      Change::PlaceChange {
+      Change::RoomChange {
         lesson,
         subject,
+        teachers,
         place,
         notice,
-        ..
       } => {
-        match find_lesson(lessons, lesson, Some(subject), false)? {
+        // the subject column is sometimes left empty, the previous room still
+        // identifies the lesson in that case
+        let found = match find_lesson(lessons, lesson, Some(subject), false)? {
+          Some(found) => Some(found),
+          None => lessons
+            .iter_mut()
+            .find(|l| &l.lesson == lesson && place.from.is_some() && l.place == place.from),
+        };
+
+        match found {
           None => false,
           Some(lesson) => {
-            // TODO: teachers, place.from
             lesson.place = Some(place.to.to_string());
+            lesson.teachers.clone_from(teachers);
             lesson.notice = Some(notice.to_string());
+            lesson.change = Some(LessonChange::Room);
             true
           }
         }
@@ -159,85 +208,227 @@ impl Change {
       Change::Addition {
         lesson,
         subject,
+        teachers,
         place,
         notice,
-        ..
       } => {
-        // TODO: teachers
         lessons.push(Lesson {
           lesson: *lesson,
           subject: subject.clone(),
           iteration: None,
+          exam: false,
           place: place.as_ref().map(|string| string.to_string()),
+          teachers: teachers.clone(),
           notice: Some(notice.to_string()),
+          change: Some(LessonChange::Moved),
         });
         true
       }
       Change::Replacement {
         lesson,
         subject,
+        teachers,
         place,
         notice,
-        ..
       } => {
         match find_lesson(lessons, lesson, subject.from.as_ref(), true)? {
           None => false,
           Some(lesson) => {
-            // TODO: teachers, place.from
+            // TODO: place.from
+            lesson.change = if lesson.subject != subject.to {
+              Some(LessonChange::Substitution)
+            } else if lesson.place.as_ref() != Some(&place.to) {
+              Some(LessonChange::Room)
+            } else {
+              lesson.change
+            };
             lesson.subject = subject.to.clone();
             lesson.place = Some(place.to.to_string());
+            lesson.teachers.clone_from(&teachers.to);
             lesson.notice = Some(notice.to_string());
             true
           }
         }
       }
+      Change::Exam {
+        lesson,
+        subject,
+        teachers,
+        place,
+        notice,
+      } => match find_lesson(lessons, lesson, Some(subject), false)? {
+        None => false,
+        Some(lesson) => {
+          lesson.exam = true;
+          if !place.is_empty() {
+            lesson.place = Some(place.to_string());
+          }
+          lesson.teachers.clone_from(teachers);
+          lesson.notice = Some(notice.to_string());
+          true
+        }
+      },
+      // supervisions don't affect lessons, they are listed on their own
+      Change::Supervision { .. } => true,
+      Change::TeacherChange {
+        lesson,
+        subject,
+        teachers,
+        notice,
+        ..
+      } => match find_lesson(lessons, lesson, Some(subject), false)? {
+        None => false,
+        Some(lesson) => {
+          // the lesson takes place, only the teacher differs
+          lesson.teachers.clone_from(&teachers.to);
+          lesson.notice = Some(notice.to_string());
+          lesson.change = lesson.change.or(Some(LessonChange::Teacher));
+          true
+        }
+      },
       Change::Other { .. } => false,
     })
   }
 
-  pub(crate) fn lesson(&self) -> u8 {
+  /// All subjects the change refers to.
+  pub fn subjects(&self) -> Vec<&Subject> {
+    match self {
+      Change::Cancel { subject, .. } => vec![subject],
+      Change::RoomChange { subject, .. } => vec![subject],
+      Change::Addition { subject, .. } => vec![subject],
+      Change::Replacement { subject, .. } => subject.from.iter().chain(once(&subject.to)).collect(),
+      Change::Exam { subject, .. } => vec![subject],
+      Change::Supervision { .. } => vec![],
+      Change::TeacherChange { subject, .. } => vec![subject],
+      Change::Other { subject, .. } => vec![subject],
+    }
+  }
+
+  pub fn lesson(&self) -> u8 {
     match self {
       Change::Cancel { lesson, .. } => *lesson,
-      Change::PlaceChange { lesson, .. } => *lesson,
+      Change::RoomChange { lesson, .. } => *lesson,
       Change::Addition { lesson, .. } => *lesson,
       Change::Replacement { lesson, .. } => *lesson,
+      Change::Exam { lesson, .. } => *lesson,
+      Change::Supervision { lesson, .. } => *lesson,
+      Change::TeacherChange { lesson, .. } => *lesson,
       Change::Other { lesson, .. } => *lesson,
     }
   }
 }
 
+pub(crate) fn is_supervision(type_of_change: &str, subject: &str) -> bool {
+  type_of_change.contains("Aufsicht") || subject == "Aufsicht"
+}
+
+fn parse_teachers(value: &str) -> Vec<String> {
+  value.split(',').map(|s| s.trim().to_string()).collect()
+}
+
 fn find_lesson<'a>(
   lessons: &'a mut [Lesson],
   lesson: &u8,
   subject: Option<&Subject>,
   allow_cancel: bool,
 ) -> anyhow::Result<Option<&'a mut Lesson>> {
-  let lessons = lessons
-    .iter_mut()
-    .filter(|l| &l.lesson == lesson)
-    .collect::<Vec<&mut Lesson>>();
-
-  match subject {
-    None => {
-      if lessons.len() == 1 {
-        Ok(lessons.into_iter().next())
+  let Some(subject) = subject else {
+    let mut lessons = lessons
+      .iter_mut()
+      .filter(|l| &l.lesson == lesson)
+      .collect::<Vec<&mut Lesson>>();
+
+    return if lessons.len() == 1 {
+      Ok(lessons.pop())
+    } else {
+      Err(anyhow!("Found multiple subjects without original value to clearly identify lesson. less: {}, subjs: {:?}",lesson , lessons))
+    };
+  };
+
+  let subject_matches = |l: &Lesson| {
+    if &l.subject == subject {
+      true
+    } else if allow_cancel {
+      if let Subject::Cancel(inner) = &l.subject {
+        inner.as_ref() == subject
       } else {
-        Err(anyhow!("Found multiple subjects without original value to clearly identify lesson. less: {}, subjs: {:?}",lesson , lessons))
+        false
       }
+    } else {
+      false
     }
-    Some(subject) => Ok(lessons.into_iter().find(|lesson| {
-      if &lesson.subject == subject {
-        true
-      } else if allow_cancel {
-        if let Subject::Cancel(inner) = &lesson.subject {
-          inner.as_ref() == subject
-        } else {
-          false
-        }
-      } else {
-        false
+  };
+
+  if let Some(index) = lessons
+    .iter()
+    .position(|l| &l.lesson == lesson && subject_matches(l))
+  {
+    return Ok(Some(&mut lessons[index]));
+  }
+
+  // the plan doesn't always match the timetable exactly, fall back to the most
+  // likely lesson if it is unambiguous
+  let in_block = lessons.iter().filter(|l| &l.lesson == lesson).count();
+  let mut best: Option<(usize, f32)> = None;
+  let mut ambiguous = false;
+
+  for (index, candidate) in lessons.iter().enumerate() {
+    let confidence = match_confidence(
+      candidate,
+      *lesson,
+      subject,
+      subject_matches(candidate),
+      in_block == 1,
+    );
+
+    if confidence < MIN_CONFIDENCE {
+      continue;
+    }
+
+    match best {
+      Some((_, best_confidence)) if best_confidence > confidence => {}
+      Some((_, best_confidence)) if best_confidence == confidence => ambiguous = true,
+      _ => {
+        best = Some((index, confidence));
+        ambiguous = false;
       }
-    })),
+    }
+  }
+
+  match best {
+    Some((index, confidence)) if !ambiguous => {
+      info!(
+        "Fuzzy matched {}. Block {} to {}. Block {} with confidence {}",
+        lesson, subject, lessons[index].lesson, lessons[index].subject, confidence
+      );
+      Ok(Some(&mut lessons[index]))
+    }
+    _ => Ok(None),
+  }
+}
+
+const MIN_CONFIDENCE: f32 = 0.5;
+
+/// How likely `candidate` is the lesson a change refers to, if there is no
+/// exact match.
+fn match_confidence(
+  candidate: &Lesson,
+  lesson: u8,
+  subject: &Subject,
+  subject_matches: bool,
+  alone_in_block: bool,
+) -> f32 {
+  match candidate.lesson.abs_diff(lesson) {
+    // off by one lesson number
+    1 if subject_matches => 0.7,
+    // renamed course, only trusted for subject codes we don't know
+    0 if alone_in_block
+      && matches!(subject, Subject::Other(_))
+      && !matches!(candidate.subject, Subject::Cancel(_)) =>
+    {
+      0.5
+    }
+    _ => 0.0,
   }
 }
 
@@ -293,3 +484,196 @@ impl TryFrom<&str> for Replacement<Vec<String>> {
     })
   }
 }
+
+impl Display for Change {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Change::Cancel {
+        lesson, subject, ..
+      } => write!(f, "{lesson}. Block {subject} entfällt"),
+      Change::RoomChange {
+        lesson,
+        subject,
+        place,
+        ..
+      } => {
+        write!(f, "{lesson}. Block {subject} in {}", place.to)?;
+        if let Some(from) = &place.from {
+          write!(f, " statt {from}")?;
+        }
+        Ok(())
+      }
+      Change::Addition {
+        lesson,
+        subject,
+        place,
+        ..
+      } => {
+        write!(f, "{lesson}. Block zusätzlich {subject}")?;
+        if let Some(place) = place {
+          write!(f, " in {place}")?;
+        }
+        Ok(())
+      }
+      Change::Replacement {
+        lesson,
+        subject,
+        teachers,
+        place,
+        ..
+      } => {
+        write!(f, "{lesson}. Block {}", subject.to)?;
+        if let Some(from) = &subject.from {
+          write!(f, " statt {from}")?;
+        }
+        write!(f, " in {}", place.to)?;
+        let teachers = teacher_names(&teachers.to);
+        if !teachers.is_empty() {
+          write!(f, " bei {teachers}")?;
+        }
+        Ok(())
+      }
+      Change::Exam {
+        lesson, subject, ..
+      } => write!(f, "{lesson}. Block Klausur in {subject}"),
+      Change::Supervision {
+        teachers, place, ..
+      } => {
+        write!(f, "Aufsicht")?;
+        if !place.is_empty() {
+          write!(f, " {place}")?;
+        }
+        write!(f, " bei {}", teacher_names(&teachers.to))?;
+        if let Some(from) = &teachers.from {
+          write!(f, " statt {}", teacher_names(from))?;
+        }
+        Ok(())
+      }
+      Change::TeacherChange {
+        lesson,
+        subject,
+        teachers,
+        ..
+      } => {
+        write!(
+          f,
+          "{lesson}. Block {subject} bei {}",
+          teacher_names(&teachers.to)
+        )?;
+        if let Some(from) = &teachers.from {
+          write!(f, " statt {}", teacher_names(from))?;
+        }
+        Ok(())
+      }
+      Change::Other {
+        lesson,
+        value,
+        subject,
+        ..
+      } => write!(f, "{lesson}. Block {subject}: {value}"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::change::{Change, Replacement};
+  use crate::timetable::{Lesson, LessonChange, Subject};
+
+  #[test]
+  fn test_apply_room_change() -> anyhow::Result<()> {
+    let mut day = vec![
+      Lesson::new(1, None, Subject::GermanBasic, "B6"),
+      Lesson::new(1, None, Subject::MathBasic, "B11"),
+    ];
+
+    let change = Change::new(1, "Raumänderung", "", "+A102 (B11)".to_string(), "", None)?;
+    assert_eq!(
+      Change::RoomChange {
+        lesson: 1,
+        subject: Subject::None,
+        teachers: vec!["".to_string()],
+        place: Replacement {
+          from: Some("B11".to_string()),
+          to: "A102".to_string(),
+        },
+        notice: "Raumänderung".to_string(),
+      },
+      change
+    );
+
+    assert!(change.apply(&mut day)?);
+    assert_eq!(Some("B6"), day[0].place.as_deref());
+    assert_eq!(Some("A102"), day[1].place.as_deref());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_apply_teacher_change() -> anyhow::Result<()> {
+    let mut day = vec![Lesson::new(2, None, Subject::Chemistry, "B9")];
+
+    let change = Change::new(
+      2,
+      "anderer Lehrer",
+      "CH",
+      "B9".to_string(),
+      "+Mül (Sch)",
+      None,
+    )?;
+    assert!(change.apply(&mut day)?);
+    assert_eq!(Subject::Chemistry, day[0].subject);
+    assert_eq!(vec!["Mül".to_string()], day[0].teachers);
+    assert_eq!(Some(LessonChange::Teacher), day[0].change);
+    assert_eq!("2. Block Ch bei Mül statt Sch", change.to_string());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_apply_substitution() -> anyhow::Result<()> {
+    let mut day = vec![Lesson::new(3, None, Subject::GermanBasic, "B6")];
+
+    let change = Change::new(
+      3,
+      "Vertreten",
+      "+MA (DEU)",
+      "B6".to_string(),
+      "+Mül (Sch)",
+      None,
+    )?;
+    assert!(change.apply(&mut day)?);
+    assert_eq!(Subject::MathBasic, day[0].subject);
+    assert_eq!(Some(LessonChange::Substitution), day[0].change);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_fuzzy_apply() -> anyhow::Result<()> {
+    let mut day = vec![
+      Lesson::new(1, None, Subject::GermanBasic, "B6"),
+      Lesson::new(2, None, Subject::Chemistry, "B9"),
+      Lesson::new(3, None, Subject::Ethics, "B4"),
+    ];
+
+    // off by one
+    let change = Change::new(3, "Fällt aus", "CH", "B9".to_string(), "Sch", None)?;
+    assert!(change.apply(&mut day)?);
+    assert_eq!(
+      Subject::Cancel(Box::new(Subject::Chemistry)),
+      day[1].subject
+    );
+
+    // renamed course
+    let change = Change::new(1, "Fällt aus", "DEU-NEU", "B6".to_string(), "Sch", None)?;
+    assert!(change.apply(&mut day)?);
+    assert!(matches!(day[0].subject, Subject::Cancel(_)));
+
+    // known, but different subject
+    let change = Change::new(3, "Fällt aus", "MA", "B4".to_string(), "Sch", None)?;
+    assert!(!change.apply(&mut day)?);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashSet, VecDeque};
+
+use time::{Duration, OffsetDateTime};
+
+use crate::Row;
+
+/// How long the changes of the plan are remembered.
+const RETENTION: Duration = Duration::days(7);
+
+/// Rows added to or removed from the plan by its updates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RowChanges {
+  pub added: Vec<Row>,
+  pub removed: Vec<Row>,
+}
+
+/// Remembers what each update of the plan changed, so clients can sync
+/// incrementally. Changes before the first update of the process are unknown.
+#[derive(Default)]
+pub(crate) struct ChangeLog {
+  /// Changes after this time are complete, `None` until the first update.
+  known_since: Option<OffsetDateTime>,
+  entries: VecDeque<(OffsetDateTime, RowChanges)>,
+}
+
+impl ChangeLog {
+  pub(crate) fn record(
+    &mut self,
+    at: OffsetDateTime,
+    previous: &HashSet<Row>,
+    rows: &HashSet<Row>,
+  ) {
+    if self.known_since.is_none() {
+      self.known_since = Some(at);
+      return;
+    }
+
+    self.entries.push_back((
+      at,
+      RowChanges {
+        added: rows.difference(previous).cloned().collect(),
+        removed: previous.difference(rows).cloned().collect(),
+      },
+    ));
+
+    while let Some((oldest, _)) = self.entries.front() {
+      if *oldest >= at - RETENTION {
+        break;
+      }
+
+      self.known_since = Some(*oldest);
+      self.entries.pop_front();
+    }
+  }
+
+  /// Net changes after the given time, `None` if they aren't known anymore.
+  /// Rows that were added and removed again cancel each other out.
+  pub(crate) fn since(&self, since: OffsetDateTime) -> Option<RowChanges> {
+    if self
+      .known_since
+      .is_none_or(|known_since| since < known_since)
+    {
+      return None;
+    }
+
+    let mut added = HashSet::new();
+    let mut removed = HashSet::new();
+
+    for (_, changes) in self.entries.iter().filter(|(at, _)| *at > since) {
+      for row in &changes.added {
+        if !removed.remove(row) {
+          added.insert(row.clone());
+        }
+      }
+      for row in &changes.removed {
+        if !added.remove(row) {
+          removed.insert(row.clone());
+        }
+      }
+    }
+
+    Some(RowChanges {
+      added: added.into_iter().collect(),
+      removed: removed.into_iter().collect(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::HashSet;
+
+  use time::macros::{date, datetime};
+
+  use crate::change::Change;
+  use crate::changelog::{ChangeLog, RowChanges};
+  use crate::timetable::Subject;
+  use crate::Row;
+
+  fn cancel(lesson: u8) -> Row {
+    Row {
+      index: lesson,
+      date: date!(2024 - 03 - 06),
+      class: vec!["IGD21".to_string()],
+      change: Change::Cancel {
+        lesson,
+        subject: Subject::MathBasic,
+        teachers: vec![],
+        place: String::new(),
+        notice: String::new(),
+      },
+      raw: vec![],
+      group: None,
+    }
+  }
+
+  #[test]
+  fn test_change_log() {
+    let mut log = ChangeLog::default();
+    let first = HashSet::from([cancel(1)]);
+    let second = HashSet::from([cancel(2)]);
+
+    log.record(datetime!(2024-03-06 06:00 UTC), &HashSet::new(), &first);
+    assert_eq!(None, log.since(datetime!(2024-03-06 05:00 UTC)));
+
+    log.record(datetime!(2024-03-06 07:00 UTC), &first, &second);
+    assert_eq!(
+      Some(RowChanges {
+        added: vec![cancel(2)],
+        removed: vec![cancel(1)],
+      }),
+      log.since(datetime!(2024-03-06 06:00 UTC))
+    );
+
+    log.record(datetime!(2024-03-06 08:00 UTC), &second, &first);
+    assert_eq!(
+      Some(RowChanges::default()),
+      log.since(datetime!(2024-03-06 06:00 UTC))
+    );
+    assert_eq!(
+      Some(RowChanges {
+        added: vec![cancel(1)],
+        removed: vec![cancel(2)],
+      }),
+      log.since(datetime!(2024-03-06 07:00 UTC))
+    );
+
+    log.record(datetime!(2024-03-14 08:00 UTC), &first, &first);
+    assert_eq!(None, log.since(datetime!(2024-03-06 06:00 UTC)));
+  }
+}
@@ -0,0 +1,34 @@
+/// Normalizes class names as written on the plan, `IGD 21`, `igd21` and the
+/// group `IGD 21/1` all become `IGD21`.
+pub fn normalize_class(name: &str) -> String {
+  name
+    .split('/')
+    .next()
+    .unwrap_or_default()
+    .chars()
+    .filter(|c| !c.is_whitespace())
+    .flat_map(char::to_uppercase)
+    .collect()
+}
+
+/// Checks a comma separated list of classes (e.g. a raw table cell) for the given class.
+pub fn contains_class(list: &str, class: &str) -> bool {
+  let class = normalize_class(class);
+  list.split(',').any(|name| normalize_class(name) == class)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::class::{contains_class, normalize_class};
+
+  #[test]
+  fn test_normalize_class() {
+    assert_eq!("IGD21", normalize_class("IGD21"));
+    assert_eq!("IGD21", normalize_class(" IGD 21 "));
+    assert_eq!("IGD21", normalize_class("igd21"));
+    assert_eq!("IGD21", normalize_class("IGD 21/1"));
+
+    assert!(contains_class("IGD 20, IGD 21/2", "IGD21"));
+    assert!(!contains_class("IGD 211", "IGD21"));
+  }
+}
@@ -0,0 +1,67 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use reqwest::Url;
+
+/// First page (or directory index) of a plan, optionally tagged with the plan
+/// group it belongs to, e.g. `BGy=https://…/V_PlanBGy/V_DC_001.html`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entrypoint {
+  pub group: Option<String>,
+  pub url: Url,
+}
+
+impl From<Url> for Entrypoint {
+  fn from(url: Url) -> Self {
+    Self { group: None, url }
+  }
+}
+
+impl FromStr for Entrypoint {
+  type Err = anyhow::Error;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.split_once('=') {
+      Some((group, url)) if !group.contains([':', '/']) => Ok(Self {
+        group: Some(group.trim().to_string()),
+        url: Url::parse(url.trim())?,
+      }),
+      _ => Ok(Url::parse(value.trim())?.into()),
+    }
+  }
+}
+
+impl Display for Entrypoint {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match &self.group {
+      Some(group) => write!(f, "{} ({})", self.url, group),
+      None => write!(f, "{}", self.url),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use reqwest::Url;
+
+  use crate::Entrypoint;
+
+  #[test]
+  fn test_parse_entrypoint() -> anyhow::Result<()> {
+    let url = Url::parse("https://example.org/V_PlanBS/V_DC_001.html?a=b")?;
+
+    assert_eq!(
+      Entrypoint {
+        group: Some("BS".to_string()),
+        url: url.clone(),
+      },
+      "BS=https://example.org/V_PlanBS/V_DC_001.html?a=b".parse()?
+    );
+    assert_eq!(
+      Entrypoint::from(url),
+      "https://example.org/V_PlanBS/V_DC_001.html?a=b".parse()?
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,47 @@
+use std::collections::BTreeSet;
+
+use reqwest::Url;
+use select::document::Document;
+use select::predicate::Name;
+
+/// Html documents linked from a directory index, limited to the directory of
+/// the index and sorted by name.
+pub(crate) fn extract_plan_links(doc: &Document, index: &Url) -> Vec<Url> {
+  doc
+    .find(Name("a"))
+    .filter_map(|link| link.attr("href"))
+    .filter_map(|href| index.join(href).ok())
+    .filter(|url| url.as_str().starts_with(index.as_str()))
+    .filter(|url| {
+      let path = url.path().to_lowercase();
+      path.ends_with(".html") || path.ends_with(".htm")
+    })
+    .collect::<BTreeSet<Url>>()
+    .into_iter()
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use reqwest::Url;
+  use select::document::Document;
+
+  use crate::extractor::extract_plan_links;
+
+  #[test]
+  fn test_extract_plan_links() -> anyhow::Result<()> {
+    let index = Url::parse("https://example.org/V_PlanBGy/")?;
+    let doc = Document::from(
+      "<html><body><a href=\"../\">Parent</a><a href=\"V_DC_002.html\">2</a>\
+       <a href=\"V_DC_001.html\">1</a><a href=\"style.css\">css</a>\
+       <a href=\"https://other.org/V_DC_003.html\">3</a><a href=\"V_DC_001.html\">1</a></body></html>",
+    );
+
+    assert_eq!(
+      vec![index.join("V_DC_001.html")?, index.join("V_DC_002.html")?],
+      extract_plan_links(&doc, &index)
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,69 @@
+use select::document::Document;
+use select::node::Node;
+use select::predicate::Text;
+
+const MESSAGES_HEADING: &str = "Nachrichten zum Tag";
+
+/// Extracts the general announcements listed below the "Nachrichten zum Tag"
+/// heading, one entry per non-empty line.
+pub(crate) fn extract_messages(doc: &Document) -> Vec<String> {
+  let Some(heading) = doc
+    .find(Text)
+    .find(|node| node.text().trim() == MESSAGES_HEADING)
+    .and_then(|node| node.parent())
+  else {
+    return Vec::new();
+  };
+
+  let mut messages = Vec::new();
+  let mut sibling = heading.next();
+
+  while let Some(node) = sibling {
+    if is_section_end(&node) {
+      break;
+    }
+
+    messages.extend(
+      node
+        .text()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string),
+    );
+
+    sibling = node.next();
+  }
+
+  messages
+}
+
+/// The next heading or the substitution table start a new section.
+fn is_section_end(node: &Node) -> bool {
+  matches!(
+    node.name(),
+    Some("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table" | "hr")
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use select::document::Document;
+
+  use crate::extractor::extract_messages;
+
+  #[test]
+  fn test_extract_messages() {
+    let doc = Document::from(
+      "<html><body><h1>Montag 04.03.2024</h1><h2>Nachrichten zum Tag</h2>\
+       <p>Wandertag der Klassen 11</p><p>\n  Sprechstunde entfällt  \n</p>\
+       <table><tr><td>IGD21</td></tr></table></body></html>",
+    );
+
+    assert_eq!(
+      vec!["Wandertag der Klassen 11", "Sprechstunde entfällt"],
+      extract_messages(&doc)
+    );
+    assert!(extract_messages(&Document::from("<h1>Montag 04.03.2024</h1>")).is_empty());
+  }
+}
@@ -8,9 +8,13 @@ use select::predicate::Name;
 use time::{Date, Month};
 
 pub(crate) use html_table::*;
+pub(crate) use links::*;
+pub(crate) use messages::*;
 pub(crate) use parser::*;
 
 mod html_table;
+mod links;
+mod messages;
 mod parser;
 
 static DATE_REGEX: Lazy<Regex> =
@@ -3,7 +3,7 @@ use std::str::FromStr;
 use anyhow::anyhow;
 use time::Date;
 
-use crate::change::Change;
+use crate::change::{is_supervision, Change};
 use crate::Row;
 
 pub(crate) fn parse(
@@ -33,6 +33,29 @@ pub(crate) fn parse(
     let place = columns[3].to_string();
     let teachers = &columns[4];
 
+    // supervisions happen during breaks and often come without lesson or class
+    if is_supervision(type_of_change, subject) {
+      let row = Row {
+        index: index as u8,
+        date: *date,
+        class: class
+          .or_else(|| rows.last().map(|last| last.class.clone()))
+          .unwrap_or_default(),
+        change: Change::new(
+          lesson.unwrap_or(0),
+          type_of_change,
+          subject,
+          place,
+          teachers,
+          notice,
+        )?,
+        raw: columns,
+        group: None,
+      };
+      rows.push(row);
+      continue;
+    }
+
     let row = if let Some(last) = rows.last() {
       Row {
         index: index as u8,
@@ -47,6 +70,7 @@ pub(crate) fn parse(
           notice,
         )?,
         raw: columns,
+        group: None,
       }
     } else {
       Row {
@@ -62,6 +86,7 @@ pub(crate) fn parse(
           notice,
         )?,
         raw: columns,
+        group: None,
       }
     };
 
@@ -86,7 +111,7 @@ fn parse_list(value: &str) -> Vec<String> {
     .collect()
 }
 
-fn parse_lesson(value: &str) -> anyhow::Result<u8> {
+pub(crate) fn parse_lesson(value: &str) -> anyhow::Result<u8> {
   // split ending point from number
   let raw = &value[..value.len() - 1];
   let num = u8::from_str(raw)?;
@@ -101,7 +126,10 @@ fn convert_lesson(lesson: u8) -> u8 {
 
 #[cfg(test)]
 mod test {
-  use crate::extractor::parser::convert_lesson;
+  use time::macros::date;
+
+  use crate::change::Change;
+  use crate::extractor::parser::{convert_lesson, parse};
 
   #[test]
   fn test_convert_lesson() {
@@ -116,4 +144,23 @@ mod test {
     assert_eq!(5, convert_lesson(9));
     assert_eq!(5, convert_lesson(10));
   }
+
+  #[test]
+  fn test_parse_supervision() -> anyhow::Result<()> {
+    let table = vec![["", "", "", "Hof", "+Mül (Sch)", "Aufsicht", ""]
+      .map(str::to_string)
+      .to_vec()];
+
+    let mut rows = Vec::new();
+    parse(table, &date!(2024 - 03 - 04), &mut rows)?;
+
+    assert!(rows[0].class.is_empty());
+    assert!(matches!(
+      rows[0].change,
+      Change::Supervision { lesson: 0, .. }
+    ));
+    assert_eq!("Aufsicht Hof bei Mül statt Sch", rows[0].change.to_string());
+
+    Ok(())
+  }
 }
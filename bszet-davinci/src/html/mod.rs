@@ -1,21 +1,308 @@
 use sailfish::TemplateOnce;
-use time::Date;
+use serde::Deserialize;
+use time::{Date, OffsetDateTime, Weekday};
+
+use crate::bell::raw_lesson_times;
+use crate::class::contains_class;
+use crate::iteration::get_iteration;
+use crate::teachers::replace_teacher_names;
+use crate::timetable::{Lesson, Subject};
+
+pub(crate) const HEADER: [&str; 7] = [
+  "Klasse",
+  "Std.",
+  "Fach",
+  "Raum",
+  "Lehrkraft",
+  "Art",
+  "Mitteilung",
+];
+
+/// Explains the abbreviations and colors of the plan.
+pub(crate) const LEGEND: [(&str, &str); 4] = [
+  ("Std.", "Unterrichtsstunde, eine Doppelstunde sind zwei"),
+  ("Art", "Vertretung, Entfall, Raumänderung, …"),
+  ("Hervorgehoben", "eigene Klasse"),
+  ("Rot", "Klausur oder Prüfung"),
+];
 
 #[derive(TemplateOnce)]
 #[template(path = "plan.stpl", rm_whitespace = true)]
 pub(crate) struct SubstitutionPlanTemplate<'a> {
   pub(crate) date: Date,
+  pub(crate) iteration: Option<u8>,
+  /// When the page of the date was last changed upstream.
+  pub(crate) last_modified: Option<OffsetDateTime>,
   pub(crate) table: Vec<&'a [String]>,
+  pub(crate) messages: &'a [String],
   pub(crate) classes: &'a [&'a str],
+  pub(crate) options: PlanOptions,
+  /// All dates of the plan, for navigating between them.
+  pub(crate) dates: Vec<Date>,
+}
+
+/// The day of a class with all changes of the plan applied.
+#[derive(TemplateOnce)]
+#[template(path = "applied.stpl", rm_whitespace = true)]
+pub(crate) struct AppliedTimetableTemplate<'a> {
+  pub(crate) date: Date,
+  pub(crate) class: &'a str,
+  pub(crate) iteration: u8,
+  pub(crate) lessons: &'a [Lesson],
+  pub(crate) theme: Theme,
+}
+
+/// Monday to friday of a class side by side, with all changes applied.
+#[derive(TemplateOnce)]
+#[template(path = "week.stpl", rm_whitespace = true)]
+pub(crate) struct WeekTemplate<'a> {
+  pub(crate) class: &'a str,
+  pub(crate) days: &'a [WeekDay],
+  pub(crate) theme: Theme,
+}
+
+/// A day of [`crate::Davinci::get_week`] with all changes applied.
+pub struct WeekDay {
+  pub date: Date,
+  /// `None` for days outside of the known iterations, e.g. holidays.
+  pub iteration: Option<u8>,
+  pub lessons: Vec<Lesson>,
+}
+
+/// Colors of the html pages, `?theme=dark` in urls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+  #[default]
+  Light,
+  /// Dark background, for chats reading the plan early in the morning.
+  Dark,
+}
+
+impl Theme {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Light => "light",
+      Self::Dark => "dark",
+    }
+  }
+}
+
+/// Presentation of a rendered plan.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlanOptions {
+  pub theme: Theme,
+  /// Links to the other dates, for people browsing the plan.
+  pub navigation: bool,
+  /// Only shows the rows of the requested classes.
+  pub collapse: bool,
+}
+
+/// Plan of a day with the content of the html plan, for rendering it without
+/// a browser.
+pub struct PlanView {
+  pub title: String,
+  /// Turnus and when the plan was last changed.
+  pub subtitle: String,
+  pub header: Vec<String>,
+  pub rows: Vec<PlanViewRow>,
+  pub messages: Vec<String>,
+  /// Rows left out because they belong to other classes.
+  pub hidden_rows: usize,
+}
+
+pub struct PlanViewRow {
+  /// The lesson cell has the times as second line.
+  pub cells: Vec<String>,
+  /// Belongs to one of the requested classes.
+  pub selected: bool,
+  pub exam: bool,
+}
+
+impl PlanView {
+  pub(crate) fn new(
+    date: Date,
+    table: &[&[String]],
+    messages: &[String],
+    classes: &[&str],
+    collapse: bool,
+    last_modified: Option<OffsetDateTime>,
+  ) -> Self {
+    Self {
+      title: title(date),
+      subtitle: subtitle(get_iteration(date), last_modified),
+      header: HEADER.iter().map(|name| name.to_string()).collect(),
+      rows: table
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !collapse || is_selected(table, *index, classes))
+        .map(|(index, columns)| PlanViewRow {
+          cells: columns
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| match column {
+              1 => match raw_lesson_times(cell) {
+                Some(times) if !cell.is_empty() => format!("{cell}\n{times}"),
+                _ => cell.clone(),
+              },
+              4 => replace_teacher_names(cell),
+              _ => cell.clone(),
+            })
+            .collect(),
+          selected: is_selected(table, index, classes),
+          exam: is_exam(columns),
+        })
+        .collect(),
+      messages: messages.to_vec(),
+      hidden_rows: match collapse {
+        true => hidden_rows(table, classes),
+        false => 0,
+      },
+    }
+  }
+}
+
+pub(crate) fn title(date: Date) -> String {
+  format!(
+    "{} der {:0>2}. {} {}",
+    date.weekday(),
+    date.day(),
+    date.month(),
+    date.year()
+  )
+}
+
+pub(crate) fn subtitle(iteration: Option<u8>, last_modified: Option<OffsetDateTime>) -> String {
+  let iteration = match iteration {
+    Some(iteration) => format!("Turnus {iteration}"),
+    None => "Turnus unbekannt".to_string(),
+  };
+
+  match last_modified {
+    Some(last_modified) => format!(
+      "{iteration} · Stand {:0>2}.{:0>2}.{} {:0>2}:{:0>2} UTC",
+      last_modified.day(),
+      last_modified.month() as u8,
+      last_modified.year(),
+      last_modified.hour(),
+      last_modified.minute()
+    ),
+    None => iteration,
+  }
+}
+
+/// The dates before and after the given one.
+pub(crate) fn neighbours(dates: &[Date], date: Date) -> (Option<Date>, Option<Date>) {
+  (
+    dates.iter().rev().find(|other| **other < date).copied(),
+    dates.iter().find(|other| **other > date).copied(),
+  )
+}
+
+/// Relative url of the plan of another date with the same options.
+pub(crate) fn day_url(date: Date, classes: &[&str], options: &PlanOptions) -> String {
+  format!(
+    "{date}?class={}&theme={}&collapse={}",
+    encode_query(&classes.join(",")),
+    options.theme.as_str(),
+    options.collapse
+  )
+}
+
+fn encode_query(value: &str) -> String {
+  value
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' => (byte as char).to_string(),
+      b' ' => "+".to_string(),
+      _ => format!("%{byte:02X}"),
+    })
+    .collect()
+}
+
+/// Rows without class continue the class of the rows above.
+pub(crate) fn is_selected(table: &[&[String]], index: usize, classes: &[&str]) -> bool {
+  table[..=index]
+    .iter()
+    .rev()
+    .find(|columns| !columns[0].is_empty())
+    .is_some_and(|columns| {
+      classes
+        .iter()
+        .any(|class| contains_class(&columns[0], class))
+    })
+}
+
+/// Rows that don't belong to any of the classes.
+pub(crate) fn hidden_rows(table: &[&[String]], classes: &[&str]) -> usize {
+  (0..table.len())
+    .filter(|index| !is_selected(table, *index, classes))
+    .count()
+}
+
+/// Name of the subject and whether it was cancelled.
+pub(crate) fn applied_subject(subject: &Subject) -> (String, bool) {
+  match subject {
+    Subject::Cancel(subject) => (subject.to_string(), true),
+    Subject::None => (String::new(), false),
+    subject => (subject.to_string(), false),
+  }
+}
+
+/// "Mo 30.01.", fits the narrow columns of the week view.
+pub(crate) fn short_title(date: Date) -> String {
+  let weekday = match date.weekday() {
+    Weekday::Monday => "Mo",
+    Weekday::Tuesday => "Di",
+    Weekday::Wednesday => "Mi",
+    Weekday::Thursday => "Do",
+    Weekday::Friday => "Fr",
+    Weekday::Saturday => "Sa",
+    Weekday::Sunday => "So",
+  };
+
+  format!("{weekday} {:0>2}.{:0>2}.", date.day(), date.month() as u8)
+}
+
+/// Highest lesson of the week, the rows of the week view.
+pub(crate) fn last_lesson(days: &[WeekDay]) -> u8 {
+  days
+    .iter()
+    .flat_map(|day| &day.lessons)
+    .map(|lesson| lesson.lesson)
+    .max()
+    .unwrap_or_default()
+}
+
+/// Lessons of the day at the given lesson, groups may have more than one.
+pub(crate) fn lessons_at(day: &WeekDay, lesson: u8) -> impl Iterator<Item = &Lesson> {
+  day
+    .lessons
+    .iter()
+    .filter(move |other| other.lesson == lesson)
+}
+
+/// Only changes of the plan carry teachers or a notice.
+pub(crate) fn is_changed(lesson: &Lesson) -> bool {
+  !lesson.teachers.is_empty() || lesson.notice.is_some()
+}
+
+pub(crate) fn is_exam(columns: &[String]) -> bool {
+  ["Klausur", "Prüfung"].contains(&columns[5].as_str())
 }
 
 #[cfg(test)]
 mod test {
   use sailfish::TemplateOnce;
+  use time::macros::datetime;
   use time::Date;
   use time::Month::January;
 
-  use crate::html::SubstitutionPlanTemplate;
+  use crate::html::{
+    hidden_rows, AppliedTimetableTemplate, PlanOptions, SubstitutionPlanTemplate, Theme, WeekDay,
+    WeekTemplate,
+  };
+  use crate::timetable::{Lesson, Subject};
 
   #[test]
   fn test_template() -> anyhow::Result<()> {
@@ -49,15 +336,119 @@ mod test {
 
     let table = vec![a.as_slice(), b.as_slice(), c.as_slice(), a.as_slice()];
 
-    let classes = vec!["IGD 21".to_string(), "IGD21".to_string()];
+    let classes = vec!["IGD 21", "IGD21"];
+
+    assert_eq!(2, hidden_rows(&table, &classes));
 
     let template = SubstitutionPlanTemplate {
       date: Date::from_calendar_date(2023, January, 28)?,
-      table,
+      iteration: Some(2),
+      last_modified: Some(datetime!(2023-01-27 14:05 UTC)),
+      table: table.clone(),
+      messages: &["Wandertag".to_string()],
       classes: classes.as_slice(),
+      options: PlanOptions {
+        theme: Theme::Dark,
+        navigation: true,
+        collapse: false,
+      },
+      dates: vec![
+        Date::from_calendar_date(2023, January, 27)?,
+        Date::from_calendar_date(2023, January, 28)?,
+      ],
     };
 
-    println!("{}", template.render_once()?);
+    let html = template.render_once()?;
+    assert!(html.contains("Turnus 2 · Stand 27.01.2023 14:05 UTC"));
+    assert!(html.contains("/static/dark.css"));
+    assert!(html.contains("/static/plan.css"));
+    assert!(
+      html.contains("href=\"2023-01-27?class=IGD+21%2CIGD21&amp;theme=dark&amp;collapse=false\"")
+    );
+    println!("{html}");
+
+    let collapsed = SubstitutionPlanTemplate {
+      date: Date::from_calendar_date(2023, January, 28)?,
+      iteration: Some(2),
+      last_modified: Some(datetime!(2023-01-27 14:05 UTC)),
+      table,
+      messages: &[],
+      classes: classes.as_slice(),
+      options: PlanOptions {
+        theme: Theme::Light,
+        navigation: false,
+        collapse: true,
+      },
+      dates: Vec::new(),
+    }
+    .render_once()?;
+
+    assert_eq!(2, collapsed.matches("<tr class=\"selected").count());
+    assert!(collapsed.contains("2 weitere Einträge ausgeblendet"));
+    assert!(!collapsed.contains("<nav>"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_applied_template() -> anyhow::Result<()> {
+    let mut replaced = Lesson::new(2, None, Subject::History, "B6");
+    replaced.notice = Some("Vertretung".to_string());
+
+    let html = AppliedTimetableTemplate {
+      date: Date::from_calendar_date(2023, January, 30)?,
+      class: "IGD21",
+      iteration: 1,
+      lessons: &[
+        Lesson::new(1, None, Subject::Cancel(Box::new(Subject::MathBasic)), "B6"),
+        replaced,
+      ],
+      theme: Theme::Light,
+    }
+    .render_once()?;
+
+    assert!(html.contains("Turnus 1"));
+    assert!(html.contains("<tr class=\"cancel\">"));
+    assert!(html.contains("<tr class=\"changed\">"));
+
+    Ok(())
+  }
+  #[test]
+  fn test_week_template() -> anyhow::Result<()> {
+    let monday = Date::from_calendar_date(2023, January, 30)?;
+
+    let mut moved = Lesson::new(3, None, Subject::History, "B6");
+    moved.notice = Some("verlegt".to_string());
+
+    let days = (0..5)
+      .map(|offset| WeekDay {
+        date: monday + time::Duration::days(offset),
+        iteration: (offset < 4).then_some(1),
+        lessons: match offset {
+          0 => vec![
+            Lesson::new(1, None, Subject::Cancel(Box::new(Subject::MathBasic)), "B6"),
+            moved.clone(),
+          ],
+          _ => Vec::new(),
+        },
+      })
+      .collect::<Vec<_>>();
+
+    let html = WeekTemplate {
+      class: "IGD21",
+      days: &days,
+      theme: Theme::Light,
+    }
+    .render_once()?;
+
+    assert!(html.contains("KW 5"));
+    assert!(html.contains("Mo 30.01."));
+    assert!(html.contains("Fr 03.02."));
+    assert_eq!(4, html.matches("Turnus 1").count());
+    // header and lessons 1 to 3
+    assert_eq!(4, html.matches("<tr>").count());
+    assert!(html.contains("<div class=\"cancel\">"));
+    assert!(html.contains("<div class=\"changed\">"));
 
     Ok(())
   }
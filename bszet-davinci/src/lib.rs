@@ -1,76 +1,330 @@
-use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
+use std::iter::once;
+use std::sync::RwLock as SyncRwLock;
 
 use anyhow::anyhow;
+use futures_util::{stream, StreamExt, TryStreamExt};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use reqwest::header::LAST_MODIFIED;
-use reqwest::{Client, Url};
+use reqwest::header::{
+  HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
 use sailfish::TemplateOnce;
 use select::document::Document;
+use serde::{Deserialize, Serialize};
 use time::format_description::well_known::Rfc2822;
+use time::serde::format_description;
 use time::{Date, OffsetDateTime};
-use tokio::sync::{RwLock, RwLockReadGuard};
-use tracing::{error, info};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+use tracing::{info, warn};
 
+pub use builder::DavinciBuilder;
 use change::Change;
-
-use crate::extractor::{extract_date, extract_html_table, extract_next_page, parse};
-use crate::html::SubstitutionPlanTemplate;
+pub use changelog::RowChanges;
+use class::normalize_class;
+pub use entrypoint::Entrypoint;
+pub use html::{PlanOptions, PlanView, PlanViewRow, Theme, WeekDay};
+
+use crate::archive::{Archive, Replay};
+use crate::changelog::ChangeLog;
+use crate::extractor::{
+  extract_date, extract_html_table, extract_messages, extract_next_page, extract_plan_links, parse,
+};
+use crate::html::{AppliedTimetableTemplate, SubstitutionPlanTemplate, WeekTemplate};
 use crate::iteration::get_iteration;
-use crate::timetable::igd21::IGD21;
-use crate::timetable::Lesson;
+use crate::timetable::{Lesson, TimetableRegistry};
+
+format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+/// Lessons of the day, the rows that couldn't be applied and the iteration.
+pub type AppliedDay = (Vec<Lesson>, Vec<Row>, u8);
+
+/// Hash of the installed subject mapping, teacher names and bell schedule,
+/// they change the rendered plans without changing the data.
+pub fn settings_hash() -> u64 {
+  let mut hasher = DefaultHasher::new();
+  timetable::hash_installed_mapping(&mut hasher);
+  teachers::hash_installed(&mut hasher);
+  bell::hash_installed(&mut hasher);
+  hasher.finish()
+}
 
 static REPLACEMENT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("\\+(.*) \\((.+)\\)").unwrap());
 
-mod change;
+mod archive;
+pub mod bell;
+mod builder;
+pub mod change;
+mod changelog;
+pub mod class;
+mod entrypoint;
 mod extractor;
 mod html;
 mod iteration;
+pub mod teachers;
 #[cfg(test)]
 mod test;
 pub mod timetable;
+mod unapplied;
 
 pub struct Davinci {
   client: Client,
-  username: String,
-  password: String,
-  entrypoint: Url,
+  headers: HeaderMap,
+  username: SyncRwLock<String>,
+  password: SyncRwLock<String>,
+  entrypoints: Vec<Entrypoint>,
   data: RwLock<Option<Data>>,
+  pages: Mutex<HashMap<Url, CachedPage>>,
+  /// Urls of all pages by entrypoint, starting with the entrypoint itself.
+  chains: Mutex<HashMap<Url, Vec<Url>>>,
+  archive: Option<Archive>,
+  replay: Option<Replay>,
+  timetables: TimetableRegistry,
+  changes: Mutex<ChangeLog>,
 }
 
 pub struct Data {
   pub last_checked: OffsetDateTime,
   pub last_modified: Option<OffsetDateTime>,
+  pub last_modified_by_date: BTreeMap<Date, OffsetDateTime>,
+  /// General announcements ("Nachrichten zum Tag") of each day.
+  pub messages: BTreeMap<Date, Vec<String>>,
   pub rows: HashSet<Row>,
 }
 
+/// Upper bound of pages fetched at the same time.
+const CONCURRENT_FETCHES: usize = 4;
+
+#[derive(Clone)]
+struct Page {
+  date: Date,
+  last_modified: Option<OffsetDateTime>,
+  messages: Vec<String>,
+  next: Option<Url>,
+}
+
+/// The upstream server rejected the basic auth credentials, retrying won't help
+/// until they were updated.
+#[derive(Debug)]
+pub struct CredentialsRejected(pub StatusCode);
+
+impl std::fmt::Display for CredentialsRejected {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "credentials rejected by upstream ({})", self.0)
+  }
+}
+
+impl std::error::Error for CredentialsRejected {}
+
+trait CheckStatus {
+  fn check_status(self) -> anyhow::Result<Response>;
+}
+
+impl CheckStatus for Response {
+  fn check_status(self) -> anyhow::Result<Response> {
+    match self.status() {
+      status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+        Err(CredentialsRejected(status).into())
+      }
+      _ => Ok(self.error_for_status()?),
+    }
+  }
+}
+
+/// Validators and parsed content of a page, reused if the server answers with
+/// `304 Not Modified`.
+struct CachedPage {
+  last_modified: Option<HeaderValue>,
+  etag: Option<HeaderValue>,
+  /// Detects changes of pages without validators.
+  body_hash: u64,
+  page: Page,
+  rows: Vec<Row>,
+}
+
+impl Data {
+  /// Raw rows of the day in the order of the plan.
+  fn table(&self, date: &Date) -> Vec<&[String]> {
+    let mut rows = self
+      .rows
+      .iter()
+      .filter(|row| &row.date == date)
+      .collect::<Vec<&Row>>();
+
+    rows.sort_by_key(|row| row.index);
+    rows.iter().map(|row| row.raw.as_slice()).collect()
+  }
+
+  fn last_modified_of(&self, date: &Date) -> Option<OffsetDateTime> {
+    self
+      .last_modified_by_date
+      .get(date)
+      .copied()
+      .or(self.last_modified)
+  }
+
+  fn messages_of(&self, date: &Date) -> &[String] {
+    self
+      .messages
+      .get(date)
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+  }
+
+  /// Sorted, normalized names of all classes on the plan.
+  pub fn classes(&self) -> Vec<String> {
+    self
+      .rows
+      .iter()
+      .flat_map(|row| row.class.iter())
+      .map(|class| normalize_class(class))
+      .filter(|class| !class.is_empty())
+      .collect::<BTreeSet<String>>()
+      .into_iter()
+      .collect()
+  }
+
+  /// Sorted dates for which the plan has rows.
+  pub fn dates(&self) -> Vec<Date> {
+    self
+      .rows
+      .iter()
+      .map(|row| row.date)
+      .collect::<BTreeSet<Date>>()
+      .into_iter()
+      .collect()
+  }
+
+  /// Dates with at least one row for any of the classes.
+  pub fn dates_for(&self, classes: &[&str]) -> Vec<Date> {
+    self
+      .rows
+      .iter()
+      .filter(|row| classes.iter().any(|class| row.is_for_class(class)))
+      .map(|row| row.date)
+      .collect::<BTreeSet<Date>>()
+      .into_iter()
+      .collect()
+  }
+
+  /// Order independent hash of all rows, stable across restarts of the same build.
+  pub fn rows_hash(&self) -> u64 {
+    self
+      .rows
+      .iter()
+      .map(|row| {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        hasher.finish()
+      })
+      .fold(0, u64::wrapping_add)
+  }
+}
+
 impl Davinci {
   pub fn new(entrypoint: Url, username: String, password: String) -> Self {
-    Self {
-      client: Client::new(),
-      username,
-      password,
-      entrypoint,
-      data: RwLock::new(None),
-    }
+    Self::builder(username, password)
+      .entrypoint(entrypoint.into())
+      .build()
+  }
+
+  pub fn builder(username: String, password: String) -> DavinciBuilder {
+    DavinciBuilder::new(username, password)
+  }
+
+  /// Swaps the credentials, e.g. after the secrets were rotated.
+  pub fn set_username(&self, username: String) {
+    *self.username.write().unwrap() = username;
+  }
+
+  pub fn set_password(&self, password: String) {
+    *self.password.write().unwrap() = password;
+  }
+
+  fn get(&self, url: Url) -> RequestBuilder {
+    self
+      .client
+      .get(url)
+      .headers(self.headers.clone())
+      .basic_auth(
+        self.username.read().unwrap().clone(),
+        Some(self.password.read().unwrap().clone()),
+      )
+  }
+
+  pub fn timetables(&self) -> &TimetableRegistry {
+    &self.timetables
+  }
+
+  /// Downloads a file protected by the same credentials as the plan.
+  pub async fn download(&self, url: Url) -> anyhow::Result<Vec<u8>> {
+    Ok(
+      self
+        .get(url)
+        .send()
+        .await?
+        .check_status()?
+        .bytes()
+        .await?
+        .to_vec(),
+    )
   }
 
   pub async fn data(&self) -> RwLockReadGuard<'_, Option<Data>> {
     self.data.read().await
   }
 
+  /// Timetable of the class with all changes applied, `None` if the class is unknown.
   pub async fn get_applied_timetable(
     &self,
     date: Date,
-  ) -> anyhow::Result<(Option<OffsetDateTime>, Vec<Lesson>, Vec<Row>, u8)> {
+    class: &str,
+  ) -> anyhow::Result<Option<(Option<OffsetDateTime>, Vec<Lesson>, Vec<Row>, u8)>> {
+    let data = self.data.read().await;
+    let (last_modified, rows) = match data.as_ref() {
+      Some(data) => (data.last_modified, Some(&data.rows)),
+      None => (None, None),
+    };
+
+    Ok(
+      self
+        .apply(date, class, rows)?
+        .map(|(day, relevant_rows, iteration)| (last_modified, day, relevant_rows, iteration)),
+    )
+  }
+
+  /// Timetable of the class with the given rows applied instead of the current
+  /// plan, e.g. rows archived for a past date.
+  pub fn get_applied_timetable_from(
+    &self,
+    date: Date,
+    class: &str,
+    rows: &HashSet<Row>,
+  ) -> anyhow::Result<Option<AppliedDay>> {
+    self.apply(date, class, Some(rows))
+  }
+
+  fn apply(
+    &self,
+    date: Date,
+    class: &str,
+    rows: Option<&HashSet<Row>>,
+  ) -> anyhow::Result<Option<AppliedDay>> {
+    let Some(timetable) = self.timetables.get(class) else {
+      return Ok(None);
+    };
+
     let iteration =
       get_iteration(date).ok_or_else(|| anyhow!("Unable to find iteration for date {date}"))?;
 
-    let mut day = IGD21
+    let mut day = timetable
       .get(&date.weekday())
-      .unwrap()
+      .map(Vec::as_slice)
+      .unwrap_or_default()
       .iter()
       .filter_map(|lesson| {
         if let Some(l_iteration) = lesson.iteration {
@@ -82,86 +336,172 @@ impl Davinci {
       })
       .collect::<Vec<Lesson>>();
 
-    let mut relevant_rows = Vec::new();
+    let relevant_rows = match rows {
+      Some(rows) => apply_changes(rows, &date, class, &mut day),
+      None => Vec::new(),
+    };
 
-    let mut last_modified = None;
-    if let Some(data) = self.data.read().await.as_ref() {
-      last_modified = data.last_modified;
-
-      // first ally all cancel
-      // sometimes there is a cancel and than a replacement for the canceled lesson
-      for row in &data.rows {
-        if let Change::Cancel { .. } = row.change {
-          if apply_change(&date, &mut day, &mut relevant_rows, row) {
-            continue;
-          }
-        }
-      }
+    Ok(Some((day, relevant_rows, iteration)))
+  }
 
-      // alter that apply all other changes
-      for row in &data.rows {
-        if let Change::Cancel { .. } = row.change {
-          continue;
+  pub async fn get_html(
+    &self,
+    date: &Date,
+    classes: &[&str],
+    options: PlanOptions,
+  ) -> anyhow::Result<Option<String>> {
+    Ok(match self.data.read().await.as_ref() {
+      None => None,
+      Some(data) => Some(
+        SubstitutionPlanTemplate {
+          date: *date,
+          iteration: get_iteration(*date),
+          last_modified: data.last_modified_of(date),
+          table: data.table(date),
+          messages: data.messages_of(date),
+          classes,
+          options,
+          dates: data.dates(),
         }
+        .render_once()?,
+      ),
+    })
+  }
 
-        if apply_change(&date, &mut day, &mut relevant_rows, row) {
-          continue;
-        }
+  /// [`Davinci::get_applied_timetable`] as html, `None` if the class is unknown.
+  pub async fn get_applied_html(
+    &self,
+    date: Date,
+    class: &str,
+    theme: Theme,
+  ) -> anyhow::Result<Option<String>> {
+    let Some((_, lessons, _, iteration)) = self.get_applied_timetable(date, class).await? else {
+      return Ok(None);
+    };
+
+    Ok(Some(
+      AppliedTimetableTemplate {
+        date,
+        class,
+        iteration,
+        lessons: &lessons,
+        theme,
       }
+      .render_once()?,
+    ))
+  }
+
+  /// Monday to friday of the week of the date, `None` if the class is unknown.
+  /// Days without a known iteration stay empty.
+  pub async fn get_week(&self, date: Date, class: &str) -> Option<Vec<WeekDay>> {
+    let monday = date - time::Duration::days(date.weekday().number_days_from_monday() as i64);
+
+    let mut days = Vec::new();
+    for offset in 0..5 {
+      let date = monday + time::Duration::days(offset);
+      days.push(match self.get_applied_timetable(date, class).await {
+        Ok(Some((_, lessons, _, iteration))) => WeekDay {
+          date,
+          iteration: Some(iteration),
+          lessons,
+        },
+        Ok(None) => return None,
+        Err(_) => WeekDay {
+          date,
+          iteration: None,
+          lessons: Vec::new(),
+        },
+      });
     }
 
-    Ok((last_modified, day, relevant_rows, iteration))
+    Some(days)
   }
 
-  pub async fn get_html(&self, date: &Date, classes: &[&str]) -> anyhow::Result<Option<String>> {
-    Ok(match self.data.read().await.as_ref() {
-      None => None,
-      Some(data) => {
-        let mut table = data
-          .rows
-          .iter()
-          .filter(|row| &row.date == date)
-          .collect::<Vec<&Row>>();
-
-        table.sort_by(|a, b| a.index.cmp(&b.index));
+  /// [`Davinci::get_week`] as html, `None` if the class is unknown.
+  pub async fn get_week_html(
+    &self,
+    date: Date,
+    class: &str,
+    theme: Theme,
+  ) -> anyhow::Result<Option<String>> {
+    let Some(days) = self.get_week(date, class).await else {
+      return Ok(None);
+    };
 
-        let table = table
-          .iter()
-          .map(|row| row.raw.as_slice())
-          .collect::<Vec<&[String]>>();
-
-        Some(
-          SubstitutionPlanTemplate {
-            date: *date,
-            table,
-            classes,
-          }
-          .render_once()?,
-        )
+    Ok(Some(
+      WeekTemplate {
+        class,
+        days: &days,
+        theme,
       }
+      .render_once()?,
+    ))
+  }
+
+  /// Same content as [`Davinci::get_html`], for rendering without a browser.
+  pub async fn get_plan_view(
+    &self,
+    date: &Date,
+    classes: &[&str],
+    collapse: bool,
+  ) -> Option<PlanView> {
+    self.data.read().await.as_ref().map(|data| {
+      PlanView::new(
+        *date,
+        &data.table(date),
+        data.messages_of(date),
+        classes,
+        collapse,
+        data.last_modified_of(date),
+      )
     })
   }
 
+  /// Rows added or removed after the given time, `None` if that is longer ago
+  /// than remembered, e.g. before the start of the process.
+  pub async fn changes_since(&self, since: OffsetDateTime) -> Option<RowChanges> {
+    self.changes.lock().await.since(since)
+  }
+
   pub async fn update(&self) -> anyhow::Result<bool> {
-    let mut start_url = self.entrypoint.clone();
     let mut rows = Vec::new();
     let mut last_modified = None;
+    let mut last_modified_by_date = BTreeMap::new();
+    let mut messages = BTreeMap::new();
 
-    loop {
-      match self.fetch(start_url, &mut rows).await? {
-        None => break,
-        Some((curr_last_modified, next)) => {
-          if let Some(last_last_modified) = last_modified {
-            if last_last_modified < curr_last_modified {
-              last_modified = Some(curr_last_modified);
-            }
-          } else {
-            last_modified = Some(curr_last_modified);
-          }
+    let Crawl { pages, failed } = self.crawl().await?;
 
-          start_url = next
+    for (page, page_rows) in pages {
+      let Page {
+        date,
+        last_modified: curr_last_modified,
+        messages: curr_messages,
+        ..
+      } = page;
+
+      rows.extend(page_rows);
+
+      // pages without a timestamp are unknown, the others are still useful
+      if let Some(curr_last_modified) = curr_last_modified {
+        if last_modified.is_none_or(|last_modified| last_modified < curr_last_modified) {
+          last_modified = Some(curr_last_modified);
         }
-      };
+
+        // multiple plan groups might have a page for the same date
+        let by_date = last_modified_by_date
+          .entry(date)
+          .or_insert(curr_last_modified);
+        if *by_date < curr_last_modified {
+          *by_date = curr_last_modified;
+        }
+      }
+
+      if !curr_messages.is_empty() {
+        messages
+          .entry(date)
+          .or_insert_with(Vec::new)
+          .extend(curr_messages);
+      }
     }
 
     let now = OffsetDateTime::now_utc();
@@ -173,76 +513,325 @@ impl Davinci {
       hash.insert(row);
     }
 
+    // rows of unavailable groups weren't removed, they are kept until the
+    // next successful crawl instead of being announced again
+    if let Some(data) = data.as_ref() {
+      hash.extend(
+        data
+          .rows
+          .iter()
+          .filter(|row| failed.contains(&row.group))
+          .cloned(),
+      );
+    }
+
     // check if there is a difference
     if let Some(data) = data.as_mut() {
       // if !hash.iter().zip(&data.rows).any(|(a, b)| a != b) {
       if hash == data.rows {
         data.last_checked = now;
+        data.messages = messages;
         return Ok(false);
       }
     }
 
+    self.changes.lock().await.record(
+      now,
+      data
+        .as_ref()
+        .map(|data| &data.rows)
+        .unwrap_or(&HashSet::new()),
+      &hash,
+    );
+
     *data = Some(Data {
       last_checked: now,
       last_modified,
+      last_modified_by_date,
+      messages,
       rows: hash,
     });
 
     Ok(true)
   }
 
-  async fn fetch(
-    &self,
-    url: Url,
-    rows: &mut Vec<Row>,
-  ) -> anyhow::Result<Option<(OffsetDateTime, Url)>> {
-    let response = self
-      .client
-      .get(url.clone())
-      .basic_auth(&self.username, Some(&self.password))
-      .send()
-      .await?
-      .error_for_status()?;
-
-    let last_modified = match response.headers().get(LAST_MODIFIED) {
-      None => return Err(anyhow!("last-modified http header is required")),
-      Some(value) => OffsetDateTime::parse(value.to_str()?, &Rfc2822)?,
+  /// Pages of all entrypoints, in order of the entrypoints. An unavailable
+  /// entrypoint is skipped, so the plans of the other groups stay up to date.
+  async fn crawl(&self) -> anyhow::Result<Crawl> {
+    if self.entrypoints.is_empty() {
+      return Err(anyhow!("No entrypoint configured"));
+    }
+
+    let mut pages = Vec::new();
+    let mut failed = Vec::new();
+    let mut last_err = None;
+
+    for entrypoint in &self.entrypoints {
+      match self.crawl_entrypoint(&entrypoint.url).await {
+        Ok(crawled) => pages.extend(crawled.into_iter().map(|(page, mut rows)| {
+          for row in &mut rows {
+            row.group.clone_from(&entrypoint.group);
+          }
+          (page, rows)
+        })),
+        Err(err) => {
+          let err = err.context(format!("Unable to crawl {entrypoint}"));
+          warn!("{:#}", err);
+          failed.push(entrypoint.group.clone());
+          last_err = Some(err);
+        }
+      }
+    }
+
+    match last_err {
+      Some(err) if failed.len() == self.entrypoints.len() => Err(err),
+      _ => Ok(Crawl { pages, failed }),
+    }
+  }
+
+  /// Pages in order of the chain, once the chain is known its pages are
+  /// fetched concurrently.
+  async fn crawl_entrypoint(&self, entrypoint: &Url) -> anyhow::Result<Vec<(Page, Vec<Row>)>> {
+    if entrypoint.path().ends_with('/') {
+      return self.crawl_index(entrypoint).await;
+    }
+
+    let chain = self
+      .chains
+      .lock()
+      .await
+      .get(entrypoint)
+      .cloned()
+      .unwrap_or_default();
+
+    if chain.len() > 1 {
+      let pages = stream::iter(chain.iter().cloned())
+        .map(|url| self.fetch(url))
+        .buffered(CONCURRENT_FETCHES)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+      // still valid if each page links to the next one
+      let valid = pages
+        .iter()
+        .map(|(page, _)| page.next.as_ref())
+        .eq(chain.iter().skip(1).map(Some).chain(once(None)));
+
+      if valid {
+        return Ok(pages);
+      }
+
+      info!("Page chain changed, crawling sequentially...");
+    }
+
+    let mut url = entrypoint.clone();
+    let mut chain = Vec::new();
+    let mut pages = Vec::new();
+
+    loop {
+      chain.push(url.clone());
+      let (page, rows) = self.fetch(url).await?;
+      let next = page.next.clone();
+      pages.push((page, rows));
+
+      match next {
+        None => break,
+        Some(next) => url = next,
+      }
+    }
+
+    self.chains.lock().await.insert(entrypoint.clone(), chain);
+
+    Ok(pages)
+  }
+
+  /// Fetches all plan documents linked from the directory index given as
+  /// entrypoint, so renamed or added documents are picked up.
+  async fn crawl_index(&self, index: &Url) -> anyhow::Result<Vec<(Page, Vec<Row>)>> {
+    let text = match &self.replay {
+      Some(replay) => replay.load(index).await?.0,
+      None => {
+        let text = self
+          .get(index.clone())
+          .send()
+          .await?
+          .check_status()?
+          .text()
+          .await?;
+        self.archive(index, &text).await;
+        text
+      }
     };
+    let urls = extract_plan_links(&Document::from(text.as_str()), index);
 
-    info!("Crawled {}, last modified {}", url, last_modified);
+    if urls.is_empty() {
+      return Err(anyhow!("No plan documents found at {}", index));
+    }
 
-    let text = response.text().await?;
-    let doc = Document::from(text.as_str());
+    info!("Discovered {} plan documents at {}", urls.len(), index);
 
-    let date = extract_date(&doc)?;
+    stream::iter(urls)
+      .map(|url| self.fetch(url))
+      .buffered(CONCURRENT_FETCHES)
+      .try_collect()
+      .await
+  }
 
-    let table = extract_html_table(&doc);
-    parse(table, &date, rows)?;
+  async fn archive(&self, url: &Url, text: &str) {
+    if let Some(archive) = &self.archive {
+      match archive.store(url, OffsetDateTime::now_utc(), text).await {
+        Ok(path) => info!("Archived {} to {}", url, path.display()),
+        Err(err) => warn!("Unable to archive {}: {}", url, err),
+      }
+    }
+  }
 
-    Ok(match extract_next_page(&doc) {
-      None => None,
-      Some(next) => {
-        let next = url.join(next)?;
-        if next == url {
-          None
-        } else {
-          Some((last_modified, next))
-        }
+  async fn fetch(&self, url: Url) -> anyhow::Result<(Page, Vec<Row>)> {
+    if let Some(replay) = &self.replay {
+      let (text, crawled) = replay.load(&url).await?;
+      info!("Replayed {}, crawled {}", url, crawled);
+      return parse_page(&url, &text, Some(crawled));
+    }
+
+    let mut request = self.get(url.clone());
+
+    if let Some(cached) = self.pages.lock().await.get(&url) {
+      if let Some(last_modified) = &cached.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified.clone());
       }
-    })
+      if let Some(etag) = &cached.etag {
+        request = request.header(IF_NONE_MATCH, etag.clone());
+      }
+    }
+
+    let response = request.send().await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+      if let Some(cached) = self.pages.lock().await.get(&url) {
+        info!("Crawled {}, not modified", url);
+        return Ok((cached.page.clone(), cached.rows.clone()));
+      }
+    }
+
+    let response = response.check_status()?;
+
+    let raw_last_modified = response.headers().get(LAST_MODIFIED).cloned();
+    let last_modified = raw_last_modified.as_ref().and_then(|value| {
+      let parsed = value
+        .to_str()
+        .ok()
+        .and_then(|value| OffsetDateTime::parse(value, &Rfc2822).ok());
+      if parsed.is_none() {
+        warn!("Invalid last-modified header of {}: {:?}", url, value);
+      }
+      parsed
+    });
+    let etag = response.headers().get(ETAG).cloned();
+
+    match last_modified {
+      Some(last_modified) => info!("Crawled {}, last modified {}", url, last_modified),
+      None => warn!("Crawled {}, last modified unknown", url),
+    }
+
+    let text = response.text().await?;
+
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    let body_hash = hasher.finish();
+
+    if let Some(cached) = self.pages.lock().await.get_mut(&url) {
+      if cached.body_hash == body_hash {
+        // the next conditional request has to send the new validators
+        cached.last_modified = raw_last_modified;
+        cached.etag = etag;
+        cached.page.last_modified = last_modified;
+        return Ok((cached.page.clone(), cached.rows.clone()));
+      }
+    }
+
+    self.archive(&url, &text).await;
+
+    let (page, rows) = parse_page(&url, &text, last_modified)?;
+
+    self.pages.lock().await.insert(
+      url,
+      CachedPage {
+        last_modified: raw_last_modified,
+        etag,
+        body_hash,
+        page: page.clone(),
+        rows: rows.clone(),
+      },
+    );
+
+    Ok((page, rows))
   }
 }
 
-#[derive(Clone, Debug)]
+fn parse_page(
+  url: &Url,
+  text: &str,
+  last_modified: Option<OffsetDateTime>,
+) -> anyhow::Result<(Page, Vec<Row>)> {
+  let doc = Document::from(text);
+
+  let date = extract_date(&doc)?;
+  let messages = extract_messages(&doc);
+
+  let table = extract_html_table(&doc);
+  let mut rows = Vec::new();
+  parse(table, &date, &mut rows)?;
+
+  let next = match extract_next_page(&doc) {
+    None => None,
+    Some(next) => {
+      let next = url.join(next)?;
+      if &next == url {
+        None
+      } else {
+        Some(next)
+      }
+    }
+  };
+
+  let page = Page {
+    date,
+    last_modified,
+    messages,
+    next,
+  };
+
+  Ok((page, rows))
+}
+
+/// Crawled pages and the plan groups of the entrypoints that failed.
+struct Crawl {
+  pages: Vec<(Page, Vec<Row>)>,
+  failed: Vec<Option<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Row {
   /// IF YOU ADD PROPERTIES, UPDATE IMPLEMENTATIONS BELOW
   // ignored for Eq, PartialEq and Hash
   pub index: u8,
+  #[serde(with = "iso_date")]
   pub date: Date,
   pub class: Vec<String>,
   pub change: Change,
   // ignored for Eq, PartialEq and Hash
   pub raw: Vec<String>,
+  /// Plan group of the entrypoint the row was crawled from.
+  // ignored for Eq, PartialEq and Hash
+  #[serde(default)]
+  pub group: Option<String>,
+}
+
+impl Row {
+  pub fn is_for_class(&self, class: &str) -> bool {
+    let class = normalize_class(class);
+    self.class.iter().any(|name| normalize_class(name) == class)
+  }
 }
 
 impl Hash for Row {
@@ -261,15 +850,39 @@ impl PartialEq<Self> for Row {
 
 impl Eq for Row {}
 
+/// Applies all rows of the given class and date, returns the rows that
+/// couldn't be applied.
+fn apply_changes(rows: &HashSet<Row>, date: &Date, class: &str, day: &mut Vec<Lesson>) -> Vec<Row> {
+  let mut relevant_rows = Vec::new();
+
+  // first ally all cancel
+  // sometimes there is a cancel and than a replacement for the canceled lesson
+  for row in rows {
+    if let Change::Cancel { .. } = row.change {
+      apply_change(date, class, day, &mut relevant_rows, row);
+    }
+  }
+
+  // alter that apply all other changes
+  for row in rows {
+    if let Change::Cancel { .. } = row.change {
+      continue;
+    }
+
+    apply_change(date, class, day, &mut relevant_rows, row);
+  }
+
+  relevant_rows
+}
+
 fn apply_change(
   date: &Date,
+  class: &str,
   day: &mut Vec<Lesson>,
   relevant_rows: &mut Vec<Row>,
   row: &Row,
 ) -> bool {
-  if &row.date != date
-    || !(row.class.contains(&"IGD21".to_string()) || row.class.contains(&"IGD 21".to_string()))
-  {
+  if &row.date != date || !row.is_for_class(class) {
     return true;
   }
 
@@ -279,7 +892,7 @@ fn apply_change(
         return true;
       }
     }
-    Err(err) => error!("Could not apply row: {}", err),
+    Err(err) => unapplied::report(row, &err),
   }
 
   relevant_rows.push(row.clone());
@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+static TEACHER_NAMES: Lazy<RwLock<TeacherNames>> =
+  Lazy::new(|| RwLock::new(TeacherNames::default()));
+
+static WORD_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("\\p{L}+").unwrap());
+
+/// Full names of teachers by the abbreviation used on the plan, loaded from a
+/// TOML file like `Mül = "Frau Müller"`.
+#[derive(Default)]
+pub struct TeacherNames(HashMap<String, String>);
+
+impl TeacherNames {
+  pub fn load(path: &Path) -> anyhow::Result<Self> {
+    Ok(Self(toml::from_str(&std::fs::read_to_string(path)?)?))
+  }
+
+  /// Replaces the names used by the functions of this module.
+  pub fn install(self) {
+    *TEACHER_NAMES.write().unwrap() = self;
+  }
+
+  /// Full name of the teacher, unknown abbreviations are passed through unchanged.
+  pub fn name(&self, abbreviation: &str) -> String {
+    self
+      .0
+      .get(abbreviation)
+      .cloned()
+      .unwrap_or_else(|| abbreviation.to_string())
+  }
+
+  pub fn names(&self, abbreviations: &[String]) -> String {
+    abbreviations
+      .iter()
+      .map(|abbreviation| self.name(abbreviation))
+      .collect::<Vec<String>>()
+      .join(", ")
+  }
+
+  /// Replaces all known abbreviations in free text, e.g. the raw `+Mül (Sch)`.
+  pub fn replace(&self, text: &str) -> String {
+    if self.0.is_empty() {
+      return text.to_string();
+    }
+
+    WORD_REGEX
+      .replace_all(text, |captures: &Captures| self.name(&captures[0]))
+      .into_owned()
+  }
+}
+
+pub(crate) fn hash_installed(state: &mut impl Hasher) {
+  let names = TEACHER_NAMES.read().unwrap();
+  names.0.iter().collect::<BTreeMap<_, _>>().hash(state);
+}
+
+pub fn teacher_name(abbreviation: &str) -> String {
+  TEACHER_NAMES.read().unwrap().name(abbreviation)
+}
+
+pub fn teacher_names(abbreviations: &[String]) -> String {
+  TEACHER_NAMES.read().unwrap().names(abbreviations)
+}
+
+pub fn replace_teacher_names(text: &str) -> String {
+  TEACHER_NAMES.read().unwrap().replace(text)
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::HashMap;
+
+  use crate::teachers::TeacherNames;
+
+  #[test]
+  fn test_teacher_names() {
+    let names = TeacherNames(HashMap::from([(
+      "Lrz".to_string(),
+      "Frau Lorenz".to_string(),
+    )]));
+
+    assert_eq!(
+      "Frau Lorenz, Sch",
+      names.names(&["Lrz".to_string(), "Sch".to_string()])
+    );
+    assert_eq!("+Frau Lorenz (Sch)", names.replace("+Lrz (Sch)"));
+    assert_eq!("+Lrz (Sch)", TeacherNames::default().replace("+Lrz (Sch)"));
+  }
+}
@@ -0,0 +1,260 @@
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use pdf_extract::{Document, MediaBox, OutputDev, OutputError, Transform};
+use regex::Regex;
+
+use crate::timetable::registry::{LessonEntry, TimetableFile};
+use crate::timetable::Subject;
+
+static ROOM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^[A-Z]\\d{1,3}$").unwrap());
+
+const WEEKDAYS: [&str; 5] = ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag"];
+
+/// Converts the official PDF timetable of a class into the TOML format of the
+/// timetable registry. The layout is only guessed from text positions, so the
+/// result has to be reviewed before it is used.
+pub fn import_timetable_pdf(pdf: &[u8]) -> anyhow::Result<String> {
+  let doc = Document::load_mem(pdf)?;
+
+  let mut words = WordCollector::default();
+  pdf_extract::output_doc(&doc, &mut words)?;
+
+  Ok(toml::to_string(&build_timetable(words.words)?)?)
+}
+
+#[derive(Clone, Debug)]
+struct Word {
+  text: String,
+  x: f64,
+  y: f64,
+}
+
+#[derive(Default)]
+struct WordCollector {
+  words: Vec<Word>,
+  current: Option<Word>,
+}
+
+impl WordCollector {
+  fn finish_word(&mut self) {
+    if let Some(word) = self.current.take() {
+      if !word.text.trim().is_empty() {
+        self.words.push(Word {
+          text: word.text.trim().to_string(),
+          ..word
+        });
+      }
+    }
+  }
+}
+
+impl OutputDev for WordCollector {
+  fn begin_page(
+    &mut self,
+    _page_num: u32,
+    _media_box: &MediaBox,
+    _art_box: Option<(f64, f64, f64, f64)>,
+  ) -> Result<(), OutputError> {
+    Ok(())
+  }
+
+  fn end_page(&mut self) -> Result<(), OutputError> {
+    self.finish_word();
+    Ok(())
+  }
+
+  fn output_character(
+    &mut self,
+    trm: &Transform,
+    _width: f64,
+    _spacing: f64,
+    _font_size: f64,
+    char: &str,
+  ) -> Result<(), OutputError> {
+    if char.trim().is_empty() {
+      self.finish_word();
+      return Ok(());
+    }
+
+    self
+      .current
+      .get_or_insert_with(|| Word {
+        text: String::new(),
+        x: trm.m31,
+        y: trm.m32,
+      })
+      .text
+      .push_str(char);
+
+    Ok(())
+  }
+
+  fn begin_word(&mut self) -> Result<(), OutputError> {
+    Ok(())
+  }
+
+  fn end_word(&mut self) -> Result<(), OutputError> {
+    self.finish_word();
+    Ok(())
+  }
+
+  fn end_line(&mut self) -> Result<(), OutputError> {
+    self.finish_word();
+    Ok(())
+  }
+}
+
+fn build_timetable(words: Vec<Word>) -> anyhow::Result<TimetableFile> {
+  // columns are identified by the weekday headers
+  let mut columns = WEEKDAYS
+    .iter()
+    .enumerate()
+    .filter_map(|(weekday, name)| {
+      words
+        .iter()
+        .find(|word| word.text == *name)
+        .map(|word| (weekday, word.x))
+    })
+    .collect::<Vec<(usize, f64)>>();
+  columns.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+  let first_column = columns
+    .first()
+    .map(|(_, x)| *x)
+    .ok_or_else(|| anyhow!("No weekday headers found"))?;
+
+  // rows are identified by the lesson numbers left of the first weekday
+  let mut rows = words
+    .iter()
+    .filter(|word| word.x < first_column)
+    .filter_map(|word| {
+      word
+        .text
+        .trim_end_matches('.')
+        .parse::<u8>()
+        .ok()
+        .map(|lesson| (lesson, word.y))
+    })
+    .collect::<Vec<(u8, f64)>>();
+  rows.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+  if rows.is_empty() {
+    return Err(anyhow!("No lesson numbers found"));
+  }
+
+  // plans counting hours instead of blocks
+  let hours = rows.iter().any(|(lesson, _)| *lesson > 6);
+
+  let mut cells: Vec<((usize, u8), Vec<&Word>)> = Vec::new();
+
+  for word in words.iter().filter(|word| word.x >= first_column) {
+    let column = columns
+      .iter()
+      .min_by(|(_, a), (_, b)| (word.x - a).abs().total_cmp(&(word.x - b).abs()))
+      .map(|(weekday, _)| *weekday)
+      .unwrap();
+
+    // pdf coordinates grow upwards, a cell starts at its lesson number
+    let Some((lesson, _)) = rows
+      .iter()
+      .filter(|(_, y)| *y >= word.y - 1.0)
+      .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    else {
+      continue;
+    };
+
+    let lesson = if hours {
+      (lesson + lesson % 2) / 2
+    } else {
+      *lesson
+    };
+
+    match cells.iter_mut().find(|(key, _)| *key == (column, lesson)) {
+      Some((_, cell)) => cell.push(word),
+      None => cells.push(((column, lesson), vec![word])),
+    }
+  }
+
+  let mut timetable = TimetableFile::default();
+
+  for ((column, lesson), mut cell) in cells {
+    cell.sort_by(|a, b| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+
+    let day = match column {
+      0 => &mut timetable.monday,
+      1 => &mut timetable.tuesday,
+      2 => &mut timetable.wednesday,
+      3 => &mut timetable.thursday,
+      _ => &mut timetable.friday,
+    };
+
+    for word in cell {
+      if !matches!(Subject::from(word.text.as_str()), Subject::Other(_)) {
+        day.push(LessonEntry {
+          lesson,
+          subject: word.text.clone(),
+          place: None,
+          iteration: None,
+        });
+      } else if ROOM_REGEX.is_match(&word.text) {
+        if let Some(entry) = day
+          .last_mut()
+          .filter(|entry| entry.lesson == lesson && entry.place.is_none())
+        {
+          entry.place = Some(word.text.clone());
+        }
+      }
+    }
+  }
+
+  for day in [
+    &mut timetable.monday,
+    &mut timetable.tuesday,
+    &mut timetable.wednesday,
+    &mut timetable.thursday,
+    &mut timetable.friday,
+  ] {
+    day.sort_by_key(|entry| entry.lesson);
+  }
+
+  Ok(timetable)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::timetable::import::{build_timetable, Word};
+
+  fn word(text: &str, x: f64, y: f64) -> Word {
+    Word {
+      text: text.to_string(),
+      x,
+      y,
+    }
+  }
+
+  #[test]
+  fn test_build_timetable() -> anyhow::Result<()> {
+    let timetable = build_timetable(vec![
+      word("Montag", 100.0, 500.0),
+      word("Dienstag", 200.0, 500.0),
+      word("1", 10.0, 450.0),
+      word("2", 10.0, 400.0),
+      word("DEU", 100.0, 450.0),
+      word("B6", 100.0, 440.0),
+      word("Mül", 120.0, 440.0),
+      word("CH", 100.0, 400.0),
+      word("B9", 100.0, 390.0),
+      word("MA", 205.0, 400.0),
+    ])?;
+
+    assert_eq!(2, timetable.monday.len());
+    assert_eq!("DEU", timetable.monday[0].subject);
+    assert_eq!(Some("B6"), timetable.monday[0].place.as_deref());
+    assert_eq!(2, timetable.monday[1].lesson);
+    assert_eq!(Some("B9"), timetable.monday[1].place.as_deref());
+    assert_eq!("MA", timetable.tuesday[0].subject);
+    assert_eq!(2, timetable.tuesday[0].lesson);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::timetable::Subject;
+
+static SUBJECT_MAPPING: Lazy<RwLock<SubjectMapping>> =
+  Lazy::new(|| RwLock::new(SubjectMapping::default()));
+
+/// Extends the built-in subject codes, so new courses don't require a rebuild.
+///
+/// ```toml
+/// [subjects.DEU2]
+/// alias = "DEU"
+///
+/// [subjects.INF]
+/// name = "Inf"
+/// ```
+#[derive(Default, Deserialize)]
+pub struct SubjectMapping {
+  #[serde(default)]
+  subjects: HashMap<String, SubjectEntry>,
+}
+
+#[derive(Deserialize, Hash)]
+struct SubjectEntry {
+  /// Built-in code the subject is equivalent to.
+  alias: Option<String>,
+  /// Display name of a subject without built-in equivalent.
+  name: Option<String>,
+}
+
+impl SubjectMapping {
+  pub fn load(path: &Path) -> anyhow::Result<Self> {
+    Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+  }
+
+  /// Replaces the mapping used by `Subject::from` and `Subject`'s `Display` implementation.
+  pub fn install(self) {
+    *SUBJECT_MAPPING.write().unwrap() = self;
+  }
+
+  /// Subject of the code, aliases are resolved to their built-in code.
+  pub fn subject(&self, code: &str) -> Subject {
+    let alias = self
+      .subjects
+      .get(code)
+      .and_then(|entry| entry.alias.as_deref());
+
+    Subject::builtin(alias.unwrap_or(code))
+  }
+
+  /// Display name of a code without built-in equivalent.
+  pub fn name(&self, code: &str) -> Option<&str> {
+    self
+      .subjects
+      .get(code)
+      .and_then(|entry| entry.name.as_deref())
+  }
+}
+
+pub(crate) fn hash_installed(state: &mut impl Hasher) {
+  let mapping = SUBJECT_MAPPING.read().unwrap();
+  mapping
+    .subjects
+    .iter()
+    .collect::<BTreeMap<_, _>>()
+    .hash(state);
+}
+
+pub(crate) fn subject(code: &str) -> Subject {
+  SUBJECT_MAPPING.read().unwrap().subject(code)
+}
+
+pub(crate) fn display_name(code: &str) -> Option<String> {
+  SUBJECT_MAPPING
+    .read()
+    .unwrap()
+    .name(code)
+    .map(str::to_string)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::timetable::{Subject, SubjectMapping};
+
+  #[test]
+  fn test_subject_mapping() -> anyhow::Result<()> {
+    let mapping: SubjectMapping = toml::from_str(
+      r#"
+        [subjects.DEU2]
+        alias = "DEU"
+
+        [subjects.INF]
+        name = "Inf"
+      "#,
+    )?;
+
+    assert_eq!(Subject::GermanBasic, mapping.subject("DEU2"));
+    assert_eq!(Subject::GermanBasic, mapping.subject("DEU"));
+    assert_eq!(Subject::Other("INF".to_string()), mapping.subject("INF"));
+    assert_eq!(Some("Inf"), mapping.name("INF"));
+    assert_eq!(None, mapping.name("DEU2"));
+
+    Ok(())
+  }
+}
@@ -1,10 +1,21 @@
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter, Write};
 
+use serde::{Deserialize, Serialize};
 use time::Weekday;
 use tracing::warn;
 
+pub use import::import_timetable_pdf;
+pub(crate) use mapping::hash_installed as hash_installed_mapping;
+pub use mapping::SubjectMapping;
+pub use registry::*;
+pub use validate::{validate_timetable, Problem, Severity};
+
 pub mod igd21;
+mod import;
+mod mapping;
+mod registry;
+mod validate;
 
 #[derive(Clone, Debug)]
 pub struct Lesson {
@@ -12,14 +23,32 @@ pub struct Lesson {
   pub subject: Subject,
   pub iteration: Option<u8>,
   pub place: Option<String>,
+  /// Only known if a change of the substitution plan was applied.
+  pub teachers: Vec<String>,
   pub notice: Option<String>,
+  pub exam: bool,
+  /// What the applied change of the substitution plan did to the lesson.
+  /// Exams are only flagged by `exam`, the lesson takes place as planned.
+  pub change: Option<LessonChange>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LessonChange {
+  Cancel,
+  /// An additional lesson, e.g. moved from another day.
+  Moved,
+  /// Another subject takes place instead of the planned one.
+  Substitution,
+  Room,
+  /// The lesson takes place, only the teacher differs.
+  Teacher,
 }
 
 type Day = Vec<Lesson>;
 
-type Timetable = HashMap<Weekday, Day>;
+pub type Timetable = HashMap<Weekday, Day>;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Subject {
   GermanBasic,
   GermanAdvanced,
@@ -59,6 +88,13 @@ pub enum Subject {
 
 impl From<&str> for Subject {
   fn from(value: &str) -> Self {
+    mapping::subject(value)
+  }
+}
+
+impl Subject {
+  /// Subject of a built-in code, without looking at the [`SubjectMapping`].
+  fn builtin(value: &str) -> Self {
     match value {
       "DEU" => Self::GermanBasic,
       "LK-DEU" => Self::GermanAdvanced,
@@ -135,10 +171,13 @@ impl Display for Subject {
         Display::fmt(inner, f)?;
         f.write_char(')')
       }
-      Self::Other(other) => {
-        warn!("Unknown subject: {}", other);
-        f.write_str(other)
-      }
+      Self::Other(other) => match mapping::display_name(other) {
+        Some(name) => f.write_str(&name),
+        None => {
+          warn!("Unknown subject: {}", other);
+          f.write_str(other)
+        }
+      },
     }
   }
 }
@@ -150,7 +189,10 @@ impl Lesson {
       iteration,
       subject,
       place: Some(place.to_string()),
+      teachers: Vec::new(),
       notice: None,
+      exam: false,
+      change: None,
     }
   }
 }
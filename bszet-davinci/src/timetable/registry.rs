@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use time::Weekday;
+use tracing::info;
+
+use crate::class::normalize_class;
+use crate::timetable::igd21::IGD21;
+use crate::timetable::{Lesson, Subject, Timetable};
+
+/// Base timetables by (normalized) class name. Timetables loaded from a
+/// directory take precedence over the built-in ones and can be reloaded at
+/// runtime.
+pub struct TimetableRegistry {
+  timetables: RwLock<HashMap<String, Arc<Timetable>>>,
+  /// Counts the reloads, cached responses are outdated once it changed.
+  generation: AtomicU64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct TimetableFile {
+  #[serde(default)]
+  pub(crate) monday: Vec<LessonEntry>,
+  #[serde(default)]
+  pub(crate) tuesday: Vec<LessonEntry>,
+  #[serde(default)]
+  pub(crate) wednesday: Vec<LessonEntry>,
+  #[serde(default)]
+  pub(crate) thursday: Vec<LessonEntry>,
+  #[serde(default)]
+  pub(crate) friday: Vec<LessonEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LessonEntry {
+  pub(crate) lesson: u8,
+  /// Subject code as used on the substitution plan, e.g. `LK-MA`.
+  pub(crate) subject: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) place: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) iteration: Option<u8>,
+}
+
+impl TimetableRegistry {
+  pub fn builtin() -> Self {
+    Self {
+      timetables: RwLock::new(builtin()),
+      generation: AtomicU64::new(0),
+    }
+  }
+
+  pub fn generation(&self) -> u64 {
+    self.generation.load(Ordering::Relaxed)
+  }
+
+  pub fn get(&self, class: &str) -> Option<Arc<Timetable>> {
+    self
+      .timetables
+      .read()
+      .unwrap()
+      .get(&normalize_class(class))
+      .cloned()
+  }
+
+  /// Replaces the loaded timetables with all `<class>.toml` files of the directory.
+  pub fn load_dir(&self, dir: &Path) -> anyhow::Result<()> {
+    let mut timetables = builtin();
+
+    for entry in std::fs::read_dir(dir)? {
+      let path = entry?.path();
+
+      if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+        continue;
+      }
+
+      let class = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("Invalid timetable file name {}", path.display()))?;
+
+      let timetable = parse_timetable(&std::fs::read_to_string(&path)?)
+        .map_err(|err| anyhow!("Unable to parse timetable {}: {}", path.display(), err))?;
+
+      timetables.insert(normalize_class(class), Arc::new(timetable));
+    }
+
+    info!(
+      "Loaded timetables from {} for {} classes",
+      dir.display(),
+      timetables.len()
+    );
+    *self.timetables.write().unwrap() = timetables;
+    self.generation.fetch_add(1, Ordering::Relaxed);
+
+    Ok(())
+  }
+}
+
+fn builtin() -> HashMap<String, Arc<Timetable>> {
+  HashMap::from([("IGD21".to_string(), Arc::new(IGD21.clone()))])
+}
+
+pub(crate) fn parse_timetable(raw: &str) -> anyhow::Result<Timetable> {
+  let file: TimetableFile = toml::from_str(raw)?;
+
+  Ok(
+    [
+      (Weekday::Monday, file.monday),
+      (Weekday::Tuesday, file.tuesday),
+      (Weekday::Wednesday, file.wednesday),
+      (Weekday::Thursday, file.thursday),
+      (Weekday::Friday, file.friday),
+    ]
+    .into_iter()
+    .map(|(weekday, lessons)| {
+      (
+        weekday,
+        lessons
+          .into_iter()
+          .map(|entry| Lesson {
+            place: entry.place,
+            ..Lesson::new(
+              entry.lesson,
+              entry.iteration,
+              Subject::from(entry.subject.as_str()),
+              "",
+            )
+          })
+          .collect(),
+      )
+    })
+    .collect(),
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use time::Weekday;
+
+  use crate::timetable::registry::parse_timetable;
+  use crate::timetable::Subject;
+
+  #[test]
+  fn test_parse_timetable() -> anyhow::Result<()> {
+    let timetable = parse_timetable(
+      r#"
+        [[monday]]
+        lesson = 1
+        subject = "DEU"
+        place = "B6"
+
+        [[monday]]
+        lesson = 3
+        subject = "LK-MA"
+        iteration = 1
+      "#,
+    )?;
+
+    let monday = &timetable[&Weekday::Monday];
+    assert_eq!(2, monday.len());
+    assert_eq!(Subject::GermanBasic, monday[0].subject);
+    assert_eq!(Some("B6"), monday[0].place.as_deref());
+    assert_eq!(Some(1), monday[1].iteration);
+    assert_eq!(None, monday[1].place);
+    assert!(timetable[&Weekday::Friday].is_empty());
+
+    Ok(())
+  }
+}
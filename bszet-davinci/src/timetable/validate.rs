@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use time::Weekday;
+
+use crate::timetable::mapping::display_name;
+use crate::timetable::registry::TimetableFile;
+use crate::timetable::Subject;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+/// Something wrong with a single lesson of a timetable file.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+  pub severity: Severity,
+  pub weekday: Weekday,
+  pub lesson: u8,
+  pub message: String,
+}
+
+impl Display for Problem {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let severity = match self.severity {
+      Severity::Error => "error",
+      Severity::Warning => "warning",
+    };
+
+    write!(
+      f,
+      "{}: {}, block {}: {}",
+      severity, self.weekday, self.lesson, self.message
+    )
+  }
+}
+
+/// Checks a `<class>.toml` timetable for lessons sharing a slot without
+/// differing iterations, subject codes that are neither built in nor mapped
+/// and lessons without a room.
+pub fn validate_timetable(raw: &str) -> anyhow::Result<Vec<Problem>> {
+  let file: TimetableFile = toml::from_str(raw)?;
+  let mut problems = Vec::new();
+
+  for (weekday, lessons) in [
+    (Weekday::Monday, file.monday),
+    (Weekday::Tuesday, file.tuesday),
+    (Weekday::Wednesday, file.wednesday),
+    (Weekday::Thursday, file.thursday),
+    (Weekday::Friday, file.friday),
+  ] {
+    let mut slots = HashSet::new();
+
+    for entry in lessons {
+      let mut problem = |severity, message: String| {
+        problems.push(Problem {
+          severity,
+          weekday,
+          lesson: entry.lesson,
+          message,
+        })
+      };
+
+      if !slots.insert((entry.lesson, entry.iteration)) {
+        problem(
+          Severity::Error,
+          match entry.iteration {
+            Some(iteration) => format!("duplicate lesson in iteration {iteration}"),
+            None => "duplicate lesson without iteration".to_string(),
+          },
+        );
+      }
+
+      let subject = Subject::from(entry.subject.as_str());
+      if matches!(&subject, Subject::Other(code) if display_name(code).is_none()) {
+        problem(
+          Severity::Error,
+          format!("unknown subject {:?}", entry.subject),
+        );
+      }
+
+      let has_place = entry
+        .place
+        .as_deref()
+        .is_some_and(|place| !place.is_empty());
+      if !has_place && !matches!(subject, Subject::None | Subject::FaeVerb) {
+        problem(Severity::Warning, "missing room".to_string());
+      }
+    }
+  }
+
+  Ok(problems)
+}
+
+#[cfg(test)]
+mod test {
+  use time::Weekday;
+
+  use crate::timetable::{validate_timetable, Problem, Severity};
+
+  #[test]
+  fn test_validate_timetable() -> anyhow::Result<()> {
+    let problems = validate_timetable(
+      r#"
+        [[monday]]
+        lesson = 1
+        subject = "DEU"
+        place = "B6"
+
+        [[monday]]
+        lesson = 1
+        subject = "MA"
+        place = "B7"
+
+        [[monday]]
+        lesson = 2
+        subject = "ENG"
+        place = "B6"
+        iteration = 1
+
+        [[monday]]
+        lesson = 2
+        subject = "LK-ENG"
+        place = "B6"
+        iteration = 2
+
+        [[tuesday]]
+        lesson = 3
+        subject = "XYZ"
+      "#,
+    )?;
+
+    assert_eq!(
+      vec![
+        Problem {
+          severity: Severity::Error,
+          weekday: Weekday::Monday,
+          lesson: 1,
+          message: "duplicate lesson without iteration".to_string(),
+        },
+        Problem {
+          severity: Severity::Error,
+          weekday: Weekday::Tuesday,
+          lesson: 3,
+          message: "unknown subject \"XYZ\"".to_string(),
+        },
+        Problem {
+          severity: Severity::Warning,
+          weekday: Weekday::Tuesday,
+          lesson: 3,
+          message: "missing room".to_string(),
+        },
+      ],
+      problems
+    );
+    assert_eq!(
+      "error: Tuesday, block 3: unknown subject \"XYZ\"",
+      problems[1].to_string()
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,90 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use time::{Date, OffsetDateTime};
+use tracing::{debug, error, info};
+
+use crate::Row;
+
+static REPORTS: Lazy<Mutex<Reports>> = Lazy::new(|| Mutex::new(Reports::default()));
+
+/// The same rows are applied on every crawl, so errors are only reported once
+/// per day and row, with a count of the repetitions on the next day.
+#[derive(Default)]
+pub(crate) struct Reports {
+  day: Option<Date>,
+  counts: HashMap<u64, (String, u32)>,
+}
+
+impl Reports {
+  /// Returns `true` if the error wasn't reported today yet.
+  pub(crate) fn record(&mut self, row: &Row, err: &str, today: Date) -> bool {
+    if self.day != Some(today) {
+      for (err, count) in self.counts.values().filter(|(_, count)| *count > 1) {
+        info!("Could not apply row {} times: {}", count, err);
+      }
+
+      self.day = Some(today);
+      self.counts.clear();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    row.hash(&mut hasher);
+
+    let (_, count) = self
+      .counts
+      .entry(hasher.finish())
+      .or_insert_with(|| (err.to_string(), 0));
+    *count += 1;
+
+    *count == 1
+  }
+}
+
+pub(crate) fn report(row: &Row, err: &anyhow::Error) {
+  let err = err.to_string();
+  let today = OffsetDateTime::now_utc().date();
+
+  if REPORTS.lock().unwrap().record(row, &err, today) {
+    error!("Could not apply row: {}", err);
+  } else {
+    debug!("Could not apply row again: {}", err);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use time::macros::date;
+
+  use crate::change::Change;
+  use crate::timetable::Subject;
+  use crate::unapplied::Reports;
+  use crate::Row;
+
+  #[test]
+  fn test_reports() {
+    let row = Row {
+      index: 0,
+      date: date!(2023 - 11 - 06),
+      class: vec!["IGD21".to_string()],
+      change: Change::Other {
+        lesson: 1,
+        value: "Sondereinsatz".to_string(),
+        subject: Subject::Other("X".to_string()),
+        teachers: vec![],
+        place: String::new(),
+        notice: String::new(),
+      },
+      raw: vec![],
+      group: None,
+    };
+
+    let mut reports = Reports::default();
+    assert!(reports.record(&row, "unknown", date!(2023 - 11 - 06)));
+    assert!(!reports.record(&row, "unknown", date!(2023 - 11 - 06)));
+    assert!(reports.record(&row, "unknown", date!(2023 - 11 - 07)));
+  }
+}
@@ -0,0 +1,73 @@
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+use crate::limits::encode;
+
+/// Removes the margins around the content, the color of the top left pixel is
+/// taken as background. `padding` pixels of background are kept on each side.
+pub fn crop_to_content(png: &[u8], padding: u32) -> anyhow::Result<Vec<u8>> {
+  let image = image::load_from_memory_with_format(png, ImageFormat::Png)?;
+
+  let Some((left, top, right, bottom)) = content_bounds(&image) else {
+    return Ok(png.to_vec());
+  };
+
+  let left = left.saturating_sub(padding);
+  let top = top.saturating_sub(padding);
+  let right = (right + padding).min(image.width() - 1);
+  let bottom = (bottom + padding).min(image.height() - 1);
+
+  if (left, top, right, bottom) == (0, 0, image.width() - 1, image.height() - 1) {
+    return Ok(png.to_vec());
+  }
+
+  encode(&image.crop_imm(left, top, right - left + 1, bottom - top + 1))
+}
+
+/// Inclusive bounds of all pixels differing from the background.
+fn content_bounds(image: &DynamicImage) -> Option<(u32, u32, u32, u32)> {
+  let background = image.get_pixel(0, 0);
+  let mut bounds: Option<(u32, u32, u32, u32)> = None;
+
+  for (x, y, pixel) in image.pixels() {
+    if pixel == background {
+      continue;
+    }
+
+    bounds = Some(match bounds {
+      None => (x, y, x, y),
+      Some((left, top, right, bottom)) => (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+    });
+  }
+
+  bounds
+}
+
+#[cfg(test)]
+mod test {
+  use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+  use crate::crop::crop_to_content;
+  use crate::limits::encode;
+
+  #[test]
+  fn test_crop_to_content() -> anyhow::Result<()> {
+    let mut image = RgbImage::from_pixel(100, 80, Rgb([0xff, 0xff, 0xff]));
+    for x in 30..40 {
+      for y in 20..25 {
+        image.put_pixel(x, y, Rgb([0, 0, 0]));
+      }
+    }
+    let png = encode(&DynamicImage::ImageRgb8(image))?;
+
+    let cropped = image::load_from_memory(&crop_to_content(&png, 5)?)?;
+    assert_eq!((20, 15), cropped.dimensions());
+
+    let cropped = image::load_from_memory(&crop_to_content(&png, 100)?)?;
+    assert_eq!((100, 80), cropped.dimensions());
+
+    let blank = encode(&DynamicImage::new_rgb8(10, 10))?;
+    assert_eq!(blank, crop_to_content(&blank, 0)?);
+
+    Ok(())
+  }
+}
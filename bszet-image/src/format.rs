@@ -0,0 +1,88 @@
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageFormat};
+
+use crate::limits::encode;
+
+/// Encodings of the images returned by the pipeline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+  #[default]
+  Png,
+  /// Lossless, usually a good deal smaller than png.
+  WebP,
+}
+
+impl OutputFormat {
+  /// Detects the format of an encoded image.
+  pub fn guess(image: &[u8]) -> anyhow::Result<Self> {
+    match image::guess_format(image)? {
+      ImageFormat::Png => Ok(Self::Png),
+      ImageFormat::WebP => Ok(Self::WebP),
+      format => Err(anyhow!("Unsupported image format {:?}", format)),
+    }
+  }
+
+  pub fn mime(&self) -> &'static str {
+    match self {
+      Self::Png => "image/png",
+      Self::WebP => "image/webp",
+    }
+  }
+
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Self::Png => "png",
+      Self::WebP => "webp",
+    }
+  }
+
+  pub(crate) fn encode(&self, image: &DynamicImage) -> anyhow::Result<Vec<u8>> {
+    match self {
+      Self::Png => encode(image),
+      Self::WebP => {
+        let mut out = Vec::new();
+        // the encoder only accepts 8 bit rgb(a)
+        DynamicImage::ImageRgba8(image.to_rgba8())
+          .write_with_encoder(WebPEncoder::new_lossless(Cursor::new(&mut out)))?;
+        Ok(out)
+      }
+    }
+  }
+}
+
+/// Re-encodes an image, returns it untouched if it already has the format.
+pub fn convert(image: &[u8], format: OutputFormat) -> anyhow::Result<Vec<u8>> {
+  if OutputFormat::guess(image)? == format {
+    return Ok(image.to_vec());
+  }
+
+  format.encode(&image::load_from_memory(image)?)
+}
+
+#[cfg(test)]
+mod test {
+  use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+
+  use crate::format::{convert, OutputFormat};
+  use crate::limits::encode;
+
+  #[test]
+  fn test_convert() -> anyhow::Result<()> {
+    let mut image = RgbImage::from_pixel(40, 30, Rgb([0xff, 0xff, 0xff]));
+    image.put_pixel(10, 10, Rgb([0xee, 0x67, 0x23]));
+    let png = encode(&DynamicImage::ImageRgb8(image))?;
+
+    let webp = convert(&png, OutputFormat::WebP)?;
+    assert_eq!(OutputFormat::WebP, OutputFormat::guess(&webp)?);
+    assert_eq!((40, 30), image::load_from_memory(&webp)?.dimensions());
+
+    let png = convert(&webp, OutputFormat::Png)?;
+    assert_eq!(OutputFormat::Png, OutputFormat::guess(&png)?);
+    assert_eq!(png, convert(&png, OutputFormat::Png)?);
+
+    Ok(())
+  }
+}
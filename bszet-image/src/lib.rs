@@ -1,37 +1,202 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use fantoccini::{Client, ClientBuilder, Locator};
 use hyper::client::HttpConnector;
+use image::{DynamicImage, Rgb, RgbImage};
+use serde_json::json;
+use webdriver::command::{PrintParameters, WebDriverCommand};
+
+pub use crop::crop_to_content;
+pub use format::{convert, OutputFormat};
+pub use limits::*;
+pub use native::*;
+pub use pool::WebDriverPool;
+pub use session::WebDriverSession;
+pub use stitch::stitch;
+
+mod crop;
+mod format;
+mod limits;
+mod native;
+mod pool;
+mod session;
+mod stitch;
+
+/// Browser chrome reducing the viewport of a resized window.
+const WINDOW_MARGIN: u32 = 200;
+
+/// Upper bound for the plan and its webfonts to appear after loading the page.
+const READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resolves once all webfonts of the page are loaded.
+const FONTS_READY: &str =
+  "const done = arguments[arguments.length - 1]; document.fonts.ready.then(() => done(true));";
+
+/// What to screenshot of a page, the defaults fit the substitution plan.
+#[derive(Clone, Debug)]
+pub struct Capture {
+  /// Initial window size, the height grows with the content.
+  pub width: u32,
+  pub height: u32,
+  /// Css selector of the element to screenshot.
+  pub selector: String,
+  /// Css selector of an element that is present once the page is rendered.
+  pub ready_selector: String,
+  /// Crops the screenshot to its content keeping this many pixels of margin.
+  pub padding: Option<u32>,
+  pub format: OutputFormat,
+}
+
+impl Default for Capture {
+  fn default() -> Self {
+    Self {
+      width: 1500,
+      height: 10_000,
+      selector: "body".to_string(),
+      ready_selector: "table".to_string(),
+      padding: Some(16),
+      format: OutputFormat::Png,
+    }
+  }
+}
 
 pub struct WebToImageConverter {
   client: Client,
 }
 
 impl WebToImageConverter {
-  pub async fn new(gecko_driver_url: &str) -> anyhow::Result<Self> {
-    let client = ClientBuilder::new(HttpConnector::new())
-      .connect(gecko_driver_url)
-      .await?;
+  /// Pages are rendered with `scale` device pixels per css pixel, e.g. `2.0`
+  /// for sharper screenshots.
+  pub async fn new(gecko_driver_url: &str, scale: f64) -> anyhow::Result<Self> {
+    let mut builder = ClientBuilder::new(HttpConnector::new());
+
+    if scale != 1.0 {
+      let capabilities = json!({
+        "moz:firefoxOptions": {
+          "prefs": { "layout.css.devPixelsPerPx": scale.to_string() }
+        }
+      });
+      if let serde_json::Value::Object(capabilities) = capabilities {
+        builder.capabilities(capabilities);
+      }
+    }
+
+    let client = builder.connect(gecko_driver_url).await?;
 
     Ok(Self { client })
   }
 
-  pub async fn create_image(&self, url: &str) -> anyhow::Result<Vec<u8>> {
-    self.client.set_window_rect(0, 0, 1500, 10_000).await?;
+  pub async fn create_image(&self, url: &str, capture: &Capture) -> anyhow::Result<Vec<u8>> {
+    self
+      .client
+      .set_window_rect(0, 0, capture.width, capture.height)
+      .await?;
     self.client.goto(url).await?;
+    self.wait_until_ready(&capture.ready_selector).await?;
+
+    // days with many rows don't fit, everything outside the window is cut off
+    let height = self.content_height().await?;
+    if height > capture.height {
+      self
+        .client
+        .set_window_rect(0, 0, capture.width, height + WINDOW_MARGIN)
+        .await?;
+    }
 
     let image = self
       .client
-      .find(Locator::Css("body"))
+      .find(Locator::Css(&capture.selector))
       .await?
       .screenshot()
       .await?;
 
-    Ok(image)
+    let image = match capture.padding {
+      Some(padding) => crop_to_content(&image, padding)?,
+      None => image,
+    };
+
+    convert(&image, capture.format)
+  }
+
+  async fn content_height(&self) -> anyhow::Result<u32> {
+    let height = self
+      .client
+      .execute("return document.documentElement.scrollHeight;", Vec::new())
+      .await?;
+
+    height
+      .as_u64()
+      .map(|height| height as u32)
+      .ok_or_else(|| anyhow!("WebDriver returned a non numeric height: {}", height))
+  }
+
+  /// Screenshots taken right after loading occasionally show a half rendered
+  /// page, so wait for the content and the fonts.
+  async fn wait_until_ready(&self, selector: &str) -> anyhow::Result<()> {
+    self
+      .client
+      .wait()
+      .at_most(READY_TIMEOUT)
+      .for_element(Locator::Css(selector))
+      .await
+      .map_err(|err| anyhow!("Plan didn't appear: {}", err))?;
+
+    self
+      .client
+      .execute_async(FONTS_READY, Vec::new())
+      .await
+      .map_err(|err| anyhow!("Fonts weren't loaded: {}", err))?;
+
+    Ok(())
+  }
+
+  pub async fn create_pdf(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+    self.client.goto(url).await?;
+    self
+      .wait_until_ready(&Capture::default().ready_selector)
+      .await?;
+
+    let pdf = self
+      .client
+      .issue_cmd(WebDriverCommand::Print(PrintParameters {
+        background: true,
+        ..PrintParameters::default()
+      }))
+      .await?;
+
+    let pdf = pdf
+      .as_str()
+      .ok_or_else(|| anyhow!("WebDriver returned a non string print result"))?;
+
+    Ok(STANDARD.decode(pdf)?)
   }
 
   pub async fn close(&self) -> anyhow::Result<()> {
     self.client.close_window().await?;
     Ok(())
   }
+
+  /// Whether the WebDriver still knows the session.
+  pub async fn is_alive(&self) -> bool {
+    self.client.current_url().await.is_ok()
+  }
+
+  /// Ends the session, errors are ignored as it might be gone already.
+  pub async fn quit(&self) {
+    let _ = self.client.clone().close().await;
+  }
+}
+
+/// Small gradient to check image uploads without a browser.
+pub fn sample_image() -> anyhow::Result<Vec<u8>> {
+  let image = RgbImage::from_fn(400, 200, |x, y| {
+    Rgb([(x * 255 / 400) as u8, (y * 255 / 200) as u8, 160])
+  });
+
+  encode(&DynamicImage::ImageRgb8(image))
 }
 
 #[cfg(test)]
@@ -39,7 +204,7 @@ mod test {
   use std::fs::File;
   use std::io::Write;
 
-  use crate::WebToImageConverter;
+  use crate::{Capture, WebToImageConverter};
 
   fn write_to_file(file_name: &str, data: &Vec<u8>) -> std::io::Result<()> {
     let mut file = File::create(file_name)?;
@@ -49,10 +214,14 @@ mod test {
 
   #[tokio::test]
   async fn open_selenium() -> anyhow::Result<()> {
-    let web_to_image_convert = WebToImageConverter::new("http://127.0.0.1:4444").await?;
+    let web_to_image_convert = WebToImageConverter::new("http://127.0.0.1:4444", 1.0).await?;
 
+    let capture = Capture {
+      ready_selector: "body".to_string(),
+      ..Capture::default()
+    };
     let image = web_to_image_convert
-      .create_image("https://www.google.com")
+      .create_image("https://www.google.com", &capture)
       .await;
     web_to_image_convert.close().await?;
     let image = image?;
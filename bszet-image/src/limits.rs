@@ -0,0 +1,122 @@
+use std::io::Cursor;
+
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::imageops::FilterType as ResizeFilter;
+use image::{DynamicImage, GenericImageView};
+
+use crate::format::OutputFormat;
+
+pub struct ImageLimits {
+  pub max_bytes: usize,
+  /// Maximum sum of width and height.
+  pub max_dimensions: u32,
+  /// Maximum ratio between the longer and the shorter side.
+  pub max_aspect_ratio: u32,
+}
+
+impl ImageLimits {
+  /// https://core.telegram.org/bots/api#sendphoto
+  pub const TELEGRAM_PHOTO: Self = Self {
+    max_bytes: 10 * 1024 * 1024,
+    max_dimensions: 10_000,
+    max_aspect_ratio: 20,
+  };
+}
+
+/// Splits overly tall images into tiles and downscales/recompresses them until
+/// every tile satisfies the given limits. Tiles keep the format of the image.
+pub fn fit_limits(encoded: &[u8], limits: &ImageLimits) -> anyhow::Result<Vec<Vec<u8>>> {
+  let format = OutputFormat::guess(encoded)?;
+  let image = image::load_from_memory(encoded)?;
+  let (width, height) = image.dimensions();
+
+  if encoded.len() <= limits.max_bytes
+    && width + height <= limits.max_dimensions
+    && height <= width * limits.max_aspect_ratio
+  {
+    return Ok(vec![encoded.to_vec()]);
+  }
+
+  let tile_height = (width * limits.max_aspect_ratio).max(1);
+
+  let mut tiles = Vec::new();
+  let mut y = 0;
+
+  while y < height {
+    let tile = image.crop_imm(0, y, width, tile_height.min(height - y));
+    tiles.push(shrink(tile, limits, format)?);
+    y += tile_height;
+  }
+
+  Ok(tiles)
+}
+
+fn shrink(
+  image: DynamicImage,
+  limits: &ImageLimits,
+  format: OutputFormat,
+) -> anyhow::Result<Vec<u8>> {
+  let (width, height) = image.dimensions();
+  let mut scale = (limits.max_dimensions as f64 / (width + height) as f64).min(1.0);
+
+  loop {
+    let resized = if scale < 1.0 {
+      image.resize(
+        ((width as f64 * scale) as u32).max(1),
+        ((height as f64 * scale) as u32).max(1),
+        ResizeFilter::Triangle,
+      )
+    } else {
+      image.clone()
+    };
+
+    let encoded = format.encode(&resized)?;
+
+    if encoded.len() <= limits.max_bytes || resized.width() <= 1 || resized.height() <= 1 {
+      return Ok(encoded);
+    }
+
+    scale *= 0.8;
+  }
+}
+
+pub(crate) fn encode(image: &DynamicImage) -> anyhow::Result<Vec<u8>> {
+  let mut out = Vec::new();
+  image.write_with_encoder(PngEncoder::new_with_quality(
+    Cursor::new(&mut out),
+    CompressionType::Best,
+    FilterType::Adaptive,
+  ))?;
+  Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+  use image::{DynamicImage, GenericImageView};
+
+  use crate::limits::{encode, fit_limits, ImageLimits};
+
+  #[test]
+  fn test_fit_limits() -> anyhow::Result<()> {
+    let png = encode(&DynamicImage::new_rgb8(100, 450))?;
+
+    let limits = ImageLimits {
+      max_bytes: usize::MAX,
+      max_dimensions: 100,
+      max_aspect_ratio: 2,
+    };
+
+    let tiles = fit_limits(&png, &limits)?;
+    assert_eq!(3, tiles.len());
+
+    for tile in tiles {
+      let (width, height) = image::load_from_memory(&tile)?.dimensions();
+      assert!(width + height <= limits.max_dimensions);
+      assert!(height <= width * limits.max_aspect_ratio);
+    }
+
+    assert_eq!(1, fit_limits(&png, &ImageLimits::TELEGRAM_PHOTO)?.len());
+
+    Ok(())
+  }
+}
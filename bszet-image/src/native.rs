@@ -0,0 +1,517 @@
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::limits::encode;
+
+const PADDING: u32 = 16;
+const CELL_PADDING_X: u32 = 10;
+const CELL_PADDING_Y: u32 = 4;
+const TEXT_SIZE: f32 = 38.0;
+const SMALL_SIZE: f32 = 19.0;
+const TITLE_SIZE: f32 = 64.0;
+const HEADING_SIZE: f32 = 50.0;
+
+const WHITE: Rgba<u8> = Rgba([0xff, 0xff, 0xff, 0xff]);
+const HEADER: Rgba<u8> = Rgba([0xee, 0x67, 0x23, 0xff]);
+
+/// Colors of the html plan, see the css of the template.
+struct Palette {
+  background: Rgba<u8>,
+  text: Rgba<u8>,
+  even: Rgba<u8>,
+  odd: Rgba<u8>,
+  selected_even: Rgba<u8>,
+  selected_odd: Rgba<u8>,
+  emphasized: Rgba<u8>,
+  footer: Rgba<u8>,
+}
+
+const LIGHT: Palette = Palette {
+  background: WHITE,
+  text: Rgba([0x00, 0x00, 0x00, 0xff]),
+  even: Rgba([0xf6, 0xf6, 0xf6, 0xff]),
+  odd: Rgba([0xe5, 0xe5, 0xe5, 0xff]),
+  selected_even: Rgba([0xff, 0xa9, 0x92, 0xff]),
+  selected_odd: Rgba([0xff, 0x81, 0x63, 0xff]),
+  emphasized: Rgba([0xb0, 0x00, 0x20, 0xff]),
+  footer: Rgba([0x75, 0x75, 0x75, 0xff]),
+};
+
+const DARK: Palette = Palette {
+  background: Rgba([0x12, 0x12, 0x12, 0xff]),
+  text: Rgba([0xe0, 0xe0, 0xe0, 0xff]),
+  even: Rgba([0x1e, 0x1e, 0x1e, 0xff]),
+  odd: Rgba([0x2a, 0x2a, 0x2a, 0xff]),
+  selected_even: Rgba([0x6b, 0x3a, 0x2a, 0xff]),
+  selected_odd: Rgba([0x80, 0x43, 0x2e, 0xff]),
+  emphasized: Rgba([0xff, 0x6e, 0x7f, 0xff]),
+  footer: Rgba([0x9e, 0x9e, 0x9e, 0xff]),
+};
+
+/// Content of a plan image, drawn like the html plan.
+pub struct Table {
+  pub title: String,
+  pub subtitle: Option<String>,
+  pub header: Vec<String>,
+  pub rows: Vec<TableRow>,
+  /// Listed below the table, e.g. the announcements of the day.
+  pub notes_title: String,
+  pub notes: Vec<String>,
+  /// Small print below the table, e.g. how many rows were left out.
+  pub footer: Option<String>,
+  pub dark: bool,
+}
+
+pub struct TableRow {
+  /// Lines after the first one of a cell are drawn smaller, e.g. the times of
+  /// a lesson.
+  pub cells: Vec<String>,
+  pub selected: bool,
+  /// Column drawn in red, e.g. the type of an exam.
+  pub emphasized: Option<usize>,
+}
+
+/// Draws plan images without a browser, only a TrueType/OpenType font is
+/// required.
+pub struct NativeRenderer {
+  font: FontVec,
+}
+
+impl NativeRenderer {
+  pub fn new(font: Vec<u8>) -> anyhow::Result<Self> {
+    Ok(Self {
+      font: FontVec::try_from_vec(font)?,
+    })
+  }
+
+  pub fn render(&self, table: &Table) -> anyhow::Result<Vec<u8>> {
+    let palette = match table.dark {
+      true => &DARK,
+      false => &LIGHT,
+    };
+    let Layout {
+      width,
+      height,
+      widths,
+      header_height,
+      heights,
+      subtitle_height,
+    } = Layout::new(table, self);
+    let table_width = widths.iter().sum::<u32>();
+
+    let mut image = RgbaImage::from_pixel(width, height, palette.background);
+    let mut y = PADDING;
+
+    self.draw_text(
+      &mut image,
+      &table.title,
+      PADDING,
+      y,
+      TITLE_SIZE,
+      palette.text,
+    );
+    y += self.line_height(TITLE_SIZE);
+
+    if let Some(subtitle) = &table.subtitle {
+      self.draw_text(&mut image, subtitle, PADDING, y, SMALL_SIZE, palette.text);
+      y += subtitle_height;
+    }
+    y += PADDING;
+
+    fill(&mut image, PADDING, y, table_width, header_height, HEADER);
+    self.draw_row(&mut image, &table.header, &widths, y, None, WHITE);
+    y += header_height;
+
+    for (index, (row, height)) in table.rows.iter().zip(heights).enumerate() {
+      // the header is the first row of the html table
+      let background = match (row.selected, index % 2 == 0) {
+        (false, true) => palette.even,
+        (false, false) => palette.odd,
+        (true, true) => palette.selected_even,
+        (true, false) => palette.selected_odd,
+      };
+
+      fill(&mut image, PADDING, y, table_width, height, background);
+      self.draw_row(
+        &mut image,
+        &row.cells,
+        &widths,
+        y,
+        row.emphasized.map(|column| (column, palette.emphasized)),
+        palette.text,
+      );
+      y += height;
+    }
+
+    if let Some(footer) = &table.footer {
+      y += PADDING / 2;
+      self.draw_text(&mut image, footer, PADDING, y, SMALL_SIZE, palette.footer);
+      y += self.line_height(SMALL_SIZE) + PADDING / 2;
+    }
+
+    if !table.notes.is_empty() {
+      y += PADDING;
+      self.draw_text(
+        &mut image,
+        &table.notes_title,
+        PADDING,
+        y,
+        HEADING_SIZE,
+        palette.text,
+      );
+      y += self.line_height(HEADING_SIZE);
+
+      for note in &table.notes {
+        self.draw_text(
+          &mut image,
+          &format!("• {note}"),
+          PADDING,
+          y,
+          TEXT_SIZE,
+          palette.text,
+        );
+        y += self.line_height(TEXT_SIZE);
+      }
+    }
+
+    encode(&DynamicImage::ImageRgba8(image))
+  }
+
+  fn draw_row(
+    &self,
+    image: &mut RgbaImage,
+    cells: &[String],
+    widths: &[u32],
+    y: u32,
+    emphasized: Option<(usize, Rgba<u8>)>,
+    color: Rgba<u8>,
+  ) {
+    let mut x = PADDING;
+
+    for (column, (cell, width)) in cells.iter().zip(widths).enumerate() {
+      let color = match emphasized {
+        Some((emphasized, color)) if emphasized == column => color,
+        _ => color,
+      };
+      let mut line_y = y + CELL_PADDING_Y;
+
+      for (index, line) in cell.lines().enumerate() {
+        let size = line_size(index);
+        // centered like the html table
+        let line_x = x + (width - self.text_width(line, size)) / 2;
+        self.draw_text(image, line, line_x, line_y, size, color);
+        line_y += self.line_height(size);
+      }
+
+      x += width;
+    }
+  }
+
+  fn draw_text(
+    &self,
+    image: &mut RgbaImage,
+    text: &str,
+    x: u32,
+    y: u32,
+    size: f32,
+    color: Rgba<u8>,
+  ) {
+    let font = self.font.as_scaled(PxScale::from(size));
+    let mut caret = point(x as f32, y as f32 + font.ascent());
+    let mut previous = None;
+
+    for char in text.chars() {
+      let glyph_id = font.glyph_id(char);
+      if let Some(previous) = previous {
+        caret.x += font.kern(previous, glyph_id);
+      }
+      previous = Some(glyph_id);
+
+      let glyph = glyph_id.with_scale_and_position(size, caret);
+      caret.x += font.h_advance(glyph_id);
+
+      let Some(outlined) = self.font.outline_glyph(glyph) else {
+        continue;
+      };
+      let bounds = outlined.px_bounds();
+
+      outlined.draw(|glyph_x, glyph_y, coverage| {
+        let x = bounds.min.x as i64 + glyph_x as i64;
+        let y = bounds.min.y as i64 + glyph_y as i64;
+        if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+          return;
+        }
+
+        let pixel = image.get_pixel_mut(x as u32, y as u32);
+        for channel in 0..3 {
+          pixel.0[channel] = blend(pixel.0[channel], color.0[channel], coverage);
+        }
+      });
+    }
+  }
+}
+
+/// Sizes of texts, measured with the font of the [`NativeRenderer`], so the
+/// layout can be computed without rasterising any glyphs.
+trait Metrics {
+  fn text_width(&self, text: &str, size: f32) -> u32;
+
+  fn line_height(&self, size: f32) -> u32;
+
+  fn cell_width(&self, cell: &str) -> u32 {
+    cell
+      .lines()
+      .enumerate()
+      .map(|(index, line)| self.text_width(line, line_size(index)))
+      .max()
+      .unwrap_or_default()
+  }
+
+  fn cell_height(&self, cells: &[String]) -> u32 {
+    let lines = cells
+      .iter()
+      .map(|cell| {
+        cell
+          .lines()
+          .enumerate()
+          .map(|(index, _)| self.line_height(line_size(index)))
+          .sum::<u32>()
+      })
+      .max()
+      .unwrap_or_default();
+
+    lines.max(self.line_height(TEXT_SIZE)) + 2 * CELL_PADDING_Y
+  }
+}
+
+impl Metrics for NativeRenderer {
+  fn text_width(&self, text: &str, size: f32) -> u32 {
+    let font = self.font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+
+    for char in text.chars() {
+      let glyph_id = font.glyph_id(char);
+      if let Some(previous) = previous {
+        width += font.kern(previous, glyph_id);
+      }
+      width += font.h_advance(glyph_id);
+      previous = Some(glyph_id);
+    }
+
+    width.ceil() as u32
+  }
+
+  fn line_height(&self, size: f32) -> u32 {
+    let font = self.font.as_scaled(PxScale::from(size));
+    (font.height() + font.line_gap()).ceil() as u32
+  }
+}
+
+/// Where the parts of a [`Table`] go, all in pixels.
+#[derive(Debug, PartialEq)]
+struct Layout {
+  width: u32,
+  height: u32,
+  /// Of each column, including the cell padding.
+  widths: Vec<u32>,
+  header_height: u32,
+  /// Of each row, including the cell padding.
+  heights: Vec<u32>,
+  subtitle_height: u32,
+}
+
+impl Layout {
+  fn new(table: &Table, metrics: &impl Metrics) -> Self {
+    let columns = table
+      .rows
+      .iter()
+      .map(|row| row.cells.len())
+      .chain([table.header.len()])
+      .max()
+      .unwrap_or_default();
+
+    let mut widths = vec![0; columns];
+    for cells in table
+      .rows
+      .iter()
+      .map(|row| &row.cells)
+      .chain([&table.header])
+    {
+      for (column, cell) in cells.iter().enumerate() {
+        widths[column] = widths[column].max(metrics.cell_width(cell) + 2 * CELL_PADDING_X);
+      }
+    }
+
+    let table_width = widths.iter().sum::<u32>();
+    let notes_width = table
+      .notes
+      .iter()
+      .map(|note| metrics.text_width(&format!("• {note}"), TEXT_SIZE))
+      .max()
+      .unwrap_or_default();
+    let footer_width = table
+      .footer
+      .as_ref()
+      .map(|footer| metrics.text_width(footer, SMALL_SIZE))
+      .unwrap_or_default();
+    let subtitle_width = table
+      .subtitle
+      .as_ref()
+      .map(|subtitle| metrics.text_width(subtitle, SMALL_SIZE))
+      .unwrap_or_default();
+    let width = table_width
+      .max(metrics.text_width(&table.title, TITLE_SIZE))
+      .max(subtitle_width)
+      .max(notes_width)
+      .max(footer_width)
+      + 2 * PADDING;
+
+    let header_height = metrics.cell_height(&table.header);
+    let heights = table
+      .rows
+      .iter()
+      .map(|row| metrics.cell_height(&row.cells))
+      .collect::<Vec<_>>();
+
+    let subtitle_height = table
+      .subtitle
+      .as_ref()
+      .map(|_| metrics.line_height(SMALL_SIZE))
+      .unwrap_or_default();
+    let mut height = PADDING
+      + metrics.line_height(TITLE_SIZE)
+      + subtitle_height
+      + PADDING
+      + header_height
+      + heights.iter().sum::<u32>()
+      + PADDING;
+    if table.footer.is_some() {
+      height += metrics.line_height(SMALL_SIZE);
+    }
+    if !table.notes.is_empty() {
+      height += metrics.line_height(HEADING_SIZE)
+        + table.notes.len() as u32 * metrics.line_height(TEXT_SIZE)
+        + PADDING;
+    }
+
+    Self {
+      width,
+      height,
+      widths,
+      header_height,
+      heights,
+      subtitle_height,
+    }
+  }
+}
+
+fn line_size(index: usize) -> f32 {
+  match index {
+    0 => TEXT_SIZE,
+    _ => SMALL_SIZE,
+  }
+}
+
+fn fill(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+  for y in y..(y + height).min(image.height()) {
+    for x in x..(x + width).min(image.width()) {
+      image.put_pixel(x, y, color);
+    }
+  }
+}
+
+fn blend(background: u8, foreground: u8, coverage: f32) -> u8 {
+  let coverage = coverage.clamp(0.0, 1.0);
+  (background as f32 * (1.0 - coverage) + foreground as f32 * coverage).round() as u8
+}
+
+#[cfg(test)]
+mod test {
+  use image::ImageFormat;
+
+  use crate::native::{Layout, Metrics, NativeRenderer, Table, TableRow};
+
+  /// Every char is 10 pixels wide, lines are as high as the font size.
+  struct Monospace;
+
+  impl Metrics for Monospace {
+    fn text_width(&self, text: &str, _size: f32) -> u32 {
+      text.chars().count() as u32 * 10
+    }
+
+    fn line_height(&self, size: f32) -> u32 {
+      size as u32
+    }
+  }
+
+  fn table() -> Table {
+    Table {
+      title: "Montag".to_string(),
+      subtitle: Some("Turnus 1".to_string()),
+      header: vec!["Klasse".to_string(), "Std.".to_string()],
+      rows: vec![TableRow {
+        cells: vec!["IGD21".to_string(), "1\n07:30".to_string()],
+        selected: true,
+        emphasized: None,
+      }],
+      notes_title: "Nachrichten zum Tag".to_string(),
+      notes: vec!["Sportfest".to_string()],
+      footer: Some("3 weitere".to_string()),
+      dark: false,
+    }
+  }
+
+  #[test]
+  fn test_layout() {
+    assert_eq!(
+      Layout {
+        width: 16 + 80 + 70 + 16,
+        height: 16 + 64 + 19 + 16 + 46 + 65 + 16 + 19 + 50 + 38 + 16,
+        widths: vec![60 + 20, 50 + 20],
+        header_height: 38 + 8,
+        // the times are drawn smaller below the lesson
+        heights: vec![38 + 19 + 8],
+        subtitle_height: 19,
+      },
+      Layout::new(&table(), &Monospace)
+    );
+
+    let mut table = table();
+    table.notes = vec!["Sportfest in der Turnhalle".to_string()];
+    table.footer = None;
+    table.subtitle = None;
+    let layout = Layout::new(&table, &Monospace);
+    // the notes are wider than the table
+    assert_eq!(16 + 280 + 16, layout.width);
+    assert_eq!(16 + 64 + 16 + 46 + 65 + 16 + 50 + 38 + 16, layout.height);
+  }
+
+  #[test]
+  #[ignore = "requires the DejaVu fonts of the system"]
+  fn test_render() -> anyhow::Result<()> {
+    let font = std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf")?;
+    let renderer = NativeRenderer::new(font)?;
+
+    let png = renderer.render(&Table {
+      title: "Montag der 06. Mai 2024".to_string(),
+      subtitle: Some("Turnus 1".to_string()),
+      header: vec!["Klasse".to_string(), "Std.".to_string()],
+      rows: vec![TableRow {
+        cells: vec!["IGD21".to_string(), "1\n07:30 - 08:15".to_string()],
+        selected: true,
+        emphasized: None,
+      }],
+      notes_title: "Nachrichten zum Tag".to_string(),
+      notes: vec!["Sportfest".to_string()],
+      footer: Some("3 weitere Einträge ausgeblendet".to_string()),
+      dark: false,
+    })?;
+
+    let image = image::load_from_memory_with_format(&png, ImageFormat::Png)?;
+    assert!(image.width() > 200);
+    assert!(image.height() > 200);
+
+    Ok(())
+  }
+}
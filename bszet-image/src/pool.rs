@@ -0,0 +1,99 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+
+use crate::{Capture, WebDriverSession};
+
+/// Renders with several WebDriver sessions in parallel, jobs wait for an idle
+/// session if all of them are busy.
+pub struct WebDriverPool {
+  sessions: Vec<WebDriverSession>,
+  idle: Mutex<Vec<usize>>,
+  permits: Semaphore,
+  /// Per job, including the time waiting for a session and all retries.
+  job_timeout: Duration,
+}
+
+/// Exclusive use of a session, returned to the pool on drop.
+struct Lease<'a> {
+  pool: &'a WebDriverPool,
+  index: usize,
+}
+
+impl Drop for Lease<'_> {
+  fn drop(&mut self) {
+    self.pool.idle.lock().unwrap().push(self.index);
+  }
+}
+
+impl WebDriverPool {
+  pub fn new(sessions: Vec<WebDriverSession>, job_timeout: Duration) -> Self {
+    Self {
+      idle: Mutex::new((0..sessions.len()).rev().collect()),
+      permits: Semaphore::new(sessions.len()),
+      sessions,
+      job_timeout,
+    }
+  }
+
+  pub async fn create_image(&self, url: &str, capture: &Capture) -> anyhow::Result<Vec<u8>> {
+    self.run(|session| session.create_image(url, capture)).await
+  }
+
+  pub async fn create_pdf(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+    self.run(|session| session.create_pdf(url)).await
+  }
+
+  /// Ends all sessions, only needed before exiting.
+  pub async fn quit(&self) {
+    for session in &self.sessions {
+      session.quit().await;
+    }
+  }
+
+  async fn run<'a, T, F, Fut>(&'a self, job: F) -> anyhow::Result<T>
+  where
+    F: FnOnce(&'a WebDriverSession) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+  {
+    timeout(self.job_timeout, async {
+      // a permit guarantees an idle session
+      let _permit = self.permits.acquire().await?;
+      let lease = Lease {
+        pool: self,
+        index: self.idle.lock().unwrap().pop().unwrap(),
+      };
+
+      job(&self.sessions[lease.index]).await
+    })
+    .await
+    .unwrap_or_else(|_| {
+      Err(anyhow!(
+        "Rendering didn't finish within {} seconds",
+        self.job_timeout.as_secs()
+      ))
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::Duration;
+
+  use crate::{WebDriverPool, WebDriverSession};
+
+  #[tokio::test]
+  async fn test_job_timeout() {
+    // nothing listens there, the session retries until the job times out
+    let session = WebDriverSession::new("http://127.0.0.1:9", 1.0, Duration::from_secs(60), 100);
+    let pool = WebDriverPool::new(vec![session], Duration::from_millis(50));
+
+    assert!(pool.create_pdf("about:blank").await.is_err());
+    // the session is idle again
+    assert_eq!(1, pool.idle.lock().unwrap().len());
+  }
+}
@@ -0,0 +1,110 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+use crate::{Capture, WebToImageConverter};
+
+/// Upper bound for ending a session, the WebDriver might be unresponsive.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// WebDriver session kept open across renders, as creating one takes a few
+/// seconds. It's re-created if the WebDriver lost it or an operation failed.
+pub struct WebDriverSession {
+  gecko_driver_url: String,
+  converter: Mutex<Option<Arc<WebToImageConverter>>>,
+  scale: f64,
+  /// Per attempt of an operation, including creating the session.
+  timeout: Duration,
+  retries: u32,
+}
+
+impl WebDriverSession {
+  pub fn new(gecko_driver_url: &str, scale: f64, timeout: Duration, retries: u32) -> Self {
+    Self {
+      gecko_driver_url: gecko_driver_url.to_string(),
+      converter: Mutex::new(None),
+      scale,
+      timeout,
+      retries,
+    }
+  }
+
+  async fn converter(&self) -> anyhow::Result<Arc<WebToImageConverter>> {
+    let mut converter = self.converter.lock().await;
+
+    if let Some(current) = converter.as_ref() {
+      if current.is_alive().await {
+        return Ok(current.clone());
+      }
+
+      warn!("WebDriver session is gone, creating a new one");
+      let _ = timeout(QUIT_TIMEOUT, current.quit()).await;
+    }
+
+    info!("Creating WebDriver session");
+    let created = Arc::new(WebToImageConverter::new(&self.gecko_driver_url, self.scale).await?);
+    *converter = Some(created.clone());
+
+    Ok(created)
+  }
+
+  /// Ends the session, the next operation starts a new one.
+  pub async fn quit(&self) {
+    if let Some(converter) = self.converter.lock().await.take() {
+      let _ = timeout(QUIT_TIMEOUT, converter.quit()).await;
+    }
+  }
+
+  pub async fn create_image(&self, url: &str, capture: &Capture) -> anyhow::Result<Vec<u8>> {
+    self
+      .run(|converter| async move { converter.create_image(url, capture).await })
+      .await
+  }
+
+  pub async fn create_pdf(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+    self
+      .run(|converter| async move { converter.create_pdf(url).await })
+      .await
+  }
+
+  /// Runs the operation with a timeout, failed attempts are retried with a
+  /// fresh session.
+  async fn run<T, F, Fut>(&self, operation: F) -> anyhow::Result<T>
+  where
+    F: Fn(Arc<WebToImageConverter>) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+  {
+    let mut attempt = 0;
+
+    loop {
+      let result = timeout(self.timeout, async {
+        operation(self.converter().await?).await
+      })
+      .await
+      .unwrap_or_else(|_| {
+        Err(anyhow!(
+          "WebDriver didn't respond within {} seconds",
+          self.timeout.as_secs()
+        ))
+      });
+
+      let err = match result {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+      };
+
+      self.quit().await;
+
+      if attempt >= self.retries {
+        return Err(err);
+      }
+      attempt += 1;
+      warn!("WebDriver operation failed, retrying: {}", err);
+    }
+  }
+}
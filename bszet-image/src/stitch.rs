@@ -0,0 +1,63 @@
+use image::imageops::overlay;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+
+use crate::format::OutputFormat;
+
+/// Stacks images vertically with `gap` pixels between them, in the format of
+/// the first image. Narrower images are left aligned, the space around them
+/// gets the background color of the first image.
+pub fn stitch(images: &[Vec<u8>], gap: u32) -> anyhow::Result<Vec<u8>> {
+  let [first, ..] = images else {
+    return Err(anyhow::anyhow!("Nothing to stitch"));
+  };
+  if images.len() == 1 {
+    return Ok(first.clone());
+  }
+
+  let format = OutputFormat::guess(first)?;
+  let decoded = images
+    .iter()
+    .map(|image| image::load_from_memory(image))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let width = decoded
+    .iter()
+    .map(|image| image.width())
+    .max()
+    .unwrap_or_default();
+  let height =
+    decoded.iter().map(|image| image.height()).sum::<u32>() + gap * (decoded.len() as u32 - 1);
+
+  let background = decoded[0].get_pixel(0, 0);
+  let mut stitched = RgbaImage::from_pixel(width, height, background);
+  let mut y = 0;
+
+  for image in &decoded {
+    overlay(&mut stitched, image, 0, y as i64);
+    y += image.height() + gap;
+  }
+
+  format.encode(&DynamicImage::ImageRgba8(stitched))
+}
+
+#[cfg(test)]
+mod test {
+  use image::{DynamicImage, GenericImageView};
+
+  use crate::limits::encode;
+  use crate::stitch::stitch;
+
+  #[test]
+  fn test_stitch() -> anyhow::Result<()> {
+    let a = encode(&DynamicImage::new_rgb8(100, 40))?;
+    let b = encode(&DynamicImage::new_rgb8(60, 30))?;
+
+    let stitched = image::load_from_memory(&stitch(&[a.clone(), b], 10)?)?;
+    assert_eq!((100, 80), stitched.dimensions());
+
+    assert_eq!(a, stitch(std::slice::from_ref(&a), 10)?);
+    assert!(stitch(&[], 10).is_err());
+
+    Ok(())
+  }
+}
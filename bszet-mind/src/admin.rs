@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use bszet_davinci::CredentialsRejected;
+use bszet_notify::telegram::Telegram;
+use time::{OffsetDateTime, Weekday};
+use tracing::{error, info};
+
+/// Sends operational alerts to a dedicated admin chat, separated from the
+/// student-facing chats.
+pub(crate) struct Admin {
+  chat_id: Option<i64>,
+  crawl_failure_threshold: u32,
+  crawl_failures: u32,
+  credentials_alerted: bool,
+  credentials_rejected: bool,
+  max_plan_age: Option<Duration>,
+  stale_alerted: bool,
+}
+
+impl Admin {
+  pub(crate) fn new(
+    chat_id: Option<i64>,
+    crawl_failure_threshold: u32,
+    max_plan_age: Option<Duration>,
+  ) -> Self {
+    Self {
+      chat_id,
+      crawl_failure_threshold,
+      crawl_failures: 0,
+      credentials_alerted: false,
+      credentials_rejected: false,
+      max_plan_age,
+      stale_alerted: false,
+    }
+  }
+
+  /// Applies reloaded settings, keeping the failure counters.
+  pub(crate) fn configure(
+    &mut self,
+    chat_id: Option<i64>,
+    crawl_failure_threshold: u32,
+    max_plan_age: Option<Duration>,
+  ) {
+    self.chat_id = chat_id;
+    self.crawl_failure_threshold = crawl_failure_threshold;
+    self.max_plan_age = max_plan_age;
+  }
+
+  pub(crate) async fn alert(&self, telegram: &Telegram, message: &str) {
+    let Some(chat_id) = self.chat_id else {
+      return;
+    };
+
+    info!("Sending admin alert: {}", message);
+
+    if let Err(err) = telegram.send_text(chat_id, message).await {
+      error!("Unable to send admin alert: {}", err);
+    }
+  }
+
+  pub(crate) async fn send_failed(&self, telegram: &Telegram, chat_id: i64, err: &anyhow::Error) {
+    error!("Unable to send notification to chat {}: {}", chat_id, err);
+
+    self
+      .alert(
+        telegram,
+        &format!("⚠️ Unable to send notification to chat {chat_id}:\n```\n{err}\n```"),
+      )
+      .await;
+  }
+
+  pub(crate) fn crawl_failures(&self) -> u32 {
+    self.crawl_failures
+  }
+
+  /// After too many failures in a row the upstream server is considered down,
+  /// crawling is reduced to a single probe per iteration until it recovers.
+  pub(crate) fn is_circuit_open(&self) -> bool {
+    self.crawl_failures >= self.crawl_failure_threshold
+  }
+
+  /// Whether the last crawl failed because of rejected credentials.
+  pub(crate) fn credentials_rejected(&self) -> bool {
+    self.credentials_rejected
+  }
+
+  pub(crate) async fn crawl_failed(&mut self, telegram: &Telegram, err: &anyhow::Error) {
+    self.crawl_failures += 1;
+    self.credentials_rejected = err.is::<CredentialsRejected>();
+
+    if let Some(rejected) = err.downcast_ref::<CredentialsRejected>() {
+      if !self.credentials_alerted {
+        self.credentials_alerted = true;
+        self
+          .alert(
+            telegram,
+            &format!(
+              "🔑 Credentials rejected by the substitution plan ({}), was the password rotated?",
+              rejected.0
+            ),
+          )
+          .await;
+      }
+      return;
+    }
+
+    if self.crawl_failures == self.crawl_failure_threshold {
+      self
+        .alert(
+          telegram,
+          &format!(
+            "⚠️ Crawling failed {} times in a row, only probing until it recovers:\n```\n{}\n```",
+            self.crawl_failures, err
+          ),
+        )
+        .await;
+    }
+  }
+
+  pub(crate) async fn crawl_succeeded(&mut self, telegram: &Telegram) {
+    if self.crawl_failures >= self.crawl_failure_threshold {
+      self
+        .alert(
+          telegram,
+          &format!(
+            "✅ Crawling recovered after {} failed attempts.",
+            self.crawl_failures
+          ),
+        )
+        .await;
+    }
+
+    self.crawl_failures = 0;
+    self.credentials_alerted = false;
+    self.credentials_rejected = false;
+  }
+
+  /// Warns once if the upstream plan wasn't modified for too long on a school
+  /// day, even though crawling itself succeeds.
+  pub(crate) async fn check_plan_age(
+    &mut self,
+    telegram: &Telegram,
+    last_modified: OffsetDateTime,
+    now: OffsetDateTime,
+  ) {
+    let Some(max_plan_age) = self.max_plan_age else {
+      return;
+    };
+
+    let age = (now - last_modified).unsigned_abs();
+
+    if age <= max_plan_age {
+      self.stale_alerted = false;
+      return;
+    }
+
+    if self.stale_alerted || matches!(now.weekday(), Weekday::Saturday | Weekday::Sunday) {
+      return;
+    }
+
+    self.stale_alerted = true;
+    self
+      .alert(
+        telegram,
+        &format!(
+          "⚠️ The substitution plan wasn't modified since {} hours, is the upstream page frozen?",
+          age.as_secs() / 3600
+        ),
+      )
+      .await;
+  }
+}
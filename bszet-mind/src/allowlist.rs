@@ -0,0 +1,49 @@
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use ipnet::IpNet;
+use tracing::warn;
+
+/// Network given in CIDR notation or as a single address.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Network(IpNet);
+
+impl FromStr for Network {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.parse::<IpAddr>() {
+      Ok(addr) => Ok(Self(addr.into())),
+      Err(_) => value
+        .parse::<IpNet>()
+        .map(Self)
+        .map_err(|err| format!("invalid network {value}: {err}")),
+    }
+  }
+}
+
+impl Network {
+  pub(crate) fn contains(&self, addr: IpAddr) -> bool {
+    self.0.contains(&addr)
+  }
+}
+
+/// Rejects requests from addresses outside of the allowed networks.
+pub(crate) async fn allowlist(
+  State(networks): State<Arc<Vec<Network>>>,
+  ConnectInfo(addr): ConnectInfo<SocketAddr>,
+  request: Request,
+  next: Next,
+) -> Response {
+  if !networks.iter().any(|network| network.contains(addr.ip())) {
+    warn!("Rejected internal request from {}", addr.ip());
+    return StatusCode::FORBIDDEN.into_response();
+  }
+
+  next.run(request).await
+}
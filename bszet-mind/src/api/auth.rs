@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{MatchedPath, Path, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use tracing::debug;
+
+use bszet_davinci::class::normalize_class;
+
+/// Static bearer token of the api, swapped when the token file is rotated.
+pub(crate) type ApiToken = Arc<RwLock<Option<String>>>;
+
+/// Validates tokens issued by an identity provider, as an alternative to the
+/// static api token.
+pub(crate) struct Jwt {
+  key: DecodingKey,
+  validation: Validation,
+}
+
+impl Jwt {
+  pub(crate) fn hs256(secret: &str) -> Self {
+    Self {
+      key: DecodingKey::from_secret(secret.as_bytes()),
+      validation: Validation::new(Algorithm::HS256),
+    }
+  }
+
+  pub(crate) fn rs256(pem: &[u8]) -> anyhow::Result<Self> {
+    Ok(Self {
+      key: DecodingKey::from_rsa_pem(pem)?,
+      validation: Validation::new(Algorithm::RS256),
+    })
+  }
+
+  pub(crate) fn with_claims(mut self, issuer: Option<&str>, audience: Option<&str>) -> Self {
+    if let Some(issuer) = issuer {
+      self.validation.set_issuer(&[issuer]);
+    }
+
+    match audience {
+      Some(audience) => self.validation.set_audience(&[audience]),
+      None => self.validation.validate_aud = false,
+    }
+
+    self
+  }
+
+  pub(crate) fn verify(&self, token: &str) -> bool {
+    match decode::<HashMap<String, serde_json::Value>>(token, &self.key, &self.validation) {
+      Ok(_) => true,
+      Err(err) => {
+        debug!("Rejected jwt: {}", err);
+        false
+      }
+    }
+  }
+}
+
+/// Token limited to reading the plan of a single class, written as
+/// `<class>=<token>` (e.g. `IGD21=secret`).
+#[derive(Clone, Debug)]
+pub(crate) struct ClassToken {
+  pub(crate) class: String,
+  pub(crate) token: String,
+}
+
+impl FromStr for ClassToken {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.split_once('=') {
+      Some((class, token)) if !class.trim().is_empty() && !token.trim().is_empty() => Ok(Self {
+        class: class.trim().to_string(),
+        token: token.trim().to_string(),
+      }),
+      _ => Err(format!("expected <class>=<token>, got {value}")),
+    }
+  }
+}
+
+/// What the bearer of a token may access.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Scope {
+  All,
+  /// Only reads of the plan of the class.
+  Class(String),
+}
+
+/// Routes class scoped tokens may read, each takes the class from the path.
+const CLASS_ROUTES: &[&str] = &[
+  "/davinci/:date/:class",
+  "/davinci/:date/:class/unapplied",
+  "/davinci/:date/:class/applied",
+  "/davinci/week/:week/:class",
+  "/davinci/week/:class",
+  "/davinci/now/:class",
+  "/davinci/history/:date/:class",
+];
+
+impl Scope {
+  /// Whether a request with the method to the matched route for the class of
+  /// its path is allowed.
+  pub(crate) fn allows(&self, method: &Method, route: &str, class: Option<&str>) -> bool {
+    match self {
+      Self::All => true,
+      Self::Class(scope) => {
+        method == Method::GET
+          && CLASS_ROUTES.contains(&route)
+          && class.is_some_and(|class| normalize_class(class) == normalize_class(scope))
+      }
+    }
+  }
+}
+
+#[derive(Clone)]
+pub(crate) struct Auth {
+  pub(crate) token: ApiToken,
+  pub(crate) jwt: Option<Arc<Jwt>>,
+  pub(crate) class_tokens: Arc<Vec<ClassToken>>,
+}
+
+impl Auth {
+  fn scope(&self, bearer: &str) -> Option<Scope> {
+    if let Some(token) = &*self.token.read().unwrap() {
+      if bearer == token {
+        return Some(Scope::All);
+      }
+    }
+
+    if self.jwt.as_ref().is_some_and(|jwt| jwt.verify(bearer)) {
+      return Some(Scope::All);
+    }
+
+    self
+      .class_tokens
+      .iter()
+      .find(|class_token| class_token.token == bearer)
+      .map(|class_token| Scope::Class(class_token.class.clone()))
+  }
+}
+
+/// Rejects requests without a known token, the [`Scope`] of the token is
+/// checked by [`scoped`] once the route is known.
+pub(crate) async fn bearer(State(auth): State<Auth>, mut request: Request, next: Next) -> Response {
+  let scope = request
+    .headers()
+    .get(AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.strip_prefix("Bearer "))
+    .and_then(|value| auth.scope(value));
+
+  let Some(scope) = scope else {
+    return StatusCode::UNAUTHORIZED.into_response();
+  };
+
+  request.extensions_mut().insert(scope);
+  next.run(request).await
+}
+
+/// Limits class scoped tokens to the routes of their class, tenants are only
+/// accessible with the full api token.
+pub(crate) async fn scoped(
+  route: Option<MatchedPath>,
+  params: Option<Path<HashMap<String, String>>>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let scope = request
+    .extensions()
+    .get::<Scope>()
+    .cloned()
+    .unwrap_or(Scope::Class(String::new()));
+
+  let params = params.map(|Path(params)| params).unwrap_or_default();
+  let allowed = scope.allows(
+    request.method(),
+    route.as_ref().map(MatchedPath::as_str).unwrap_or_default(),
+    params.get("class").map(String::as_str),
+  );
+
+  if !allowed {
+    return StatusCode::FORBIDDEN.into_response();
+  }
+
+  next.run(request).await
+}
@@ -1,14 +1,22 @@
+use crate::api::rows::RowQuery;
 use crate::api::AppError;
 use crate::api::AppError::PlanUnavailable;
+use crate::stats::StatsStore;
 use axum::extract::{Path, Query};
-use axum::response::{Html, IntoResponse};
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Response};
 use axum::{Extension, Json};
+use bszet_davinci::bell::{format_lesson_times, from_school_time, lesson_times, school_time};
+use bszet_davinci::class::normalize_class;
 use bszet_davinci::timetable::Subject;
-use bszet_davinci::Davinci;
+use bszet_davinci::{Davinci, PlanOptions, Row, RowChanges, Theme};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
 use std::sync::Arc;
 use time::serde::format_description;
-use time::Date;
+use time::{Date, OffsetDateTime, Time, UtcOffset, Weekday};
 
 format_description!(iso_date, Date, "[year]-[month]-[day]");
 
@@ -21,22 +29,85 @@ pub(crate) struct PlanPath {
 #[derive(Deserialize)]
 pub(crate) struct PlanQuery {
   class: String,
+  #[serde(default)]
+  theme: Theme,
+  #[serde(default)]
+  collapse: bool,
+  /// Links to the other dates, turned off for screenshots.
+  #[serde(default = "navigation")]
+  nav: bool,
+}
+
+fn navigation() -> bool {
+  true
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ThemeQuery {
+  #[serde(default)]
+  theme: Theme,
 }
 
 pub(crate) async fn html_plan(
   Extension(davinci): Extension<Arc<Davinci>>,
   Path(PlanPath { date }): Path<PlanPath>,
-  Query(PlanQuery { class }): Query<PlanQuery>,
+  Query(PlanQuery {
+    class,
+    theme,
+    collapse,
+    nav,
+  }): Query<PlanQuery>,
 ) -> Result<impl IntoResponse, AppError> {
   let split = class.split(',').collect::<Vec<&str>>();
   Ok(Html(
     davinci
-      .get_html(&date, split.as_slice())
+      .get_html(
+        &date,
+        split.as_slice(),
+        PlanOptions {
+          theme,
+          navigation: nav,
+          collapse,
+        },
+      )
       .await?
       .ok_or(PlanUnavailable)?,
   ))
 }
 
+/// Dates for which the current plan has rows.
+pub(crate) async fn dates(
+  Extension(davinci): Extension<Arc<Davinci>>,
+) -> Result<impl IntoResponse, AppError> {
+  let data = davinci.data().await;
+  let data = data.as_ref().ok_or(PlanUnavailable)?;
+
+  Ok(Json(
+    data
+      .dates()
+      .into_iter()
+      .map(|date| {
+        format!(
+          "{}-{:0>2}-{:0>2}",
+          date.year(),
+          date.month() as u8,
+          date.day()
+        )
+      })
+      .collect::<Vec<String>>(),
+  ))
+}
+
+/// Classes seen on the current plan.
+pub(crate) async fn classes(
+  Extension(davinci): Extension<Arc<Davinci>>,
+) -> Result<impl IntoResponse, AppError> {
+  let data = davinci.data().await;
+  let data = data.as_ref().ok_or(PlanUnavailable)?;
+
+  Ok(Json(data.classes()))
+}
+
 #[derive(Deserialize)]
 pub(crate) struct TimetablePath {
   #[serde(with = "iso_date")]
@@ -44,42 +115,527 @@ pub(crate) struct TimetablePath {
   class: String,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct Lesson {
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Lesson {
   pub lesson: u8,
   pub subject: String,
   pub iteration: Option<u8>,
   pub place: Option<String>,
+  pub teachers: Vec<String>,
   pub notice: Option<String>,
   pub cancel: bool,
+  pub exam: bool,
 }
 
-pub(crate) async fn timetable(
+#[derive(Deserialize)]
+pub(crate) struct TimetableQuery {
+  /// Overrides the `Accept` header, e.g. `?format=csv`.
+  format: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Format {
+  Json,
+  Csv,
+  Ics,
+}
+
+impl Format {
+  pub(crate) fn negotiate(format: Option<&str>, headers: &HeaderMap) -> Result<Self, AppError> {
+    let accept = headers
+      .get(ACCEPT)
+      .and_then(|accept| accept.to_str().ok())
+      .unwrap_or_default();
+
+    Ok(match format {
+      Some("json") => Format::Json,
+      Some("csv") => Format::Csv,
+      Some("ics") => Format::Ics,
+      Some(_) => return Err(AppError::UnknownFormat),
+      None if accept.contains("text/csv") => Format::Csv,
+      None if accept.contains("text/calendar") => Format::Ics,
+      None => Format::Json,
+    })
+  }
+}
+
+/// The applied timetable as html page, cancellations are struck through.
+pub(crate) async fn applied(
   Extension(davinci): Extension<Arc<Davinci>>,
-  Path(TimetablePath { date, .. }): Path<TimetablePath>,
+  Path(TimetablePath { date, class }): Path<TimetablePath>,
+  Query(ThemeQuery { theme }): Query<ThemeQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-  Ok(Json(
+  Ok(Html(
     davinci
-      .get_applied_timetable(date)
+      .get_applied_html(date, &class, theme)
       .await
       .map_err(|_| AppError::IterationNotAvailable)?
-      .1
+      .ok_or(AppError::UnknownClass)?,
+  ))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct WeekPath {
+  /// e.g. `2023-W05`
+  week: String,
+  class: String,
+}
+
+/// Monday of an iso week like `2023-W05`.
+pub(crate) fn parse_iso_week(week: &str) -> Option<Date> {
+  let (year, week) = week.split_once("-W")?;
+  Date::from_iso_week_date(year.parse().ok()?, week.parse().ok()?, Weekday::Monday).ok()
+}
+
+/// Monday to friday of the applied timetable as html page.
+pub(crate) async fn week(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Path(WeekPath { week, class }): Path<WeekPath>,
+  Query(ThemeQuery { theme }): Query<ThemeQuery>,
+) -> Result<impl IntoResponse, AppError> {
+  let monday = parse_iso_week(&week).ok_or(AppError::InvalidWeek)?;
+
+  Ok(Html(
+    davinci
+      .get_week_html(monday, &class, theme)
+      .await?
+      .ok_or(AppError::UnknownClass)?,
+  ))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ClassPath {
+  class: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct WeekQuery {
+  /// e.g. `2023-W05`, the current week by default.
+  week: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Day {
+  #[serde(with = "iso_date")]
+  date: Date,
+  /// `None` for days outside of the known iterations, e.g. holidays.
+  iteration: Option<u8>,
+  lessons: Vec<Lesson>,
+}
+
+/// Monday to friday of the applied timetable.
+pub(crate) async fn week_json(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Path(ClassPath { class }): Path<ClassPath>,
+  Query(WeekQuery { week }): Query<WeekQuery>,
+) -> Result<impl IntoResponse, AppError> {
+  let date = match week {
+    Some(week) => parse_iso_week(&week).ok_or(AppError::InvalidWeek)?,
+    None => OffsetDateTime::now_utc().date(),
+  };
+
+  Ok(Json(
+    davinci
+      .get_week(date, &class)
+      .await
+      .ok_or(AppError::UnknownClass)?
       .into_iter()
-      .map(|lesson| {
-        let (subject, cancel) = match lesson.subject {
-          Subject::Cancel(subject) => (*subject, true),
-          subject => (subject, false),
-        };
-
-        Lesson {
-          lesson: lesson.lesson,
-          subject: format!("{subject}"),
-          iteration: lesson.iteration,
-          place: lesson.place,
-          notice: lesson.notice,
-          cancel,
+      .map(|day| Day {
+        date: day.date,
+        iteration: day.iteration,
+        lessons: lessons_json(day.lessons),
+      })
+      .collect::<Vec<Day>>(),
+  ))
+}
+
+pub(crate) async fn timetable(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Path(TimetablePath { date, class }): Path<TimetablePath>,
+  Query(TimetableQuery { format }): Query<TimetableQuery>,
+  headers: HeaderMap,
+) -> Result<Response, AppError> {
+  let lessons = davinci
+    .get_applied_timetable(date, &class)
+    .await
+    .map_err(|_| AppError::IterationNotAvailable)?
+    .ok_or(AppError::UnknownClass)?
+    .1;
+
+  Ok(match Format::negotiate(format.as_deref(), &headers)? {
+    Format::Json => Json(lessons_json(lessons)).into_response(),
+    Format::Csv => (
+      [(CONTENT_TYPE, "text/csv; charset=utf-8")],
+      lessons_csv(&lessons_json(lessons)),
+    )
+      .into_response(),
+    Format::Ics => (
+      [(CONTENT_TYPE, "text/calendar; charset=utf-8")],
+      lessons_ics(
+        date,
+        &class,
+        &lessons_json(lessons),
+        OffsetDateTime::now_utc(),
+      ),
+    )
+      .into_response(),
+  })
+}
+
+fn lessons_json(lessons: Vec<bszet_davinci::timetable::Lesson>) -> Vec<Lesson> {
+  lessons
+    .into_iter()
+    .map(|lesson| {
+      let (subject, cancel) = match lesson.subject {
+        Subject::Cancel(subject) => (*subject, true),
+        subject => (subject, false),
+      };
+
+      Lesson {
+        lesson: lesson.lesson,
+        subject: format!("{subject}"),
+        iteration: lesson.iteration,
+        place: lesson.place,
+        teachers: lesson
+          .teachers
+          .into_iter()
+          .filter(|teacher| !teacher.is_empty())
+          .collect(),
+        notice: lesson.notice,
+        cancel,
+        exam: lesson.exam,
+      }
+    })
+    .collect()
+}
+
+/// `lesson,time,subject,room,change` rows for spreadsheets and signage.
+pub(crate) fn lessons_csv(lessons: &[Lesson]) -> String {
+  let mut out = String::from("lesson,time,subject,room,change\r\n");
+
+  for lesson in lessons {
+    let change = if lesson.cancel {
+      "Entfall"
+    } else if lesson.exam {
+      "Klausur"
+    } else {
+      lesson.notice.as_deref().unwrap_or_default()
+    };
+
+    let fields = [
+      lesson.lesson.to_string(),
+      format_lesson_times(lesson.lesson).unwrap_or_default(),
+      lesson.subject.clone(),
+      lesson.place.clone().unwrap_or_default(),
+      change.to_string(),
+    ];
+
+    out.push_str(
+      &fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(","),
+    );
+    out.push_str("\r\n");
+  }
+
+  out
+}
+
+/// Single-day calendar with one event per lesson, cancelled lessons are kept
+/// with `STATUS:CANCELLED`. Without a bell schedule the events span the whole day.
+pub(crate) fn lessons_ics(
+  date: Date,
+  class: &str,
+  lessons: &[Lesson],
+  now: OffsetDateTime,
+) -> String {
+  let day = format!(
+    "{}{:0>2}{:0>2}",
+    date.year(),
+    date.month() as u8,
+    date.day()
+  );
+
+  let mut out =
+    String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//bszet-mind//timetable//DE\r\n");
+
+  // parallel group lessons share the block, the index tells them apart
+  for (index, lesson) in lessons.iter().enumerate() {
+    out.push_str("BEGIN:VEVENT\r\n");
+    out.push_str(&format!(
+      "UID:{day}-{}-{}-{}-{index}@bszet-mind\r\n",
+      class.to_lowercase(),
+      lesson.lesson,
+      lesson.iteration.unwrap_or_default()
+    ));
+    out.push_str(&format!("DTSTAMP:{}\r\n", ics_utc(now)));
+
+    match lesson_times(lesson.lesson) {
+      Some((start, end)) => {
+        // the bell schedule is local, utc needs no VTIMEZONE
+        for (name, time) in [("DTSTART", start), ("DTEND", end)] {
+          out.push_str(&format!(
+            "{name}:{}\r\n",
+            ics_utc(from_school_time(date, time))
+          ));
         }
+      }
+      None => out.push_str(&format!("DTSTART;VALUE=DATE:{day}\r\n")),
+    }
+
+    let summary = if lesson.exam {
+      format!("{} (Klausur)", lesson.subject)
+    } else {
+      lesson.subject.clone()
+    };
+    out.push_str(&format!("SUMMARY:{}\r\n", ics_text(&summary)));
+
+    if let Some(place) = &lesson.place {
+      out.push_str(&format!("LOCATION:{}\r\n", ics_text(place)));
+    }
+    if let Some(notice) = &lesson.notice {
+      out.push_str(&format!("DESCRIPTION:{}\r\n", ics_text(notice)));
+    }
+    if lesson.cancel {
+      out.push_str("STATUS:CANCELLED\r\n");
+    }
+
+    out.push_str("END:VEVENT\r\n");
+  }
+
+  out.push_str("END:VCALENDAR\r\n");
+  out
+}
+
+fn ics_utc(time: OffsetDateTime) -> String {
+  let time = time.to_offset(UtcOffset::UTC);
+  format!(
+    "{}{:0>2}{:0>2}T{:0>2}{:0>2}{:0>2}Z",
+    time.year(),
+    time.month() as u8,
+    time.day(),
+    time.hour(),
+    time.minute(),
+    time.second()
+  )
+}
+
+fn ics_text(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace(';', "\\;")
+    .replace(',', "\\,")
+    .replace('\n', "\\n")
+}
+
+fn csv_field(value: &str) -> String {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ChangesQuery {
+  #[serde(with = "time::serde::rfc3339")]
+  since: OffsetDateTime,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Changes {
+  added: Vec<Value>,
+  removed: Vec<Value>,
+  /// Sorted dates of the added and removed rows.
+  dates: Vec<String>,
+  /// Sorted, normalized classes of the added and removed rows.
+  classes: Vec<String>,
+}
+
+/// Rows added or removed after `since` (RFC 3339), for incremental syncs.
+pub(crate) async fn changes(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Query(ChangesQuery { since }): Query<ChangesQuery>,
+  Query(query): Query<RowQuery>,
+) -> Result<impl IntoResponse, AppError> {
+  let RowChanges { added, removed } = davinci
+    .changes_since(since)
+    .await
+    .ok_or(AppError::ChangesUnknown)?;
+
+  let rows = added
+    .iter()
+    .chain(&removed)
+    .filter(|row| query.matches(row));
+  let dates = rows.clone().map(|row| row.date).collect::<BTreeSet<Date>>();
+  let classes = rows
+    .flat_map(|row| &row.class)
+    .map(|class| normalize_class(class))
+    .filter(|class| !class.is_empty())
+    .collect::<BTreeSet<String>>();
+
+  Ok(Json(Changes {
+    dates: dates
+      .into_iter()
+      .map(|date| {
+        format!(
+          "{}-{:0>2}-{:0>2}",
+          date.year(),
+          date.month() as u8,
+          date.day()
+        )
       })
-      .collect::<Vec<Lesson>>(),
+      .collect(),
+    classes: classes.into_iter().collect(),
+    added: query.select(&added),
+    removed: query.select(&removed),
+  }))
+}
+
+#[derive(Serialize)]
+pub(crate) struct History {
+  lessons: Vec<Lesson>,
+  /// Archived rows of the class, the evidence for the lessons.
+  rows: Vec<Row>,
+}
+
+/// Timetable of a past date with the archived rows applied, available after
+/// the school removed the plan. Only the tracked classes are archived, without
+/// archived rows the plan of the date is unknown instead of unchanged.
+pub(crate) async fn history(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Extension(stats): Extension<Arc<StatsStore>>,
+  Path(TimetablePath { date, class }): Path<TimetablePath>,
+) -> Result<impl IntoResponse, AppError> {
+  let rows = stats
+    .rows_of(date)
+    .await
+    .into_iter()
+    .filter(|row| row.is_for_class(&class))
+    .collect::<HashSet<Row>>();
+
+  if rows.is_empty() {
+    return Err(AppError::NotArchived);
+  }
+
+  let (lessons, _, _) = davinci
+    .get_applied_timetable_from(date, &class, &rows)
+    .map_err(|_| AppError::IterationNotAvailable)?
+    .ok_or(AppError::UnknownClass)?;
+
+  let mut rows = rows.into_iter().collect::<Vec<Row>>();
+  rows.sort_by_key(|row| row.index);
+
+  Ok(Json(History {
+    lessons: lessons_json(lessons),
+    rows,
+  }))
+}
+
+/// Rows of the plan that couldn't be applied to the timetable.
+pub(crate) async fn unapplied(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Path(TimetablePath { date, class }): Path<TimetablePath>,
+) -> Result<impl IntoResponse, AppError> {
+  Ok(Json(
+    davinci
+      .get_applied_timetable(date, &class)
+      .await
+      .map_err(|_| AppError::IterationNotAvailable)?
+      .ok_or(AppError::UnknownClass)?
+      .2,
   ))
 }
+
+#[derive(Deserialize)]
+pub(crate) struct NowQuery {
+  /// Time of the day (`HH:MM`, local time of the school) to look at, now by
+  /// default.
+  #[serde(default, with = "hour_minute::option")]
+  at: Option<Time>,
+}
+
+format_description!(hour_minute, Time, "[hour]:[minute]");
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct NowLesson {
+  #[serde(flatten)]
+  pub lesson: Lesson,
+  /// e.g. `09:40`
+  pub start: String,
+  pub end: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Now {
+  pub current: Option<NowLesson>,
+  pub next: Option<NowLesson>,
+}
+
+/// The running and the next lesson of today according to the bell schedule.
+pub(crate) async fn now(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Path(ClassPath { class }): Path<ClassPath>,
+  Query(NowQuery { at }): Query<NowQuery>,
+) -> Result<impl IntoResponse, AppError> {
+  // the bell schedule is in local time
+  let now = school_time(OffsetDateTime::now_utc());
+
+  let lessons = davinci
+    .get_applied_timetable(now.date(), &class)
+    .await
+    .map_err(|_| AppError::IterationNotAvailable)?
+    .ok_or(AppError::UnknownClass)?
+    .1;
+
+  Ok(Json(current_and_next(
+    lessons_json(lessons),
+    at.unwrap_or(now.time()),
+    lesson_times,
+  )))
+}
+
+/// Cancelled lessons are reported while running, but never as the next one.
+pub(crate) fn current_and_next(
+  lessons: Vec<Lesson>,
+  at: Time,
+  times: impl Fn(u8) -> Option<(Time, Time)>,
+) -> Now {
+  let mut lessons = lessons
+    .into_iter()
+    .filter_map(|lesson| {
+      let (start, end) = times(lesson.lesson)?;
+      Some((start, end, lesson))
+    })
+    .collect::<Vec<_>>();
+  lessons.sort_by_key(|(start, _, _)| *start);
+
+  let mut now = Now {
+    current: None,
+    next: None,
+  };
+
+  for (start, end, lesson) in lessons {
+    let lesson = NowLesson {
+      lesson,
+      start: format_time(start),
+      end: format_time(end),
+    };
+
+    if start <= at && at < end {
+      if now.current.is_none() {
+        now.current = Some(lesson);
+      }
+    } else if start > at && !lesson.lesson.cancel && now.next.is_none() {
+      now.next = Some(lesson);
+    }
+  }
+
+  now
+}
+
+fn format_time(time: Time) -> String {
+  format!("{:0>2}:{:0>2}", time.hour(), time.minute())
+}
@@ -0,0 +1,66 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use axum::extract::Request;
+use axum::http::header::{ACCEPT, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+
+use bszet_davinci::{settings_hash, Davinci};
+
+/// Answers `If-None-Match` requests with `304 Not Modified` as long as the plan,
+/// its messages, the timetables and the installed settings didn't change, so
+/// polling clients don't re-download identical plans. Only for routes answered
+/// from those alone.
+pub(crate) async fn etag(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  request: Request,
+  next: Next,
+) -> Response {
+  if request.method() != Method::GET {
+    return next.run(request).await;
+  }
+
+  let Some(etag) = davinci.data().await.as_ref().map(|data| {
+    let mut hasher = DefaultHasher::new();
+    data.rows_hash().hash(&mut hasher);
+    data.last_modified.hash(&mut hasher);
+    // replaced on every update, even if the rows stay the same
+    data.messages.hash(&mut hasher);
+    davinci.timetables().generation().hash(&mut hasher);
+    settings_hash().hash(&mut hasher);
+    // different representations of the same resource need different tags
+    request.uri().hash(&mut hasher);
+    request.headers().get(ACCEPT).hash(&mut hasher);
+    format!("\"{:x}\"", hasher.finish())
+  }) else {
+    return next.run(request).await;
+  };
+
+  let matches = request
+    .headers()
+    .get(IF_NONE_MATCH)
+    .and_then(|value| value.to_str().ok())
+    .is_some_and(|value| {
+      value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == etag)
+    });
+
+  if matches {
+    return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+  }
+
+  let mut response = next.run(request).await;
+  if response.status() == StatusCode::OK {
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+      response.headers_mut().insert(ETAG, value);
+    }
+  }
+
+  response
+}
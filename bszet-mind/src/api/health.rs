@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use axum::http::StatusCode;
+use time::OffsetDateTime;
+
+/// Unix timestamp of the last sign of life of the crawl loop.
+static HEARTBEAT: AtomicI64 = AtomicI64::new(0);
+
+/// Longest expected gap between heartbeats: one slot plus a slow iteration.
+const MAX_SILENCE_SECS: i64 = 30 * 60;
+
+pub(crate) fn heartbeat() {
+  HEARTBEAT.store(
+    OffsetDateTime::now_utc().unix_timestamp(),
+    Ordering::Relaxed,
+  );
+}
+
+pub(crate) fn is_alive(heartbeat: i64, now: i64) -> bool {
+  now - heartbeat <= MAX_SILENCE_SECS
+}
+
+/// Unauthenticated and without body, meant for container healthchecks.
+pub(crate) async fn healthz() -> StatusCode {
+  let now = OffsetDateTime::now_utc().unix_timestamp();
+
+  match is_alive(HEARTBEAT.load(Ordering::Relaxed), now) {
+    true => StatusCode::OK,
+    false => StatusCode::SERVICE_UNAVAILABLE,
+  }
+}
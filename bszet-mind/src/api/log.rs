@@ -0,0 +1,40 @@
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use tracing::info;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::api::AppError;
+
+pub(crate) type LogHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Current log filter in `RUST_LOG` syntax.
+pub(crate) async fn log_level(
+  Extension(handle): Extension<LogHandle>,
+) -> Result<impl IntoResponse, AppError> {
+  Ok(
+    handle
+      .with_current(|filter| filter.to_string())
+      .map_err(|err| anyhow!(err))?,
+  )
+}
+
+/// Replaces the log filter, e.g. with `info,bszet_davinci=debug`.
+pub(crate) async fn set_log_level(
+  Extension(handle): Extension<LogHandle>,
+  body: String,
+) -> Response {
+  let filter = match EnvFilter::try_new(body.trim()) {
+    Ok(filter) => filter,
+    Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+  };
+
+  match handle.reload(filter) {
+    Ok(()) => {
+      info!("Changed log filter to {}", body.trim());
+      StatusCode::NO_CONTENT.into_response()
+    }
+    Err(err) => AppError::from(anyhow!(err)).into_response(),
+  }
+}
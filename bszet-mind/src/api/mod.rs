@@ -2,12 +2,25 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use tracing::error;
 
+pub(crate) mod auth;
 pub(crate) mod davinci;
+pub(crate) mod etag;
+pub(crate) mod health;
+pub(crate) mod log;
+pub(crate) mod rows;
+pub(crate) mod stats;
+pub(crate) mod subscribers;
 
 pub(crate) enum AppError {
   InternalServerError(anyhow::Error),
   PlanUnavailable,
   IterationNotAvailable,
+  UnknownClass,
+  UnknownTenant,
+  InvalidWeek,
+  UnknownFormat,
+  ChangesUnknown,
+  NotArchived,
 }
 
 impl From<anyhow::Error> for AppError {
@@ -31,6 +44,18 @@ impl IntoResponse for AppError {
         StatusCode::BAD_REQUEST,
         "iteration for given date not available",
       ),
+      AppError::UnknownClass => (StatusCode::NOT_FOUND, "unknown class"),
+      AppError::UnknownTenant => (StatusCode::NOT_FOUND, "unknown tenant"),
+      AppError::InvalidWeek => (StatusCode::BAD_REQUEST, "week has to look like 2023-W05"),
+      AppError::UnknownFormat => (StatusCode::BAD_REQUEST, "format has to be json, csv or ics"),
+      AppError::ChangesUnknown => (
+        StatusCode::GONE,
+        "changes since then aren't known anymore, fetch the whole plan",
+      ),
+      AppError::NotArchived => (
+        StatusCode::NOT_FOUND,
+        "no changes of the class archived for the date",
+      ),
     };
 
     (status, error_message).into_response()
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use axum::extract::Query;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use bszet_davinci::change::Change;
+use bszet_davinci::{Davinci, Row};
+
+use crate::api::AppError;
+
+/// Narrows down the rows of the JSON endpoints for thin clients.
+#[derive(Deserialize)]
+pub(crate) struct RowQuery {
+  pub(crate) class: Option<String>,
+  /// e.g. `cancel` or `room_change`
+  pub(crate) change_type: Option<String>,
+  /// Comma separated, e.g. `lesson,subject`.
+  pub(crate) fields: Option<String>,
+}
+
+impl RowQuery {
+  pub(crate) fn matches(&self, row: &Row) -> bool {
+    self
+      .class
+      .as_ref()
+      .is_none_or(|class| row.is_for_class(class))
+      && self
+        .change_type
+        .as_ref()
+        .is_none_or(|change_type| change_type.eq_ignore_ascii_case(kind(&row.change)))
+  }
+
+  /// The matching rows with the change flattened into them, sorted by date and
+  /// position on the plan.
+  pub(crate) fn select<'a>(&self, rows: impl IntoIterator<Item = &'a Row>) -> Vec<Value> {
+    let mut rows = rows
+      .into_iter()
+      .filter(|row| self.matches(row))
+      .collect::<Vec<&Row>>();
+    rows.sort_by_key(|row| (row.date, row.index));
+
+    let fields = self
+      .fields
+      .as_ref()
+      .map(|fields| fields.split(',').map(str::trim).collect::<Vec<&str>>());
+
+    rows
+      .into_iter()
+      .map(|row| {
+        let mut row = flatten(row);
+        if let Some(fields) = &fields {
+          row.retain(|key, _| fields.contains(&key.as_str()));
+        }
+        Value::Object(row)
+      })
+      .collect()
+  }
+}
+
+/// Snake case name of the kind of change, e.g. `room_change`.
+pub(crate) fn kind(change: &Change) -> &'static str {
+  match change {
+    Change::Cancel { .. } => "cancel",
+    Change::RoomChange { .. } => "room_change",
+    Change::Addition { .. } => "addition",
+    Change::Replacement { .. } => "replacement",
+    Change::Exam { .. } => "exam",
+    Change::Supervision { .. } => "supervision",
+    Change::TeacherChange { .. } => "teacher_change",
+    Change::Other { .. } => "other",
+  }
+}
+
+/// The row with the fields of its change next to `date` and `class` and the
+/// kind of change as `change_type`.
+pub(crate) fn flatten(row: &Row) -> Map<String, Value> {
+  let mut object = match serde_json::to_value(row) {
+    Ok(Value::Object(object)) => object,
+    _ => Map::new(),
+  };
+
+  // changes are serialized like {"Cancel": {"lesson": 1, ...}}
+  if let Some(Value::Object(change)) = object.remove("change") {
+    for (_, fields) in change {
+      if let Value::Object(fields) = fields {
+        object.extend(fields);
+      }
+    }
+  }
+
+  object.insert(
+    "change_type".to_string(),
+    Value::String(kind(&row.change).to_string()),
+  );
+  object
+}
+
+/// Rows of the current plan.
+pub(crate) async fn rows(
+  Extension(davinci): Extension<Arc<Davinci>>,
+  Query(query): Query<RowQuery>,
+) -> Result<impl IntoResponse, AppError> {
+  let data = davinci.data().await;
+  let data = data.as_ref().ok_or(AppError::PlanUnavailable)?;
+
+  Ok(Json(query.select(&data.rows)))
+}
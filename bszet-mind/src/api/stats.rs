@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use axum::extract::Query;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use serde::Deserialize;
+use time::serde::format_description;
+use time::{Date, OffsetDateTime};
+
+use crate::stats::{school_year_start, StatsStore};
+
+format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+#[derive(Deserialize)]
+pub(crate) struct StatsQuery {
+  #[serde(default, with = "iso_date::option")]
+  from: Option<Date>,
+  #[serde(default, with = "iso_date::option")]
+  to: Option<Date>,
+}
+
+/// Statistics of the current school year unless specified otherwise.
+pub(crate) async fn stats(
+  Extension(stats): Extension<Arc<StatsStore>>,
+  Query(StatsQuery { from, to }): Query<StatsQuery>,
+) -> impl IntoResponse {
+  let today = OffsetDateTime::now_utc().date();
+
+  Json(
+    stats
+      .statistics(
+        from.unwrap_or_else(|| school_year_start(today)),
+        to.unwrap_or(Date::MAX),
+      )
+      .await,
+  )
+}
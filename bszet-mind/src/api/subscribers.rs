@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+
+use crate::api::AppError;
+use crate::subscribers::{Subscriber, SubscriberStore};
+
+/// Preferences of all chats that changed any.
+pub(crate) async fn subscribers(
+  Extension(subscribers): Extension<Arc<SubscriberStore>>,
+) -> impl IntoResponse {
+  Json(subscribers.all().await)
+}
+
+pub(crate) async fn subscriber(
+  Extension(subscribers): Extension<Arc<SubscriberStore>>,
+  Path(chat_id): Path<i64>,
+) -> impl IntoResponse {
+  Json(subscribers.get(chat_id).await)
+}
+
+/// Replaces the preferences of the chat, omitted fields are reset.
+pub(crate) async fn set_subscriber(
+  Extension(subscribers): Extension<Arc<SubscriberStore>>,
+  Path(chat_id): Path<i64>,
+  Json(subscriber): Json<Subscriber>,
+) -> Response {
+  if subscriber
+    .quiet_hours
+    .is_some_and(|quiet_hours| quiet_hours.from > 23 || quiet_hours.to > 23)
+  {
+    return (
+      StatusCode::BAD_REQUEST,
+      "quiet hours have to be between 0 and 23",
+    )
+      .into_response();
+  }
+
+  match subscribers.update(chat_id, |chat| *chat = subscriber).await {
+    Ok(()) => StatusCode::NO_CONTENT.into_response(),
+    Err(err) => AppError::from(err).into_response(),
+  }
+}
@@ -1,65 +1,299 @@
 use std::fmt::Write;
 
-use bszet_davinci::timetable::Lesson;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
-pub fn table(day: Vec<Lesson>) -> String {
-  let mut lesson_w = 0;
-  let mut subject_w = 0;
-  let mut place_w = 0;
+use bszet_davinci::bell::format_lesson_times;
+use bszet_davinci::teachers::teacher_names;
+use bszet_davinci::timetable::{Lesson, LessonChange, Subject};
 
-  for lesson in &day {
-    let l = format!("{}", lesson.lesson);
-    let s = format!("{}", lesson.subject);
-    let p = &lesson.place;
+use crate::diff::escape_markdown;
+use crate::i18n::Language;
 
-    lesson_w = lesson_w.max(l.chars().count());
-    subject_w = subject_w.max(s.chars().count());
-    place_w = place_w.max(p.as_ref().map(|s| s.chars().count()).unwrap_or(0));
+/// Cells of a lesson, see [`columns`].
+const COLUMNS: usize = 7;
+
+/// Widest subjects and rooms, longer ones are abbreviated.
+const SUBJECT_WIDTH: usize = 6;
+const ROOM_WIDTH: usize = 5;
+
+/// Narrowest teacher or notice column, below that the lesson times are left
+/// out.
+const MIN_WRAP_WIDTH: usize = 8;
+
+/// How the lessons of the day are written into the notification text.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TableFormat {
+  /// Aligned columns in a monospace block.
+  Ascii,
+  /// Pipe table, for chats bridged to clients rendering Markdown.
+  Markdown,
+}
+
+impl TableFormat {
+  /// The table of the day, ready to be embedded in a Markdown message.
+  pub(crate) fn format(&self, day: Vec<Lesson>, width: usize, language: Language) -> String {
+    match self {
+      Self::Ascii => format!("```\n{}```", table(day, width)),
+      Self::Markdown => markdown_table(day, language),
+    }
   }
+}
 
-  // only works with ascii characters, with utf like ü, ä, ö, ß, ...
-  // there will be an additional allocation
-  let mut out = String::with_capacity(day.len() * (lesson_w + subject_w + place_w + 2));
+pub fn table(day: Vec<Lesson>, max_width: usize) -> String {
+  let rows = day
+    .iter()
+    .map(|lesson| {
+      let mut columns = columns(lesson);
+      columns[3] = abbreviate(&columns[3], SUBJECT_WIDTH);
+      columns[4] = abbreviate(&columns[4].replace(' ', ""), ROOM_WIDTH);
+      columns
+    })
+    .collect::<Vec<[String; COLUMNS]>>();
 
-  let mut first = true;
+  let mut widths = [0; COLUMNS];
+  for columns in &rows {
+    for (index, column) in columns.iter().enumerate() {
+      widths[index] = widths[index].max(column.chars().count());
+    }
+  }
 
-  for lesson in &day {
-    let l = format!("{}", lesson.lesson);
-    let s = format!("{}", lesson.subject);
-    let p = &lesson.place;
+  // empty columns, like unknown bell schedules or teachers, are skipped
+  let mut shown = (0..COLUMNS)
+    .filter(|index| widths[*index] > 0)
+    .collect::<Vec<usize>>();
 
-    if first {
-      first = false;
-    } else {
+  // teachers and notices wrap into the remaining width, the lesson times are
+  // left out first if too little remains
+  let wrapped = shown.iter().filter(|index| is_wrapped(**index)).count();
+  if max_width.saturating_sub(fixed_width(&shown, &widths)) < MIN_WRAP_WIDTH * wrapped {
+    shown.retain(|index| *index != 1);
+  }
+  let available = max_width
+    .saturating_sub(fixed_width(&shown, &widths))
+    .max(MIN_WRAP_WIDTH * wrapped);
+
+  if widths[5] + widths[6] + 1 > available && wrapped == 2 {
+    widths[5] = widths[5].min((available / 3).max(MIN_WRAP_WIDTH));
+    widths[6] = available - widths[5] - 1;
+  } else if wrapped == 1 {
+    widths[5] = widths[5].min(available);
+    widths[6] = widths[6].min(available);
+  }
+
+  let mut out = String::new();
+
+  for (row, columns) in rows.iter().enumerate() {
+    if row != 0 {
       writeln!(out).unwrap();
     }
 
-    if let Some(notice) = &lesson.notice {
-      write!(
-        out,
-        "{}{} {}{} {}{} {}",
-        l,
-        " ".repeat(lesson_w - l.chars().count()),
-        s,
-        " ".repeat(subject_w - s.chars().count()),
-        p.as_ref().unwrap_or(&"".to_string()),
-        " ".repeat(place_w - p.as_ref().map(|s| s.chars().count()).unwrap_or(0)),
-        notice
-      )
-      .unwrap();
-    } else {
-      write!(
-        out,
-        "{}{} {}{} {}",
-        l,
-        " ".repeat(lesson_w - l.chars().count()),
-        s,
-        " ".repeat(subject_w - s.chars().count()),
-        p.as_ref().unwrap_or(&"".to_string())
-      )
-      .unwrap();
+    let cells = shown
+      .iter()
+      .map(|index| match is_wrapped(*index) {
+        true => wrap(&columns[*index], widths[*index]),
+        false => vec![columns[*index].clone()],
+      })
+      .collect::<Vec<Vec<String>>>();
+    let lines = cells.iter().map(Vec::len).max().unwrap_or(1);
+
+    for line_index in 0..lines {
+      if line_index != 0 {
+        writeln!(out).unwrap();
+      }
+
+      let mut line = String::new();
+
+      for (cell, index) in cells.iter().zip(&shown) {
+        let text = cell.get(line_index).map(String::as_str).unwrap_or_default();
+
+        if !line.is_empty() {
+          line.push(' ');
+        }
+
+        line.push_str(text);
+        line.push_str(&" ".repeat(widths[*index].saturating_sub(text.chars().count())));
+      }
+
+      out.push_str(line.trim_end());
     }
   }
 
   out
 }
+
+/// Teachers and notices continue on the next lines.
+fn is_wrapped(index: usize) -> bool {
+  matches!(index, 5 | 6)
+}
+
+/// Width of the columns that aren't wrapped, including the spaces between
+/// all columns.
+fn fixed_width(shown: &[usize], widths: &[usize]) -> usize {
+  shown
+    .iter()
+    .filter(|index| !is_wrapped(**index))
+    .map(|index| widths[*index])
+    .sum::<usize>()
+    + shown.len().saturating_sub(1)
+}
+
+/// Splits the cell into lines at spaces, words longer than the width are
+/// split themselves.
+fn wrap(cell: &str, width: usize) -> Vec<String> {
+  let width = width.max(1);
+  let mut lines = Vec::new();
+  let mut line = String::new();
+
+  for word in cell.split_whitespace() {
+    let mut word = word.chars().collect::<Vec<char>>();
+
+    while !word.is_empty() {
+      let length = line.chars().count();
+      let space = usize::from(length != 0);
+
+      if length + space + word.len() <= width {
+        if space == 1 {
+          line.push(' ');
+        }
+        line.extend(word.drain(..));
+      } else if length != 0 {
+        lines.push(std::mem::take(&mut line));
+      } else {
+        line.extend(word.drain(..width));
+        lines.push(std::mem::take(&mut line));
+      }
+    }
+  }
+
+  if !line.is_empty() || lines.is_empty() {
+    lines.push(line);
+  }
+
+  lines
+}
+
+/// Shortens subjects and rooms longer than `max`, to the initials of their
+/// words ("Gemeinschaftskunde Rechtserziehung Wirtschaft" → "GRW") or else
+/// like handwritten abbreviations ("Informatik" → "Info.").
+fn abbreviate(cell: &str, max: usize) -> String {
+  if cell.chars().count() <= max {
+    return cell.to_string();
+  }
+
+  let words = cell.split_whitespace().collect::<Vec<&str>>();
+  if words.len() > 1 && words.len() <= max {
+    return words
+      .iter()
+      .filter_map(|word| word.chars().next())
+      .flat_map(char::to_uppercase)
+      .collect();
+  }
+
+  let mut abbreviation = cell.chars().take(max - 1).collect::<String>();
+  abbreviation.push('.');
+  abbreviation
+}
+
+/// One sentence per lesson for screen readers, e.g. "3. Stunde Mathe
+/// entfällt."
+pub fn sentences(day: Vec<Lesson>, language: Language) -> String {
+  day
+    .iter()
+    .map(|lesson| {
+      let cancelled = matches!(lesson.subject, Subject::Cancel(_));
+      let mut sentence = language.lesson_sentence(
+        lesson.lesson,
+        &language.subject(&lesson.subject),
+        cancelled,
+        lesson.place.as_deref().filter(|place| !place.is_empty()),
+        &teacher_names(&lesson.teachers),
+      );
+
+      if lesson.exam {
+        write!(sentence, ", {}", language.exam()).unwrap();
+      }
+      if let Some(notice) = lesson.notice.as_ref().filter(|_| !cancelled) {
+        write!(sentence, " ({notice})").unwrap();
+      }
+      sentence.push('.');
+
+      escape_markdown(&sentence)
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+/// Github flavored Markdown table, cells aren't abbreviated as the clients
+/// wrap them.
+pub fn markdown_table(day: Vec<Lesson>, language: Language) -> String {
+  let rows = day.iter().map(columns).collect::<Vec<[String; COLUMNS]>>();
+
+  // same optional columns as the monospace table
+  let shown = (0..COLUMNS)
+    .filter(|index| !matches!(index, 1 | 2 | 5) || rows.iter().any(|row| !row[*index].is_empty()))
+    .collect::<Vec<usize>>();
+
+  let line = |cells: &[String]| {
+    let cells = shown
+      .iter()
+      .map(|index| escape_cell(&cells[*index]))
+      .collect::<Vec<String>>();
+    format!("| {} |", cells.join(" | "))
+  };
+
+  let mut out = line(&language.table_header().map(str::to_string));
+  write!(out, "\n|{}", "---|".repeat(shown.len())).unwrap();
+  for row in &rows {
+    write!(out, "\n{}", line(row)).unwrap();
+  }
+
+  out
+}
+
+fn columns(lesson: &Lesson) -> [String; COLUMNS] {
+  [
+    format!("{}", lesson.lesson),
+    format_lesson_times(lesson.lesson).unwrap_or_default(),
+    marker(lesson).to_string(),
+    subject(lesson),
+    lesson.place.clone().unwrap_or_default(),
+    teacher_names(&lesson.teachers),
+    lesson.notice.clone().unwrap_or_default(),
+  ]
+}
+
+fn escape_cell(cell: &str) -> String {
+  let mut out = String::with_capacity(cell.len());
+
+  for char in cell.chars() {
+    if matches!(char, '|' | '_' | '*' | '`' | '[') {
+      out.push('\\');
+    }
+    out.push(char);
+  }
+
+  out
+}
+
+/// ✗ cancelled, ↷ moved, ⇄ substituted, Δ room changed and ≠ another teacher.
+fn marker(lesson: &Lesson) -> &'static str {
+  match lesson.change {
+    Some(LessonChange::Cancel) => "✗",
+    Some(LessonChange::Moved) => "↷",
+    Some(LessonChange::Substitution) => "⇄",
+    Some(LessonChange::Room) => "Δ",
+    Some(LessonChange::Teacher) => "≠",
+    None => "",
+  }
+}
+
+/// Exams are marked with an exclamation mark.
+fn subject(lesson: &Lesson) -> String {
+  if lesson.exam {
+    format!("{}!", lesson.subject)
+  } else {
+    format!("{}", lesson.subject)
+  }
+}
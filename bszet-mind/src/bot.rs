@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use time::{OffsetDateTime, Time, Weekday};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use bszet_davinci::timetable::Subject;
+use bszet_davinci::Davinci;
+use bszet_notify::telegram::Telegram;
+
+use crate::admin::Admin;
+use crate::ascii::TableFormat;
+use crate::i18n::{Language, Text};
+use crate::subscribers::{Mute, QuietHours, SubscriberStore};
+use crate::{send_notifications, Args};
+
+static DURATION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^(\\d+)\\s*(m|min|h|d)$").unwrap());
+static QUIET_HOURS_REGEX: Lazy<Regex> =
+  Lazy::new(|| Regex::new("^(\\d{1,2})\\s*-\\s*(\\d{1,2})$").unwrap());
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Command {
+  Plan,
+  Mute(Mute),
+  Unmute,
+  /// Whether plan images are sent with a dark background.
+  Dark(bool),
+  /// Whether lessons are sent as sentences instead of a table and images.
+  Plain(bool),
+  Language(Language),
+  Format(TableFormat),
+  Ignore(Subject),
+  Unignore(Subject),
+  /// Hours without notifications, `None` turns them off.
+  Quiet(Option<QuietHours>),
+  Help,
+}
+
+/// Parses a bot command, returns `None` for messages that aren't meant for us.
+pub(crate) fn parse_command(text: &str, now: OffsetDateTime) -> Option<Command> {
+  let text = text.trim();
+  let (command, argument) = text.split_once(' ').unwrap_or((text, ""));
+  // commands in groups are suffixed with the bot name, e.g. /mute@bszet_bot
+  let command = command.split('@').next().unwrap_or(command);
+  let argument = argument.trim();
+
+  Some(match command {
+    "/plan" => Command::Plan,
+    "/unmute" => Command::Unmute,
+    "/dark" => Command::Dark(true),
+    "/light" => Command::Dark(false),
+    "/plain" => Command::Plain(true),
+    "/table" => Command::Plain(false),
+    "/language" | "/sprache" => match Language::parse(argument) {
+      Some(language) => Command::Language(language),
+      None => Command::Help,
+    },
+    "/format" => match TableFormat::from_str(argument, true) {
+      Ok(format) => Command::Format(format),
+      Err(_) => Command::Help,
+    },
+    "/ignore" if !argument.is_empty() => Command::Ignore(Subject::from(argument)),
+    "/unignore" if !argument.is_empty() => Command::Unignore(Subject::from(argument)),
+    "/quiet" if argument == "off" || argument == "aus" => Command::Quiet(None),
+    "/quiet" => match parse_quiet_hours(argument) {
+      Some(quiet_hours) => Command::Quiet(Some(quiet_hours)),
+      None => Command::Help,
+    },
+    "/snooze" => match parse_duration(argument) {
+      Some(duration) => Command::Mute(Mute::Until(now + duration)),
+      None => Command::Help,
+    },
+    "/mute" if argument.is_empty() => Command::Mute(Mute::Indefinitely),
+    "/mute" => {
+      let weekday = argument
+        .strip_prefix("until ")
+        .or_else(|| argument.strip_prefix("bis "))
+        .and_then(parse_weekday);
+
+      match weekday {
+        Some(weekday) => Command::Mute(Mute::Until(next_weekday(now, weekday))),
+        None => Command::Help,
+      }
+    }
+    "/help" | "/start" => Command::Help,
+    _ => return None,
+  })
+}
+
+fn parse_duration(value: &str) -> Option<time::Duration> {
+  let captures = DURATION_REGEX.captures(value)?;
+  let amount = captures.get(1)?.as_str().parse::<i64>().ok()?;
+
+  Some(match captures.get(2)?.as_str() {
+    "m" | "min" => time::Duration::minutes(amount),
+    "h" => time::Duration::hours(amount),
+    _ => time::Duration::days(amount),
+  })
+}
+
+fn parse_quiet_hours(value: &str) -> Option<QuietHours> {
+  let captures = QUIET_HOURS_REGEX.captures(value)?;
+  let from = captures.get(1)?.as_str().parse::<u8>().ok()?;
+  let to = captures.get(2)?.as_str().parse::<u8>().ok()?;
+
+  (from < 24 && to < 24).then_some(QuietHours { from, to })
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+  Some(match value.trim().to_lowercase().as_str() {
+    "monday" | "montag" => Weekday::Monday,
+    "tuesday" | "dienstag" => Weekday::Tuesday,
+    "wednesday" | "mittwoch" => Weekday::Wednesday,
+    "thursday" | "donnerstag" => Weekday::Thursday,
+    "friday" | "freitag" => Weekday::Friday,
+    "saturday" | "samstag" => Weekday::Saturday,
+    "sunday" | "sonntag" => Weekday::Sunday,
+    _ => return None,
+  })
+}
+
+/// Start of the next given weekday, never today.
+fn next_weekday(now: OffsetDateTime, weekday: Weekday) -> OffsetDateTime {
+  let mut date = now.date().next_day().unwrap();
+  while date.weekday() != weekday {
+    date = date.next_day().unwrap();
+  }
+  date.with_time(Time::MIDNIGHT).assume_offset(now.offset())
+}
+
+pub(crate) async fn run(
+  args: watch::Receiver<Args>,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  subscribers: &SubscriberStore,
+) {
+  let mut admin = {
+    let args = args.borrow();
+    Admin::new(
+      args.admin_chat_id,
+      args.admin_alert_threshold,
+      args.max_plan_age(),
+    )
+  };
+  let mut offset = None;
+
+  loop {
+    let updates = match telegram.get_updates(offset, 50).await {
+      Ok(updates) => updates,
+      Err(err) => {
+        error!("Unable to receive bot updates: {}", err);
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        continue;
+      }
+    };
+
+    let args = args.borrow().clone();
+    admin.configure(
+      args.admin_chat_id,
+      args.admin_alert_threshold,
+      args.max_plan_age(),
+    );
+
+    for update in updates {
+      offset = Some(update.update_id + 1);
+
+      let Some((chat_id, text)) = update
+        .message
+        .and_then(|message| Some((message.chat.id, message.text?)))
+      else {
+        continue;
+      };
+
+      // the plan is protected, only answer subscribed chats
+      if !args.chat_ids.contains(&chat_id) {
+        continue;
+      }
+
+      let Some(command) = parse_command(&text, OffsetDateTime::now_utc()) else {
+        continue;
+      };
+
+      info!("Received {:?} from chat {}", command, chat_id);
+
+      if let Err(err) = handle(
+        &args,
+        telegram,
+        davinci,
+        subscribers,
+        &admin,
+        chat_id,
+        command,
+      )
+      .await
+      {
+        error!("Unable to handle command from chat {}: {}", chat_id, err);
+      }
+    }
+  }
+}
+
+async fn handle(
+  args: &Args,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  subscribers: &SubscriberStore,
+  admin: &Admin,
+  chat_id: i64,
+  command: Command,
+) -> anyhow::Result<()> {
+  let language = subscribers.language(chat_id, args.language).await;
+
+  match command {
+    Command::Plan => {
+      send_notifications(
+        args,
+        telegram,
+        davinci,
+        admin,
+        subscribers,
+        None,
+        &[chat_id],
+      )
+      .await
+    }
+    Command::Mute(mute) => {
+      subscribers.set_mute(chat_id, Some(mute)).await?;
+
+      let text = match mute {
+        Mute::Until(until) => language.muted_until(until),
+        Mute::Indefinitely => language.text(Text::Muted).to_string(),
+      };
+
+      telegram.send_text(chat_id, &text).await
+    }
+    Command::Unmute => {
+      subscribers.set_mute(chat_id, None).await?;
+      telegram
+        .send_text(chat_id, language.text(Text::Unmuted))
+        .await
+    }
+    Command::Dark(dark) => {
+      subscribers.set_dark(chat_id, dark).await?;
+
+      let text = match dark {
+        true => Text::Dark,
+        false => Text::Light,
+      };
+
+      telegram.send_text(chat_id, language.text(text)).await
+    }
+    Command::Plain(plain) => {
+      subscribers.set_plain(chat_id, plain).await?;
+
+      let text = match plain {
+        true => Text::Plain,
+        false => Text::Table,
+      };
+
+      telegram.send_text(chat_id, language.text(text)).await
+    }
+    Command::Language(language) => {
+      subscribers.set_language(chat_id, language).await?;
+      telegram
+        .send_text(chat_id, language.text(Text::LanguageChanged))
+        .await
+    }
+    Command::Format(format) => {
+      subscribers
+        .update(chat_id, |chat| chat.table_format = Some(format))
+        .await?;
+      telegram
+        .send_text(chat_id, language.text(Text::FormatChanged))
+        .await
+    }
+    Command::Ignore(subject) => {
+      let text = language.ignored(&subject);
+      subscribers
+        .update(chat_id, |chat| {
+          if !chat.ignored.contains(&subject) {
+            chat.ignored.push(subject);
+          }
+        })
+        .await?;
+
+      telegram.send_text(chat_id, &text).await
+    }
+    Command::Unignore(subject) => {
+      let text = language.unignored(&subject);
+      subscribers
+        .update(chat_id, |chat| {
+          chat.ignored.retain(|other| *other != subject)
+        })
+        .await?;
+
+      telegram.send_text(chat_id, &text).await
+    }
+    Command::Quiet(quiet_hours) => {
+      subscribers
+        .update(chat_id, |chat| chat.quiet_hours = quiet_hours)
+        .await?;
+      telegram
+        .send_text(chat_id, &language.quiet_hours(quiet_hours))
+        .await
+    }
+    Command::Help => {
+      telegram
+        .send_text(chat_id, language.text(Text::Usage))
+        .await
+    }
+  }
+}
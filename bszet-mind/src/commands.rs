@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::Date;
+use tracing::info;
+
+use bszet_davinci::timetable::{validate_timetable, Severity};
+use bszet_davinci::{Davinci, PlanOptions, Row};
+use bszet_image::sample_image;
+use bszet_notify::telegram::Telegram;
+
+use crate::renderer::PlanRenderer;
+use crate::Args;
+
+#[derive(Serialize)]
+struct Dump<'a> {
+  /// RFC 3339 last-modified of each page by date.
+  last_modified: BTreeMap<String, String>,
+  messages: BTreeMap<String, &'a Vec<String>>,
+  rows: Vec<&'a Row>,
+}
+
+/// Crawls the plan once and prints everything parsed from it as JSON.
+pub(crate) async fn fetch(davinci: &Davinci) -> anyhow::Result<()> {
+  davinci.update().await?;
+
+  let data = davinci.data().await;
+  let data = data.as_ref().ok_or_else(|| anyhow!("Nothing crawled"))?;
+
+  let mut rows = data.rows.iter().collect::<Vec<_>>();
+  rows.sort_by_key(|row| (row.date, row.index));
+
+  let dump = Dump {
+    last_modified: data
+      .last_modified_by_date
+      .iter()
+      .map(|(date, last_modified)| Ok((date.to_string(), last_modified.format(&Rfc3339)?)))
+      .collect::<anyhow::Result<_>>()?,
+    messages: data
+      .messages
+      .iter()
+      .map(|(date, messages)| (date.to_string(), messages))
+      .collect(),
+    rows,
+  };
+
+  println!("{}", serde_json::to_string_pretty(&dump)?);
+
+  Ok(())
+}
+
+/// Renders the plan of each date like the images sent to the chats and writes
+/// them as `<date>_<class>.png` to the output directory.
+pub(crate) async fn render(
+  args: &Args,
+  davinci: &Davinci,
+  date: Option<Date>,
+  class: &str,
+  options: PlanOptions,
+  output: &Path,
+) -> anyhow::Result<()> {
+  let dates = match date {
+    Some(date) => vec![date],
+    None => davinci
+      .data()
+      .await
+      .as_ref()
+      .ok_or_else(|| anyhow!("Nothing crawled"))?
+      .dates(),
+  };
+
+  tokio::fs::create_dir_all(output).await?;
+  let renderer = PlanRenderer::new(args).await?;
+  let classes = class.split(',').collect::<Vec<_>>();
+
+  for date in dates {
+    let path = output.join(format!("{}_{}.png", date, class.replace(',', "-")));
+
+    match renderer.image(davinci, date, &classes, options).await? {
+      Some(image) => {
+        tokio::fs::write(&path, image).await?;
+        info!("Rendered {} to {}", date, path.display());
+      }
+      None => info!("No plan for {}", date),
+    }
+  }
+
+  renderer.quit().await;
+
+  Ok(())
+}
+
+/// Prints the problems of each timetable file, errors fail the validation.
+pub(crate) fn validate_timetables(files: &[PathBuf]) -> anyhow::Result<()> {
+  let mut errors = 0;
+
+  for file in files {
+    let problems = std::fs::read_to_string(file)
+      .map_err(anyhow::Error::from)
+      .and_then(|raw| validate_timetable(&raw));
+
+    match problems {
+      Err(err) => {
+        errors += 1;
+        println!("{}: error: {}", file.display(), err);
+      }
+      Ok(problems) if problems.is_empty() => println!("{}: ok", file.display()),
+      Ok(problems) => {
+        for problem in problems {
+          if problem.severity == Severity::Error {
+            errors += 1;
+          }
+          println!("{}: {}", file.display(), problem);
+        }
+      }
+    }
+  }
+
+  match errors {
+    0 => Ok(()),
+    errors => Err(anyhow!("Found {} errors", errors)),
+  }
+}
+
+pub(crate) async fn send_test(
+  telegram: &Telegram,
+  chat_id: i64,
+  image: bool,
+) -> anyhow::Result<()> {
+  telegram
+    .send_text(chat_id, "✅ Testnachricht von bszet-mind")
+    .await?;
+
+  if image {
+    telegram
+      .send_images(chat_id, "🖼️ Testbild", &[sample_image()?])
+      .await?;
+  }
+
+  info!("Sent test message to chat {}", chat_id);
+
+  Ok(())
+}
@@ -0,0 +1,147 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command, CommandFactory, FromArgMatches};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::Args;
+
+/// The `--config` TOML file, its keys are the names of the arguments. The
+/// sections only group them, every argument may be set in any of them:
+///
+/// ```toml
+/// timeout = 30
+///
+/// [channels]
+/// chat_ids = [-100123, -100456]
+/// admin_chat_id = -100789
+///
+/// [classes]
+/// class_tokens = ["IGD21=secret"]
+///
+/// [scheduling]
+/// max_plan_age_hours = 48
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ConfigFile {
+  #[serde(default)]
+  channels: Table,
+  #[serde(default)]
+  classes: Table,
+  #[serde(default)]
+  scheduling: Table,
+  #[serde(flatten)]
+  general: Table,
+}
+
+impl ConfigFile {
+  pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+    toml::from_str(&std::fs::read_to_string(path)?)
+      .map_err(|err| anyhow!("Unable to parse config {}: {}", path.display(), err))
+  }
+
+  fn values(&self) -> impl Iterator<Item = (&String, &Value)> {
+    self
+      .general
+      .iter()
+      .chain(&self.channels)
+      .chain(&self.classes)
+      .chain(&self.scheduling)
+  }
+}
+
+/// Arguments merged from the command line, the environment and the config
+/// file, in this order of precedence. Read again on every reload.
+pub(crate) fn parse_args() -> anyhow::Result<Args> {
+  let args = std::env::args_os().collect::<Vec<OsString>>();
+
+  // errors like missing arguments are reported once the config is merged
+  let matches = Args::command()
+    .ignore_errors(true)
+    .try_get_matches_from(&args)
+    .ok();
+
+  let config = match matches
+    .as_ref()
+    .and_then(|matches| matches.get_one::<PathBuf>("config"))
+  {
+    Some(path) => ConfigFile::load(path)?,
+    None => ConfigFile::default(),
+  };
+
+  let config_args = match &matches {
+    Some(matches) => config_args(&Args::command(), matches, &config)?,
+    None => Vec::new(),
+  };
+
+  // in front of a subcommand, its arguments come after it
+  let merged = args
+    .iter()
+    .take(1)
+    .cloned()
+    .chain(config_args.into_iter().map(OsString::from))
+    .chain(args.iter().skip(1).cloned());
+
+  let matches = Args::command().try_get_matches_from(merged)?;
+  Ok(Args::from_arg_matches(&matches)?)
+}
+
+/// Arguments for the values of the config file that weren't given on the
+/// command line or in the environment.
+pub(crate) fn config_args(
+  command: &Command,
+  matches: &ArgMatches,
+  config: &ConfigFile,
+) -> anyhow::Result<Vec<String>> {
+  let mut args = Vec::new();
+
+  for (key, value) in config.values() {
+    let id = key.replace('-', "_");
+    let arg = command
+      .get_arguments()
+      .find(|arg| arg.get_id() == id.as_str() && arg.get_long().is_some())
+      .ok_or_else(|| anyhow!("Unknown config option {}", key))?;
+
+    if matches!(
+      matches.value_source(&id),
+      Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    ) {
+      continue;
+    }
+
+    let long = arg.get_long().unwrap();
+
+    if matches!(arg.get_action(), ArgAction::SetTrue) {
+      match value {
+        Value::Boolean(true) => args.push(format!("--{long}")),
+        Value::Boolean(false) => {}
+        _ => return Err(anyhow!("{} has to be true or false", key)),
+      }
+      continue;
+    }
+
+    let values = match value {
+      Value::Array(values) => values.iter().collect(),
+      value => vec![value],
+    };
+    for value in values {
+      args.push(format!("--{long}={}", scalar(key, value)?));
+    }
+  }
+
+  Ok(args)
+}
+
+fn scalar(key: &str, value: &Value) -> anyhow::Result<String> {
+  Ok(match value {
+    Value::String(value) => value.clone(),
+    Value::Integer(value) => value.to_string(),
+    Value::Float(value) => value.to_string(),
+    Value::Boolean(value) => value.to_string(),
+    Value::Datetime(value) => value.to_string(),
+    Value::Array(_) | Value::Table(_) => return Err(anyhow!("{} must not be nested", key)),
+  })
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use bszet_davinci::change::Change;
+use bszet_davinci::timetable::Subject;
+use bszet_davinci::Row;
+
+use crate::filter::is_ignored;
+use crate::i18n::{Language, Text};
+
+/// Rows of the given classes that were added or removed since the last
+/// announced plan.
+pub(crate) struct RowChanges<'a> {
+  pub(crate) added: Vec<&'a Row>,
+  pub(crate) removed: Vec<&'a Row>,
+}
+
+pub(crate) fn diff<'a>(
+  old: &'a HashSet<Row>,
+  new: &'a HashSet<Row>,
+  classes: &[&str],
+) -> RowChanges<'a> {
+  let relevant = |row: &&Row| classes.iter().any(|class| row.is_for_class(class));
+
+  let mut added = new.difference(old).filter(relevant).collect::<Vec<&Row>>();
+  let mut removed = old.difference(new).filter(relevant).collect::<Vec<&Row>>();
+
+  added.sort_by_key(|row| (row.date, row.change.lesson()));
+  removed.sort_by_key(|row| (row.date, row.change.lesson()));
+
+  RowChanges { added, removed }
+}
+
+impl RowChanges<'_> {
+  pub(crate) fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty()
+  }
+
+  pub(crate) fn without(&self, ignored: &[Subject]) -> RowChanges<'_> {
+    RowChanges {
+      added: self
+        .added
+        .iter()
+        .filter(|row| !is_ignored(row, ignored))
+        .copied()
+        .collect(),
+      removed: self
+        .removed
+        .iter()
+        .filter(|row| !is_ignored(row, ignored))
+        .copied()
+        .collect(),
+    }
+  }
+
+  pub(crate) fn to_markdown(&self, language: Language) -> Option<String> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let mut out = format!("*{}*", language.text(Text::Changes));
+
+    for row in &self.added {
+      write!(out, "\n+ {}", describe(row)).unwrap();
+    }
+
+    for row in &self.removed {
+      write!(
+        out,
+        "\n− {} ({})",
+        describe(row),
+        language.text(Text::Withdrawn)
+      )
+      .unwrap();
+    }
+
+    Some(out)
+  }
+}
+
+fn describe(row: &Row) -> String {
+  let description = escape_markdown(&format!(
+    "{:0>2}.{:0>2}.: {}",
+    row.date.day(),
+    row.date.month() as u8,
+    row.change
+  ));
+
+  match indicator(&row.change) {
+    Some(indicator) => format!("{indicator} {description}"),
+    None => description,
+  }
+}
+
+/// Shows the kind of change in the notification preview, before the chat is
+/// opened.
+fn indicator(change: &Change) -> Option<&'static str> {
+  match change {
+    Change::Cancel { .. } => Some("🚫"),
+    Change::Replacement { .. } | Change::TeacherChange { .. } => Some("🔁"),
+    Change::RoomChange { .. } => Some("🚪"),
+    Change::Exam { .. } => Some("📝"),
+    _ => None,
+  }
+}
+
+/// Escapes characters with a special meaning in Telegram's legacy Markdown.
+pub(crate) fn escape_markdown(value: &str) -> String {
+  let mut out = String::with_capacity(value.len());
+
+  for char in value.chars() {
+    if matches!(char, '_' | '*' | '`' | '[') {
+      out.push('\\');
+    }
+    out.push(char);
+  }
+
+  out
+}
@@ -0,0 +1,63 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use time::{Date, Duration};
+
+use bszet_davinci::timetable::Subject;
+use bszet_davinci::Row;
+
+use crate::diff::escape_markdown;
+use crate::filter::is_ignored;
+use crate::i18n::{Language, Text};
+
+/// Lists all known changes of the given classes for the school week starting
+/// at `monday`, grouped by day.
+pub(crate) fn weekly_digest(
+  rows: &HashSet<Row>,
+  monday: Date,
+  classes: &[&str],
+  ignored: &[Subject],
+  language: Language,
+) -> String {
+  let friday = monday + Duration::days(4);
+
+  let mut days = BTreeMap::<Date, Vec<&Row>>::new();
+
+  for row in rows {
+    if row.date < monday
+      || row.date > friday
+      || !classes.iter().any(|class| row.is_for_class(class))
+      || is_ignored(row, ignored)
+    {
+      continue;
+    }
+
+    days.entry(row.date).or_default().push(row);
+  }
+
+  let mut out = language.weekly_digest_title(monday, friday);
+
+  if days.is_empty() {
+    write!(out, "\n\n{}", language.text(Text::NoChangesThisWeek)).unwrap();
+    return out;
+  }
+
+  for (date, mut rows) in days {
+    rows.sort_by_key(|row| row.change.lesson());
+
+    write!(
+      out,
+      "\n\n*{} {:0>2}.{:0>2}.*",
+      language.weekday(date.weekday()),
+      date.day(),
+      date.month() as u8
+    )
+    .unwrap();
+
+    for row in rows {
+      write!(out, "\n- {}", escape_markdown(&row.change.to_string())).unwrap();
+    }
+  }
+
+  out
+}
@@ -0,0 +1,60 @@
+use std::str::FromStr;
+
+use bszet_davinci::timetable::{Lesson, Subject};
+use bszet_davinci::Row;
+
+/// Subjects a chat doesn't attend, written as `<chat id>=<subject>;<subject>`
+/// using the subject codes of the substitution plan (e.g. `-123=LK-MA;ETH`).
+#[derive(Clone, Debug)]
+pub(crate) struct SubjectFilter {
+  pub(crate) chat_id: i64,
+  pub(crate) ignored: Vec<Subject>,
+}
+
+impl FromStr for SubjectFilter {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let (chat_id, subjects) = value
+      .split_once('=')
+      .ok_or_else(|| format!("expected <chat id>=<subject>;<subject>, got {value}"))?;
+
+    Ok(Self {
+      chat_id: chat_id.trim().parse().map_err(|err| format!("{err}"))?,
+      ignored: subjects
+        .split(';')
+        .map(str::trim)
+        .filter(|subject| !subject.is_empty())
+        .map(Subject::from)
+        .collect(),
+    })
+  }
+}
+
+pub(crate) fn ignored_subjects(filters: &[SubjectFilter], chat_id: i64) -> &[Subject] {
+  filters
+    .iter()
+    .find(|filter| filter.chat_id == chat_id)
+    .map(|filter| filter.ignored.as_slice())
+    .unwrap_or_default()
+}
+
+pub(crate) fn filter_lessons(day: &[Lesson], ignored: &[Subject]) -> Vec<Lesson> {
+  day
+    .iter()
+    .filter(|lesson| {
+      let subject = match &lesson.subject {
+        Subject::Cancel(subject) => subject.as_ref(),
+        subject => subject,
+      };
+      !ignored.contains(subject)
+    })
+    .cloned()
+    .collect()
+}
+
+/// A row is ignored if every subject it refers to is ignored.
+pub(crate) fn is_ignored(row: &Row, ignored: &[Subject]) -> bool {
+  let subjects = row.change.subjects();
+  !ignored.is_empty() && !subjects.is_empty() && subjects.iter().all(|s| ignored.contains(s))
+}
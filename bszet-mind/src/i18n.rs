@@ -0,0 +1,406 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, OffsetDateTime, Weekday};
+
+use bszet_davinci::timetable::Subject;
+
+use crate::subscribers::QuietHours;
+
+/// Language of the texts sent to the chats, each chat may pick its own with
+/// `/language`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Language {
+  #[default]
+  De,
+  En,
+}
+
+/// Texts without placeholders, see [`Language::text`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Text {
+  Usage,
+  Changes,
+  Withdrawn,
+  Supervisions,
+  Messages,
+  Unapplied,
+  NoChangesThisWeek,
+  Exams,
+  NoCancellations,
+  Subjects,
+  Teachers,
+  Muted,
+  Unmuted,
+  Dark,
+  Light,
+  LanguageChanged,
+  Plain,
+  Table,
+  FormatChanged,
+}
+
+impl Language {
+  pub(crate) fn parse(value: &str) -> Option<Self> {
+    match value.trim().to_lowercase().as_str() {
+      "de" | "deutsch" | "german" => Some(Self::De),
+      "en" | "englisch" | "english" => Some(Self::En),
+      _ => None,
+    }
+  }
+
+  pub(crate) fn text(self, text: Text) -> &'static str {
+    match (self, text) {
+      (Self::De, Text::Usage) => {
+        "Verfügbare Befehle:
+/plan - aktuellen Vertretungsplan senden
+/snooze 2h - Benachrichtigungen pausieren (m, h, d)
+/mute until Monday - bis zu einem Wochentag pausieren
+/mute - bis auf Weiteres pausieren
+/unmute - Benachrichtigungen fortsetzen
+/dark - Vertretungsplan mit dunklem Hintergrund
+/light - Vertretungsplan mit hellem Hintergrund
+/plain - Stunden als Sätze statt Tabelle und Bildern, für Screenreader
+/table - Stunden als Tabelle und Bilder
+/format markdown - Tabelle als ascii oder markdown
+/ignore LK-MA - Fach ausblenden, /unignore LK-MA zeigt es wieder
+/quiet 22-6 - keine Benachrichtigungen zwischen 22 und 6 Uhr (UTC), /quiet off
+/language en - Texte auf Englisch"
+      }
+      (Self::En, Text::Usage) => {
+        "Available commands:
+/plan - send the current substitution plan
+/snooze 2h - pause notifications (m, h, d)
+/mute until Monday - pause until a weekday
+/mute - pause until further notice
+/unmute - resume notifications
+/dark - substitution plan with a dark background
+/light - substitution plan with a light background
+/plain - lessons as sentences instead of a table and images, for screen readers
+/table - lessons as a table and images
+/format markdown - table as ascii or markdown
+/ignore LK-MA - hide a subject, /unignore LK-MA shows it again
+/quiet 22-6 - no notifications between 22 and 6 o'clock (UTC), /quiet off
+/language de - texts in German"
+      }
+      (Self::De, Text::Changes) => "Was hat sich geändert:",
+      (Self::En, Text::Changes) => "What changed:",
+      (Self::De, Text::Withdrawn) => "zurückgenommen",
+      (Self::En, Text::Withdrawn) => "withdrawn",
+      (Self::De, Text::Supervisions) => "Aufsichten:",
+      (Self::En, Text::Supervisions) => "Supervisions:",
+      (Self::De, Text::Messages) => "Nachrichten zum Tag:",
+      (Self::En, Text::Messages) => "Messages of the day:",
+      (Self::De, Text::Unapplied) => "Änderungen, die nicht angewendet werden konnten:",
+      (Self::En, Text::Unapplied) => "Changes that couldn't be applied:",
+      (Self::De, Text::NoChangesThisWeek) => "Keine bekannten Änderungen für die kommende Woche.",
+      (Self::En, Text::NoChangesThisWeek) => "No known changes for the coming week.",
+      (Self::De, Text::Exams) => "Morgen wird geschrieben:",
+      (Self::En, Text::Exams) => "Exams tomorrow:",
+      (Self::De, Text::NoCancellations) => "Keine Ausfälle 🎉",
+      (Self::En, Text::NoCancellations) => "No cancellations 🎉",
+      (Self::De, Text::Subjects) => "Fächer",
+      (Self::En, Text::Subjects) => "Subjects",
+      (Self::De, Text::Teachers) => "Lehrer",
+      (Self::En, Text::Teachers) => "Teachers",
+      (Self::De, Text::Muted) => "🔕 Benachrichtigungen pausiert, /unmute zum Fortsetzen.",
+      (Self::En, Text::Muted) => "🔕 Notifications paused, /unmute to resume.",
+      (Self::De, Text::Unmuted) => "🔔 Benachrichtigungen fortgesetzt.",
+      (Self::En, Text::Unmuted) => "🔔 Notifications resumed.",
+      (Self::De, Text::Dark) => "🌙 Der Vertretungsplan wird ab jetzt dunkel gesendet.",
+      (Self::En, Text::Dark) => "🌙 The substitution plan is sent dark from now on.",
+      (Self::De, Text::Light) => "☀️ Der Vertretungsplan wird ab jetzt hell gesendet.",
+      (Self::En, Text::Light) => "☀️ The substitution plan is sent light from now on.",
+      (Self::De, Text::LanguageChanged) => "🇩🇪 Texte werden ab jetzt auf Deutsch gesendet.",
+      (Self::En, Text::LanguageChanged) => "🇬🇧 Texts are sent in English from now on.",
+      (Self::De, Text::Plain) => "Die Stunden werden ab jetzt als Sätze ohne Bilder gesendet.",
+      (Self::En, Text::Plain) => "Lessons are sent as sentences without images from now on.",
+      (Self::De, Text::Table) => "Die Stunden werden ab jetzt als Tabelle mit Bildern gesendet.",
+      (Self::En, Text::Table) => "Lessons are sent as a table with images from now on.",
+      (Self::De, Text::FormatChanged) => "Das Tabellenformat wurde geändert.",
+      (Self::En, Text::FormatChanged) => "The table format was changed.",
+    }
+  }
+
+  pub(crate) fn weekday(self, weekday: Weekday) -> &'static str {
+    match (self, weekday) {
+      (Self::De, Weekday::Monday) => "Montag",
+      (Self::De, Weekday::Tuesday) => "Dienstag",
+      (Self::De, Weekday::Wednesday) => "Mittwoch",
+      (Self::De, Weekday::Thursday) => "Donnerstag",
+      (Self::De, Weekday::Friday) => "Freitag",
+      (Self::De, Weekday::Saturday) => "Samstag",
+      (Self::De, Weekday::Sunday) => "Sonntag",
+      (Self::En, Weekday::Monday) => "Monday",
+      (Self::En, Weekday::Tuesday) => "Tuesday",
+      (Self::En, Weekday::Wednesday) => "Wednesday",
+      (Self::En, Weekday::Thursday) => "Thursday",
+      (Self::En, Weekday::Friday) => "Friday",
+      (Self::En, Weekday::Saturday) => "Saturday",
+      (Self::En, Weekday::Sunday) => "Sunday",
+    }
+  }
+
+  pub(crate) fn month(self, month: Month) -> &'static str {
+    match (self, month) {
+      (Self::De, Month::January) => "Januar",
+      (Self::De, Month::February) => "Februar",
+      (Self::De, Month::March) => "März",
+      (Self::De, Month::April) => "April",
+      (Self::De, Month::May) => "Mai",
+      (Self::De, Month::June) => "Juni",
+      (Self::De, Month::July) => "Juli",
+      (Self::De, Month::August) => "August",
+      (Self::De, Month::September) => "September",
+      (Self::De, Month::October) => "Oktober",
+      (Self::De, Month::November) => "November",
+      (Self::De, Month::December) => "Dezember",
+      (Self::En, Month::January) => "January",
+      (Self::En, Month::February) => "February",
+      (Self::En, Month::March) => "March",
+      (Self::En, Month::April) => "April",
+      (Self::En, Month::May) => "May",
+      (Self::En, Month::June) => "June",
+      (Self::En, Month::July) => "July",
+      (Self::En, Month::August) => "August",
+      (Self::En, Month::September) => "September",
+      (Self::En, Month::October) => "October",
+      (Self::En, Month::November) => "November",
+      (Self::En, Month::December) => "December",
+    }
+  }
+
+  /// Singular and plural of the units of [`crate::format_duration`], largest
+  /// first.
+  pub(crate) fn duration_units(self) -> [(&'static str, &'static str, u64); 6] {
+    match self {
+      Self::De => [
+        ("einem Jahr", "Jahren", 31_557_600),
+        ("einem Monat", "Monaten", 2_630_016),
+        ("einem Tag", "Tagen", 86400),
+        ("einer Stunde", "Stunden", 3600),
+        ("einer Minute", "Minuten", 60),
+        ("einer Sekunde", "Sekunden", 1),
+      ],
+      Self::En => [
+        ("a year", "years", 31_557_600),
+        ("a month", "months", 2_630_016),
+        ("a day", "days", 86400),
+        ("an hour", "hours", 3600),
+        ("a minute", "minutes", 60),
+        ("a second", "seconds", 1),
+      ],
+    }
+  }
+
+  pub(crate) fn and(self) -> &'static str {
+    match self {
+      Self::De => "und",
+      Self::En => "and",
+    }
+  }
+
+  pub(crate) fn plan_title(self, date: Date, iteration: u8, age: &str) -> String {
+    match self {
+      Self::De => format!(
+        "Vertretungsplan für {} den {}. {} {}, Turnus {}. Zuletzt vor {} aktualisiert.",
+        self.weekday(date.weekday()),
+        date.day(),
+        self.month(date.month()),
+        date.year(),
+        iteration,
+        age
+      ),
+      Self::En => format!(
+        "Substitution plan for {}, {} {} {}, iteration {}. Last updated {} ago.",
+        self.weekday(date.weekday()),
+        self.month(date.month()),
+        date.day(),
+        date.year(),
+        iteration,
+        age
+      ),
+    }
+  }
+
+  pub(crate) fn muted_until(self, until: OffsetDateTime) -> String {
+    let until = format!(
+      "{:0>2}.{:0>2}.{} {:0>2}:{:0>2} UTC",
+      until.day(),
+      until.month() as u8,
+      until.year(),
+      until.hour(),
+      until.minute()
+    );
+
+    match self {
+      Self::De => format!("🔕 Benachrichtigungen pausiert bis {until}."),
+      Self::En => format!("🔕 Notifications paused until {until}."),
+    }
+  }
+
+  pub(crate) fn ignored(self, subject: &Subject) -> String {
+    match self {
+      Self::De => format!("🙈 {subject} wird ab jetzt ausgeblendet."),
+      Self::En => format!("🙈 {subject} is hidden from now on."),
+    }
+  }
+
+  pub(crate) fn unignored(self, subject: &Subject) -> String {
+    match self {
+      Self::De => format!("👀 {subject} wird wieder angezeigt."),
+      Self::En => format!("👀 {subject} is shown again."),
+    }
+  }
+
+  pub(crate) fn quiet_hours(self, quiet_hours: Option<QuietHours>) -> String {
+    match (self, quiet_hours) {
+      (Self::De, None) => "🔔 Ruhezeiten ausgeschaltet.".to_string(),
+      (Self::En, None) => "🔔 Quiet hours turned off.".to_string(),
+      (Self::De, Some(QuietHours { from, to })) => {
+        format!("🌃 Keine Benachrichtigungen zwischen {from} und {to} Uhr (UTC).")
+      }
+      (Self::En, Some(QuietHours { from, to })) => {
+        format!("🌃 No notifications between {from} and {to} o'clock (UTC).")
+      }
+    }
+  }
+
+  pub(crate) fn weekly_digest_title(self, monday: Date, friday: Date) -> String {
+    let title = match self {
+      Self::De => "Wochenübersicht",
+      Self::En => "Week overview",
+    };
+
+    format!(
+      "*{title} {:0>2}.{:0>2}. – {:0>2}.{:0>2}.*",
+      monday.day(),
+      monday.month() as u8,
+      friday.day(),
+      friday.month() as u8
+    )
+  }
+
+  pub(crate) fn stats_title(self, month: Month, year: i32) -> String {
+    let title = match self {
+      Self::De => "Ausfallstatistik",
+      Self::En => "Cancellation statistics",
+    };
+
+    format!("*{title} {:0>2}/{}*", month as u8, year)
+  }
+
+  pub(crate) fn cancellations(self, count: u32) -> String {
+    match self {
+      Self::De => format!("{count} Blöcke sind ausgefallen."),
+      Self::En => format!("{count} blocks were cancelled."),
+    }
+  }
+
+  /// Spelled out name of the subject, for screen readers.
+  pub(crate) fn subject(self, subject: &Subject) -> String {
+    let name = match (self, subject) {
+      (_, Subject::Cancel(subject)) => return self.subject(subject),
+      (Self::De, Subject::GermanBasic) => "Deutsch",
+      (Self::De, Subject::GermanAdvanced) => "Deutsch Leistungskurs",
+      (Self::De, Subject::MathBasic) => "Mathe",
+      (Self::De, Subject::MathAdvanced) => "Mathe Leistungskurs",
+      (Self::De, Subject::EnglishBasic) => "Englisch",
+      (Self::De, Subject::EnglishAdvanced) => "Englisch Leistungskurs",
+      (Self::De, Subject::Art) => "Kunst",
+      (Self::De, Subject::History) => "Geschichte",
+      (Self::De, Subject::French) => "Französisch",
+      (Self::De, Subject::Ethics) => "Ethik",
+      (Self::De, Subject::Russian) => "Russisch",
+      (Self::De, Subject::Chemistry) => "Chemie",
+      (Self::De, Subject::Physics) => "Physik",
+      (Self::De, Subject::PhysicalEducation) => "Sport",
+      (Self::De, Subject::Literature) => "Literatur",
+      (Self::En, Subject::GermanBasic) => "German",
+      (Self::En, Subject::GermanAdvanced) => "German advanced course",
+      (Self::En, Subject::MathBasic) => "Maths",
+      (Self::En, Subject::MathAdvanced) => "Maths advanced course",
+      (Self::En, Subject::EnglishBasic) => "English",
+      (Self::En, Subject::EnglishAdvanced) => "English advanced course",
+      (Self::En, Subject::Art) => "Art",
+      (Self::En, Subject::History) => "History",
+      (Self::En, Subject::French) => "French",
+      (Self::En, Subject::Ethics) => "Ethics",
+      (Self::En, Subject::Russian) => "Russian",
+      (Self::En, Subject::Chemistry) => "Chemistry",
+      (Self::En, Subject::Physics) => "Physics",
+      (Self::En, Subject::PhysicalEducation) => "PE",
+      (Self::En, Subject::Literature) => "Literature",
+      // learning fields and mapped subjects are named the same in both
+      (_, subject) => return subject.to_string().replace("LF ", "Lernfeld "),
+    };
+
+    name.to_string()
+  }
+
+  /// "3. Stunde Mathe entfällt", see [`crate::ascii::sentences`].
+  pub(crate) fn lesson_sentence(
+    self,
+    lesson: u8,
+    subject: &str,
+    cancelled: bool,
+    place: Option<&str>,
+    teachers: &str,
+  ) -> String {
+    let mut out = match self {
+      Self::De => format!("{lesson}. Stunde {subject}"),
+      Self::En => format!("Lesson {lesson}: {subject}"),
+    };
+
+    if cancelled {
+      out.push_str(match self {
+        Self::De => " entfällt",
+        Self::En => " is cancelled",
+      });
+      return out;
+    }
+
+    if let Some(place) = place {
+      match self {
+        Self::De => write!(out, " in Raum {place}"),
+        Self::En => write!(out, " in room {place}"),
+      }
+      .unwrap();
+    }
+
+    if !teachers.is_empty() {
+      match self {
+        Self::De => write!(out, " bei {teachers}"),
+        Self::En => write!(out, " with {teachers}"),
+      }
+      .unwrap();
+    }
+
+    out
+  }
+
+  pub(crate) fn exam(self) -> &'static str {
+    match self {
+      Self::De => "Prüfung",
+      Self::En => "exam",
+    }
+  }
+
+  /// Header of the Markdown table, see [`crate::ascii::markdown_table`].
+  pub(crate) fn table_header(self) -> [&'static str; 7] {
+    match self {
+      Self::De => [
+        "Std.",
+        "Zeit",
+        "",
+        "Fach",
+        "Raum",
+        "Lehrkraft",
+        "Mitteilung",
+      ],
+      Self::En => ["No.", "Time", "", "Subject", "Room", "Teacher", "Notice"],
+    }
+  }
+}
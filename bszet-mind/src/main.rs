@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
-use std::future::IntoFuture;
 use std::iter::once;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -13,48 +13,119 @@ use axum::http::header::AUTHORIZATION;
 use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
-use axum::{Extension, Router};
-use clap::{arg, Parser};
+use axum::{middleware, Extension, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use clap::{Parser, Subcommand};
+use futures_util::future::try_join_all;
 use http_body_util::{BodyExt, Empty, Full};
 use include_dir::{include_dir, Dir};
 use reqwest::Url;
-use time::{Date, OffsetDateTime, Weekday};
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, Time, Weekday};
 use tokio::net::TcpListener;
 use tokio::select;
+use tokio::sync::watch;
 use tokio::time::Instant;
+use tower_http::compression::CompressionLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::sensitive_headers::SetSensitiveRequestHeadersLayer;
 use tower_http::trace::TraceLayer;
-use tower_http::validate_request::ValidateRequestHeaderLayer;
-use tracing::{error, info, Level};
-use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing::{error, info, info_span, warn};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter};
 
-use bszet_davinci::Davinci;
-use bszet_image::WebToImageConverter;
+use bszet_davinci::bell::BellSchedule;
+use bszet_davinci::change::Change;
+use bszet_davinci::teachers::TeacherNames;
+use bszet_davinci::timetable::{import_timetable_pdf, Subject, SubjectMapping};
+use bszet_davinci::{Davinci, Entrypoint, PlanOptions, Row, Theme};
+use bszet_image::{convert, fit_limits, stitch, Capture, ImageLimits, OutputFormat};
 use bszet_notify::telegram::Telegram;
 
-use crate::api::davinci::{html_plan, timetable};
-use crate::ascii::table;
+use crate::admin::Admin;
+use crate::allowlist::{allowlist, Network};
+use crate::api::auth::{ApiToken, Auth, ClassToken, Jwt};
+use crate::api::davinci::{applied, classes, dates, html_plan, timetable, unapplied, week};
+use crate::api::health::{healthz, heartbeat};
+use crate::api::log::{log_level, set_log_level};
+use crate::ascii::{sentences, TableFormat};
+use crate::config::parse_args;
+use crate::diff::{diff, escape_markdown};
+use crate::digest::weekly_digest;
+use crate::filter::{filter_lessons, ignored_subjects, is_ignored, SubjectFilter};
+use crate::i18n::{Language, Text};
+use crate::notification::{NotificationTemplate, Section};
+use crate::rate_limit::{rate_limit, RateLimiter};
+use crate::renderer::{plan_url, pool, week_url, ImageFormat, PlanRenderer, Renderer};
+use crate::secrets::Secret;
+use crate::state::StateStore;
+use crate::stats::{monthly_summary, StatsStore};
+use crate::subscribers::SubscriberStore;
+use crate::tenants::{Tenant, Tenants};
 
+mod admin;
+mod allowlist;
 mod api;
 mod ascii;
+mod bot;
+mod commands;
+mod config;
+mod diff;
+mod digest;
+mod filter;
+mod i18n;
+mod notification;
+mod rate_limit;
+mod renderer;
+mod retry;
+mod secrets;
+mod sighup;
+mod state;
+mod stats;
+mod subscribers;
+mod tenants;
+mod timetables;
 
 #[cfg(test)]
 mod tests;
 
+const CLASS: &str = "IGD21";
+const CLASSES: &[&str] = &[CLASS];
+/// Pixels between the plans of a stitched image.
+const STITCH_GAP: u32 = 48;
+
 static STATIC_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/static");
 
 #[derive(Parser, Clone)]
 #[command(author, version, about, long_about)]
 struct Args {
+  /// TOML file with defaults for all other options, overridden by arguments
+  /// and environment variables.
+  #[arg(long, env = "BSZET_MIND_CONFIG")]
+  config: Option<PathBuf>,
+  /// First page of the plan, or a directory index ending with `/` to discover
+  /// all plan documents. Multiple plans can be given, each optionally tagged
+  /// with its plan group like `BS=https://…`.
   #[arg(
     long,
     short,
     env = "BSZET_MIND_ENTRYPOINT",
+    value_delimiter = ',',
     default_value = "https://geschuetzt.bszet.de/s-lk-vw/Vertretungsplaene/V_PlanBGy/V_DC_001.html"
   )]
-  entrypoint: Url,
+  entrypoint: Vec<Entrypoint>,
+  /// Seconds to wait for a connection to the substitution plan.
+  #[arg(long, env = "BSZET_MIND_CONNECT_TIMEOUT", default_value = "10")]
+  connect_timeout: u64,
+  /// Seconds to wait for a whole request to the substitution plan.
+  #[arg(long, env = "BSZET_MIND_TIMEOUT", default_value = "60")]
+  timeout: u64,
+  /// HTTP(S) or SOCKS5 proxy for requests to the substitution plan, e.g.
+  /// `socks5://127.0.0.1:1080`.
+  #[arg(long, env = "BSZET_MIND_PROXY")]
+  proxy: Option<Url>,
   #[arg(
     long,
     short,
@@ -65,7 +136,6 @@ struct Args {
   username: Option<String>,
   #[arg(
     long,
-    short,
     env = "BSZET_MIND_USERNAME_FILE",
     conflicts_with = "username",
     required_unless_present = "username"
@@ -81,7 +151,6 @@ struct Args {
   password: Option<String>,
   #[arg(
     long,
-    short,
     env = "BSZET_MIND_PASSWORD_FILE",
     conflicts_with = "password",
     required_unless_present = "password"
@@ -97,7 +166,6 @@ struct Args {
   telegram_token: Option<String>,
   #[arg(
     long,
-    short,
     env = "BSZET_MIND_TELEGRAM_TOKEN_FILE",
     conflicts_with = "telegram_token",
     required_unless_present = "telegram_token"
@@ -112,6 +180,72 @@ struct Args {
     default_value = "http://localhost:4444"
   )]
   gecko_driver_url: Url,
+  /// Seconds a single WebDriver operation may take, so a hung browser can't
+  /// hold back the notifications.
+  #[arg(long, env = "BSZET_MIND_WEBDRIVER_TIMEOUT", default_value = "30")]
+  webdriver_timeout: u64,
+  /// Retries of failed WebDriver operations, each with a new session.
+  #[arg(long, env = "BSZET_MIND_WEBDRIVER_RETRIES", default_value = "1")]
+  webdriver_retries: u32,
+  /// Device pixels per css pixel of WebDriver screenshots.
+  #[arg(long, env = "BSZET_MIND_WEBDRIVER_SCALE", default_value = "1")]
+  webdriver_scale: f64,
+  /// WebDriver sessions rendering the dates of the plan in parallel.
+  #[arg(long, env = "BSZET_MIND_WEBDRIVER_SESSIONS", default_value = "1")]
+  webdriver_sessions: usize,
+  /// Seconds rendering a single date may take, including waiting for a
+  /// session and retries.
+  #[arg(long, env = "BSZET_MIND_RENDER_TIMEOUT", default_value = "120")]
+  render_timeout: u64,
+  /// How plan images are drawn, the native renderer doesn't need a WebDriver
+  /// except for pdfs.
+  #[arg(
+    long,
+    env = "BSZET_MIND_RENDERER",
+    value_enum,
+    default_value = "webdriver"
+  )]
+  renderer: Renderer,
+  /// Encoding of the plan images sent to the chats.
+  #[arg(
+    long,
+    env = "BSZET_MIND_IMAGE_FORMAT",
+    value_enum,
+    default_value = "png"
+  )]
+  image_format: ImageFormat,
+  /// Layout of the lessons in the Telegram text, Markdown for chats bridged to
+  /// Discord or Matrix.
+  #[arg(
+    long,
+    env = "BSZET_MIND_TELEGRAM_TABLE_FORMAT",
+    value_enum,
+    default_value = "ascii"
+  )]
+  telegram_table_format: TableFormat,
+  /// Widest line of the monospace table, Telegram on phones fits about 40
+  /// characters.
+  #[arg(long, env = "BSZET_MIND_TABLE_WIDTH", default_value = "40")]
+  table_width: usize,
+  /// Language of the texts sent to the chats, each chat may pick its own.
+  #[arg(long, env = "BSZET_MIND_LANGUAGE", value_enum, default_value = "de")]
+  language: Language,
+  /// Sections of the notification text and their order.
+  #[arg(
+    long,
+    env = "BSZET_MIND_NOTIFICATION_SECTIONS",
+    value_enum,
+    value_delimiter = ',',
+    default_value = "changes,plan,supervisions,messages,unapplied"
+  )]
+  notification_sections: Vec<Section>,
+  /// TrueType or OpenType font of the native renderer.
+  #[arg(
+    long,
+    env = "BSZET_MIND_FONT",
+    default_value = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"
+  )]
+  font: PathBuf,
   #[arg(
     long,
     short,
@@ -126,40 +260,255 @@ struct Args {
     default_value = "127.0.0.1:8081"
   )]
   internal_listen_addr: SocketAddr,
+  /// PEM encoded certificate chain to serve both listeners via https, the
+  /// internal url has to be adjusted accordingly.
+  #[arg(long, env = "BSZET_MIND_TLS_CERT", requires = "tls_key")]
+  tls_cert: Option<PathBuf>,
+  /// PEM encoded private key of the certificate.
+  #[arg(long, env = "BSZET_MIND_TLS_KEY", requires = "tls_cert")]
+  tls_key: Option<PathBuf>,
   #[arg(
     long,
     env = "BSZET_MIND_INTERNAL_URL",
     default_value = "http://127.0.0.1:8081"
   )]
   internal_url: Url,
+  /// Addresses or networks (CIDR) allowed to access the internal listener,
+  /// everyone if empty.
+  #[arg(long, env = "BSZET_MIND_INTERNAL_ALLOWED_IPS", value_delimiter = ',')]
+  internal_allowed_ips: Vec<Network>,
   #[arg(
     long,
     env = "BSZET_MIND_API_TOKEN",
     conflicts_with = "api_token_file",
-    required_unless_present = "api_token_file"
+    required_unless_present_any = ["api_token_file", "jwt_secret", "jwt_public_key", "once"]
   )]
   api_token: Option<String>,
   #[arg(
     long,
     env = "BSZET_MIND_API_TOKEN_FILE",
     conflicts_with = "api_token",
-    required_unless_present = "api_token"
+    required_unless_present_any = ["api_token", "jwt_secret", "jwt_public_key", "once"]
   )]
   api_token_file: Option<String>,
+  /// Accepts HS256 signed jwts with this secret in addition to the api token.
+  #[arg(long, env = "BSZET_MIND_JWT_SECRET", conflicts_with = "jwt_public_key")]
+  jwt_secret: Option<String>,
+  /// Tokens only allowed to read the plan of a class, e.g. `IGD21=secret`.
+  #[arg(long, env = "BSZET_MIND_CLASS_TOKENS", value_delimiter = ',')]
+  class_tokens: Vec<ClassToken>,
+  /// PEM encoded public key to accept RS256 signed jwts in addition to the api token.
+  #[arg(long, env = "BSZET_MIND_JWT_PUBLIC_KEY", conflicts_with = "jwt_secret")]
+  jwt_public_key: Option<PathBuf>,
+  #[arg(long, env = "BSZET_MIND_JWT_ISSUER")]
+  jwt_issuer: Option<String>,
+  #[arg(long, env = "BSZET_MIND_JWT_AUDIENCE")]
+  jwt_audience: Option<String>,
+  #[arg(long, env = "BSZET_MIND_PDF")]
+  pdf: bool,
+  /// Keeps a pdf of every rendered plan as `<dir>/<date>/<HH-MM-SS>.pdf`.
+  #[arg(long, env = "BSZET_MIND_PDF_ARCHIVE_DIR")]
+  pdf_archive_dir: Option<PathBuf>,
+  /// Sends the plans of all dates as one image instead of an album, each plan
+  /// keeps its date as heading.
+  #[arg(long, env = "BSZET_MIND_STITCH")]
+  stitch: bool,
+  /// Leaves the rows of other classes out of the rendered plans.
+  #[arg(long, env = "BSZET_MIND_COLLAPSE_ROWS")]
+  collapse_rows: bool,
+  #[arg(long, env = "BSZET_MIND_ADMIN_CHAT_ID")]
+  admin_chat_id: Option<i64>,
+  #[arg(long, env = "BSZET_MIND_ADMIN_ALERT_THRESHOLD", default_value = "3")]
+  admin_alert_threshold: u32,
+  /// Hours after which an unmodified plan triggers an admin alert on school days,
+  /// disabled if unset.
+  #[arg(long, env = "BSZET_MIND_MAX_PLAN_AGE_HOURS")]
+  max_plan_age_hours: Option<u64>,
+  /// TOML file with additional subject codes.
+  #[arg(long, env = "BSZET_MIND_SUBJECT_MAPPING")]
+  subject_mapping: Option<PathBuf>,
+  /// TOML file with the full names of teachers by their abbreviation.
+  #[arg(long, env = "BSZET_MIND_TEACHER_NAMES")]
+  teacher_names: Option<PathBuf>,
+  /// TOML file with the start and end time of each block.
+  #[arg(long, env = "BSZET_MIND_BELL_SCHEDULE")]
+  bell_schedule: Option<PathBuf>,
+  /// Directory with `<class>.toml` base timetables, reloaded on changes.
+  #[arg(long, env = "BSZET_MIND_TIMETABLE_DIR")]
+  timetable_dir: Option<PathBuf>,
+  /// Directory to archive the raw html of crawled pages to, e.g. a mounted
+  /// bucket, for reproducing parsing bugs later.
+  #[arg(long, env = "BSZET_MIND_ARCHIVE_DIR")]
+  archive_dir: Option<PathBuf>,
+  /// Archived day (`<archive dir>/<YYYY-MM-DD>`) to read the plan from instead
+  /// of the upstream server.
+  #[arg(long, env = "BSZET_MIND_REPLAY", conflicts_with = "archive_dir")]
+  replay: Option<PathBuf>,
+  /// Only replay snapshots taken until this time of the day (`HH:MM`, UTC).
+  #[arg(long, env = "BSZET_MIND_REPLAY_UNTIL", requires = "replay", value_parser = parse_time)]
+  replay_until: Option<Time>,
+  #[arg(long, env = "BSZET_MIND_STATE_FILE")]
+  state_file: Option<PathBuf>,
+  #[arg(long, env = "BSZET_MIND_SUBJECT_FILTERS", value_delimiter = ',')]
+  subject_filters: Vec<SubjectFilter>,
+  #[arg(long, env = "BSZET_MIND_BOT")]
+  bot: bool,
+  /// Crawls and notifies a single time and exits instead of serving the api,
+  /// for scheduling by cron or systemd timers. Fails if crawling or sending
+  /// notifications failed.
+  #[arg(long, env = "BSZET_MIND_ONCE", conflicts_with_all = ["bot", "tenants"])]
+  once: bool,
+  #[arg(long, env = "BSZET_MIND_SUBSCRIBERS_FILE")]
+  subscribers_file: Option<PathBuf>,
+  /// Hour (UTC) on sundays to send a digest of the coming week, disabled if unset.
+  #[arg(long, env = "BSZET_MIND_WEEKLY_DIGEST_HOUR")]
+  weekly_digest_hour: Option<u8>,
+  /// Attaches a screenshot of the week view to the weekly digest, needs the
+  /// WebDriver.
+  #[arg(long, env = "BSZET_MIND_WEEKLY_DIGEST_IMAGE")]
+  weekly_digest_image: bool,
+  #[command(subcommand)]
+  command: Option<Commands>,
+  /// Hour (UTC) to remind of exams on the next day, disabled if unset.
+  #[arg(long, env = "BSZET_MIND_EXAM_REMINDER_HOUR")]
+  exam_reminder_hour: Option<u8>,
+  #[arg(long, env = "BSZET_MIND_STATS_FILE")]
+  stats_file: Option<PathBuf>,
+  /// Hour (UTC) on the first of each month to send the cancellation statistics
+  /// of the previous month, disabled if unset.
+  #[arg(long, env = "BSZET_MIND_MONTHLY_STATS_HOUR")]
+  monthly_stats_hour: Option<u8>,
+  /// TOML file with further schools served by this instance, their api is
+  /// available below `/<tenant>/davinci`.
+  #[arg(long, env = "BSZET_MIND_TENANTS")]
+  tenants: Option<PathBuf>,
+  /// Requests per minute a single client address may send to the public api,
+  /// unlimited if unset.
+  #[arg(long, env = "BSZET_MIND_RATE_LIMIT")]
+  rate_limit: Option<u32>,
+}
+
+#[derive(Subcommand, Clone)]
+enum Commands {
+  /// Converts the official PDF timetable of a class into a `<class>.toml`
+  /// for the timetable directory, printed for review unless an output is given.
+  ImportTimetable {
+    url: Url,
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+  },
+  /// Crawls the plan once and prints the parsed rows, messages and
+  /// last-modified of each page as JSON, without sending anything.
+  Fetch,
+  /// Crawls the plan once and writes the rendered plan images to a directory,
+  /// without sending anything.
+  Render {
+    /// Only render this date (`YYYY-MM-DD`) instead of all dates on the plan.
+    #[arg(long, value_parser = parse_date)]
+    date: Option<Date>,
+    /// Comma separated classes to render.
+    #[arg(long, default_value = CLASS)]
+    class: String,
+    /// Render with a dark background.
+    #[arg(long)]
+    dark: bool,
+    /// Leave out the rows of other classes.
+    #[arg(long)]
+    collapse: bool,
+    output: PathBuf,
+  },
+  /// Checks `<class>.toml` timetables for duplicate lessons, unknown subjects
+  /// and missing rooms, fails if any errors were found.
+  ValidateTimetable {
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+  },
+  /// Sends a test message to a chat, to verify the bot token and chat id.
+  SendTest {
+    #[arg(allow_negative_numbers = true)]
+    chat_id: i64,
+    /// Also sends a sample image.
+    #[arg(long)]
+    image: bool,
+  },
+}
+
+impl Args {
+  fn max_plan_age(&self) -> Option<Duration> {
+    self
+      .max_plan_age_hours
+      .map(|hours| Duration::from_secs(hours * 60 * 60))
+  }
+}
+
+fn parse_time(value: &str) -> Result<Time, time::error::Parse> {
+  Time::parse(value, format_description!("[hour]:[minute]"))
+}
+
+fn parse_date(value: &str) -> Result<Date, time::error::Parse> {
+  Date::parse(value, format_description!("[year]-[month]-[day]"))
+}
+
+/// Loads the subject mapping, teacher names and bell schedule, options that
+/// were removed fall back to the defaults.
+fn install_settings(args: &Args) -> anyhow::Result<()> {
+  match &args.subject_mapping {
+    Some(path) => SubjectMapping::load(path)
+      .map_err(|err| anyhow!("Unable to load subject mapping {}: {}", path.display(), err))?,
+    None => SubjectMapping::default(),
+  }
+  .install();
+
+  match &args.teacher_names {
+    Some(path) => TeacherNames::load(path)
+      .map_err(|err| anyhow!("Unable to load teacher names {}: {}", path.display(), err))?,
+    None => TeacherNames::default(),
+  }
+  .install();
+
+  match &args.bell_schedule {
+    Some(path) => BellSchedule::load(path)
+      .map_err(|err| anyhow!("Unable to load bell schedule {}: {}", path.display(), err))?,
+    None => BellSchedule::default(),
+  }
+  .install();
+
+  Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-  let args = Args::parse();
+  let args = match parse_args() {
+    Ok(args) => args,
+    Err(err) => match err.downcast::<clap::Error>() {
+      Ok(err) => err.exit(),
+      Err(err) => return Err(err),
+    },
+  };
+
+  // RUST_LOG style filter, adjustable at runtime via the api
+  let (filter, log_handle) = reload::Layer::new(
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+  );
+
+  // subcommands print their output to stdout
+  let writer = if args.command.is_some() {
+    BoxMakeWriter::new(std::io::stderr)
+  } else {
+    BoxMakeWriter::new(std::io::stdout)
+  };
 
   tracing_subscriber::registry()
+    .with(filter)
     .with(
       tracing_subscriber::fmt::Layer::new()
-        .with_writer(std::io::stdout.with_max_level(Level::INFO))
+        .with_writer(writer)
         .compact(),
     )
     .init();
 
+  install_settings(&args)?;
+
   let args2 = args.clone();
 
   let password = match args.password {
@@ -172,58 +521,319 @@ async fn main() -> anyhow::Result<()> {
     Some(username) => username,
   };
 
-  let api_token = match args.api_token {
-    None => tokio::fs::read_to_string(args.api_token_file.unwrap()).await?,
-    Some(api_token) => api_token,
+  let api_token = match (args.api_token, &args.api_token_file) {
+    (Some(api_token), _) => Some(api_token),
+    (None, Some(path)) => Some(tokio::fs::read_to_string(path).await?),
+    (None, None) => None,
   };
 
+  let jwt = match (&args.jwt_secret, &args.jwt_public_key) {
+    (Some(secret), _) => Some(Jwt::hs256(secret)),
+    (None, Some(path)) => Some(
+      Jwt::rs256(&tokio::fs::read(path).await?)
+        .map_err(|err| anyhow!("Unable to load jwt public key {}: {}", path.display(), err))?,
+    ),
+    (None, None) => None,
+  }
+  .map(|jwt| Arc::new(jwt.with_claims(args.jwt_issuer.as_deref(), args.jwt_audience.as_deref())));
+
   let telegram_token = match args.telegram_token {
     None => tokio::fs::read_to_string(args.telegram_token_file.unwrap()).await?,
     Some(telegram_token) => telegram_token,
   };
 
-  let davinci = Arc::new(Davinci::new(args.entrypoint.clone(), username, password));
+  let mut client = reqwest::Client::builder()
+    .connect_timeout(Duration::from_secs(args.connect_timeout))
+    .timeout(Duration::from_secs(args.timeout));
+  if let Some(proxy) = &args.proxy {
+    client = client.proxy(reqwest::Proxy::all(proxy.clone())?);
+  }
+  let client = client.build()?;
+
+  let mut builder = Davinci::builder(username.clone(), password.clone()).client(client.clone());
+  for entrypoint in &args.entrypoint {
+    builder = builder.entrypoint(entrypoint.clone());
+  }
+  if let Some(dir) = &args.archive_dir {
+    builder = builder.archive(dir.clone());
+  }
+  if let Some(dir) = &args.replay {
+    builder = builder.replay(dir.clone(), args.replay_until);
+  }
+  let davinci = Arc::new(builder.build());
+
+  if let Some(Commands::ImportTimetable { url, output }) = &args.command {
+    let timetable = import_timetable_pdf(&davinci.download(url.clone()).await?)?;
+
+    match output {
+      None => print!("{timetable}"),
+      Some(output) => tokio::fs::write(output, timetable).await?,
+    }
+
+    return Ok(());
+  }
+
+  if let Some(Commands::ValidateTimetable { files }) = &args.command {
+    return commands::validate_timetables(files);
+  }
+
+  if let Some(Commands::SendTest { chat_id, image }) = &args.command {
+    let telegram = Telegram::new(&telegram_token)?;
+    return commands::send_test(&telegram, *chat_id, *image).await;
+  }
+
+  if let Some(Commands::Fetch) = &args.command {
+    return commands::fetch(&davinci).await;
+  }
+
+  if let Some(dir) = args.timetable_dir.clone() {
+    timetables::load(davinci.clone(), dir)?;
+  }
+
+  let telegram = Arc::new(Telegram::new(&telegram_token)?);
+  let current_api_token: ApiToken = Arc::new(std::sync::RwLock::new(api_token.clone()));
+
+  let mut secrets = Vec::new();
+  if let Some(path) = &args2.username_file {
+    let davinci = davinci.clone();
+    secrets.push(Secret::new(path.clone(), username, move |value| {
+      davinci.set_username(value);
+      Ok(())
+    }));
+  }
+  if let Some(path) = &args2.password_file {
+    let davinci = davinci.clone();
+    secrets.push(Secret::new(path.clone(), password, move |value| {
+      davinci.set_password(value);
+      Ok(())
+    }));
+  }
+  if let Some(path) = &args2.telegram_token_file {
+    let telegram = telegram.clone();
+    secrets.push(Secret::new(path.into(), telegram_token, move |value| {
+      telegram.set_token(&value)
+    }));
+  }
+  if let Some(path) = &args2.api_token_file {
+    let current_api_token = current_api_token.clone();
+    let api_token = api_token.unwrap_or_default();
+    secrets.push(Secret::new(path.into(), api_token, move |value| {
+      *current_api_token.write().unwrap() = Some(value);
+      Ok(())
+    }));
+  }
+  if !secrets.is_empty() {
+    tokio::spawn(async move {
+      if let Err(err) = secrets::watch(secrets).await {
+        error!("Unable to watch secrets: {}", err);
+      }
+    });
+  }
 
   let davinci2 = davinci.clone();
+  let stats = Arc::new(StatsStore::load(args.stats_file.clone()).await);
+  let subscribers = Arc::new(SubscriberStore::load(args.subscribers_file.clone()).await);
 
-  let router = Router::new()
-    .route("/davinci/:date/:class", get(timetable))
+  let mut tenants = HashMap::new();
+  if let Some(path) = &args.tenants {
+    let configs = Tenant::load(path)
+      .map_err(|err| anyhow!("Unable to load tenants {}: {}", path.display(), err))?;
+
+    for (name, tenant) in configs {
+      let davinci = Arc::new(
+        tenant
+          .davinci(client.clone())
+          .await
+          .map_err(|err| anyhow!("Unable to set up tenant {}: {}", name, err))?,
+      );
+
+      if let Some(dir) = tenant.timetable_dir.clone() {
+        timetables::load(davinci.clone(), dir)?;
+      }
+
+      let mut args = args2.clone();
+      args.chat_ids = tenant.chat_ids;
+      args.state_file = tenant.state_file;
+      args.subscribers_file = None;
+      args.stats_file = None;
+      args.pdf_archive_dir = args.pdf_archive_dir.map(|dir| dir.join(&name));
+      args.internal_url = args.internal_url.join(&format!("{name}/"))?;
+
+      spawn_iterations(
+        watch::channel(args).1,
+        telegram.clone(),
+        davinci.clone(),
+        Arc::new(SubscriberStore::load(None).await),
+        Arc::new(StatsStore::load(None).await),
+      )
+      .await;
+
+      info!("Serving tenant {}", name);
+      tenants.insert(name, davinci);
+    }
+  }
+  let tenants: Tenants = Arc::new(tenants);
+
+  let tenant_router = plan_routes()
+    .route("/davinci/changes", get(api::davinci::changes))
+    .route("/davinci/now/:class", get(api::davinci::now))
+    .route("/davinci/week/:class", get(api::davinci::week_json))
+    .route_layer(middleware::from_fn_with_state(
+      tenants.clone(),
+      tenants::select,
+    ));
+
+  let mut router = api_routes(tenant_router)
     .layer(Extension(davinci2.clone()))
-    .layer(ValidateRequestHeaderLayer::bearer(&api_token))
+    .layer(Extension(stats.clone()))
+    .layer(Extension(subscribers.clone()))
+    .layer(Extension(log_handle))
+    .layer(middleware::from_fn_with_state(
+      Auth {
+        token: current_api_token,
+        jwt,
+        class_tokens: Arc::new(args.class_tokens.clone()),
+      },
+      api::auth::bearer,
+    ))
     .layer(SetSensitiveRequestHeadersLayer::new(once(AUTHORIZATION)))
-    .layer(TraceLayer::new_for_http());
+    // the frontend asks for the api token itself
+    .route("/", get(frontend))
+    .route("/static/*path", get(static_path))
+    .layer(CompressionLayer::new());
 
-  let internal_router = Router::new()
-    .route("/davinci/:date", get(html_plan))
+  if let Some(per_minute) = args.rate_limit {
+    router = router.layer(middleware::from_fn_with_state(
+      Arc::new(RateLimiter::new(per_minute)),
+      rate_limit,
+    ));
+  }
+
+  let router = router
+    .layer(PropagateRequestIdLayer::x_request_id())
+    .layer(TraceLayer::new_for_http().make_span_with(request_span))
+    .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
+
+  let mut internal_router = internal_plan_routes()
     .route("/static/*path", get(static_path))
+    .nest(
+      "/:tenant",
+      internal_plan_routes().route_layer(middleware::from_fn_with_state(tenants, tenants::select)),
+    )
     .layer(Extension(davinci2.clone()))
-    .layer(TraceLayer::new_for_http());
+    .layer(CompressionLayer::new());
 
-  let telegram = Telegram::new(&telegram_token)?;
+  if !args.internal_allowed_ips.is_empty() {
+    internal_router = internal_router.layer(middleware::from_fn_with_state(
+      Arc::new(args.internal_allowed_ips.clone()),
+      allowlist,
+    ));
+  }
 
-  tokio::spawn(async move {
-    let davinci2 = davinci2;
-    loop {
-      if let Err(err) = iteration(&args2, &telegram, &davinci2).await {
-        error!("Error while executing loop: {}", err);
+  // healthchecks bypass the allowlist and aren't traced
+  let internal_router = internal_router
+    .layer(PropagateRequestIdLayer::x_request_id())
+    .layer(TraceLayer::new_for_http().make_span_with(request_span))
+    .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+    .route("/healthz", get(healthz));
+
+  if args.once {
+    let mut admin = Admin::new(
+      args.admin_chat_id,
+      args.admin_alert_threshold,
+      args2.max_plan_age(),
+    );
+    let mut state = StateStore::load(args.state_file.clone()).await;
+
+    return iteration(
+      &args2,
+      &telegram,
+      &davinci2,
+      &mut admin,
+      &mut state,
+      &subscribers,
+      &stats,
+    )
+    .await;
+  }
+
+  let tls = match (&args.tls_cert, &args.tls_key) {
+    (Some(cert), Some(key)) => Some(
+      RustlsConfig::from_pem_file(cert, key)
+        .await
+        .map_err(|err| anyhow!("Unable to load tls certificate {}: {}", cert.display(), err))?,
+    ),
+    _ => None,
+  };
+
+  if let Some(Commands::Render {
+    date,
+    class,
+    dark,
+    collapse,
+    output,
+  }) = &args.command
+  {
+    // the browser loads the plan from the internal api
+    tokio::spawn(serve(
+      args.internal_listen_addr,
+      internal_router,
+      tls.clone(),
+    ));
+
+    davinci.update().await?;
+    return commands::render(
+      &args2,
+      &davinci,
+      *date,
+      class,
+      PlanOptions {
+        theme: theme(*dark),
+        navigation: false,
+        collapse: *collapse,
+      },
+      output,
+    )
+    .await;
+  }
+
+  let (args_tx, args_rx) = watch::channel(args2);
+
+  if args.bot {
+    let args = args_rx.clone();
+    let telegram = telegram.clone();
+    let davinci = davinci.clone();
+    let subscribers = subscribers.clone();
+
+    tokio::spawn(async move { bot::run(args, &telegram, &davinci, &subscribers).await });
+  }
+
+  {
+    let davinci = davinci.clone();
+    let subscribers = subscribers.clone();
+
+    tokio::spawn(async move {
+      if let Err(err) = sighup::on_sighup(args_tx, davinci, subscribers).await {
+        error!("Unable to listen for SIGHUP: {}", err);
       }
-    }
-  });
+    });
+  }
+
+  spawn_iterations(args_rx, telegram, davinci2, subscribers, stats).await;
 
-  info!("Listening on http://{}...", args.listen_addr);
-  let listener = TcpListener::bind(args.listen_addr).await?;
+  let scheme = if tls.is_some() { "https" } else { "http" };
 
+  info!("Listening on {}://{}...", scheme, args.listen_addr);
   info!(
-    "Listening on http://{}... (internal)",
-    args.internal_listen_addr
+    "Listening on {}://{}... (internal)",
+    scheme, args.internal_listen_addr
   );
-  let internal_listener = TcpListener::bind(args.internal_listen_addr).await?;
 
   select! {
-    public = axum::serve(listener, router).into_future() => {
+    public = serve(args.listen_addr, router, tls.clone()) => {
       public?;
     }
-    internal = axum::serve(internal_listener, internal_router).into_future() => {
+    internal = serve(args.internal_listen_addr, internal_router, tls) => {
       internal?;
     }
   }
@@ -231,10 +841,106 @@ async fn main() -> anyhow::Result<()> {
   Ok(())
 }
 
+/// Crawls the plan and sends notifications in the background.
+async fn spawn_iterations(
+  args: watch::Receiver<Args>,
+  telegram: Arc<Telegram>,
+  davinci: Arc<Davinci>,
+  subscribers: Arc<SubscriberStore>,
+  stats: Arc<StatsStore>,
+) {
+  let initial = args.borrow().clone();
+  let mut admin = Admin::new(
+    initial.admin_chat_id,
+    initial.admin_alert_threshold,
+    initial.max_plan_age(),
+  );
+  let mut state = StateStore::load(initial.state_file).await;
+
+  tokio::spawn(async move {
+    loop {
+      heartbeat();
+      let args = args.borrow().clone();
+      admin.configure(
+        args.admin_chat_id,
+        args.admin_alert_threshold,
+        args.max_plan_age(),
+      );
+
+      if let Err(err) = iteration(
+        &args,
+        &telegram,
+        &davinci,
+        &mut admin,
+        &mut state,
+        &subscribers,
+        &stats,
+      )
+      .await
+      {
+        error!("Error while executing loop: {}", err);
+      }
+
+      // hammering the server with wrong credentials might get us blocked
+      let slots = if admin.credentials_rejected() {
+        4
+      } else {
+        retry::cool_down_slots(admin.crawl_failures())
+      };
+
+      if slots > 1 {
+        info!(
+          "Crawling failed repeatedly, skipping {} executions",
+          slots - 1
+        );
+      }
+      for _ in 0..slots {
+        await_next_execution().await;
+        heartbeat();
+      }
+    }
+  });
+}
+
+/// Includes the request id in the logs, it's also returned to the client to
+/// correlate reported failures.
+fn request_span(request: &axum::extract::Request) -> tracing::Span {
+  let request_id = request
+    .headers()
+    .get("x-request-id")
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or_default();
+
+  info_span!(
+    "request",
+    method = %request.method(),
+    uri = %request.uri().path(),
+    request_id
+  )
+}
+
+async fn serve(addr: SocketAddr, router: Router, tls: Option<RustlsConfig>) -> anyhow::Result<()> {
+  let service = router.into_make_service_with_connect_info::<SocketAddr>();
+
+  match tls {
+    None => axum::serve(TcpListener::bind(addr).await?, service).await?,
+    Some(tls) => axum_server::bind_rustls(addr, tls).serve(service).await?,
+  }
+
+  Ok(())
+}
+
+/// Browser frontend for people without Telegram, served from the static dir.
+async fn frontend() -> impl IntoResponse {
+  static_path(Path("index.html".to_string())).await
+}
+
 async fn static_path(Path(path): Path<String>) -> impl IntoResponse {
   let path = path.trim_start_matches('/');
-  let mime_type = match path.split('.').last() {
+  let mime_type = match path.split('.').next_back() {
+    Some("html") => "text/html; charset=utf-8",
     Some("css") => "text/css",
+    Some("js") => "text/javascript",
     Some("woff2") => "font/woff2",
     _ => "application/octet-stream",
   };
@@ -255,8 +961,48 @@ async fn static_path(Path(path): Path<String>) -> impl IntoResponse {
   }
 }
 
-async fn iteration(args: &Args, telegram: &Telegram, davinci: &Davinci) -> anyhow::Result<()> {
-  let result = match davinci.update().await {
+async fn iteration(
+  args: &Args,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  admin: &mut Admin,
+  state: &mut StateStore,
+  subscribers: &SubscriberStore,
+  stats: &StatsStore,
+) -> anyhow::Result<()> {
+  let circuit_open = admin.is_circuit_open();
+  let update = if circuit_open {
+    info!("Upstream considered down, probing...");
+    davinci.update().await
+  } else {
+    retry::update(davinci).await
+  };
+
+  match &update {
+    Err(err) => admin.crawl_failed(telegram, err).await,
+    Ok(_) => admin.crawl_succeeded(telegram).await,
+  }
+
+  if let (Ok(updated), Some(data)) = (&update, davinci.data().await.as_ref()) {
+    if *updated {
+      if let Err(err) = stats.record(&data.rows, CLASSES).await {
+        error!("Unable to persist stats: {}", err);
+      }
+    }
+
+    if let Some(last_modified) = data.last_modified {
+      admin
+        .check_plan_age(telegram, last_modified, OffsetDateTime::now_utc())
+        .await;
+    }
+  }
+
+  let result = match update {
+    // already alerted, don't repeat the same error on every probe
+    Err(err) if circuit_open => {
+      info!("Probe failed: {}", err);
+      Ok(())
+    }
     Err(err) => Err(anyhow!(format!(
       "Error executing davinci update schedule: {}",
       err
@@ -266,32 +1012,328 @@ async fn iteration(args: &Args, telegram: &Telegram, davinci: &Davinci) -> anyho
 
       if now.hour() == 15 && now.minute() <= 14 {
         info!("Send 15 o'clock notification");
-        send_notifications(args, telegram, davinci).await
+        notify(args, telegram, davinci, admin, state, subscribers).await
       } else {
         info!("Nothing changed");
         Ok(())
       }
     }
-    Ok(true) => {
-      info!("Detected changes, sending notifications...");
+    Ok(true) => announce(args, telegram, davinci, admin, state, subscribers).await,
+  };
+
+  let now = OffsetDateTime::now_utc();
+  if now.weekday() == Weekday::Sunday
+    && Some(now.hour()) == args.weekly_digest_hour
+    && now.minute() <= 14
+  {
+    info!("Send weekly digest");
+    send_weekly_digest(args, telegram, davinci, admin, subscribers).await;
+  }
+
+  if Some(now.hour()) == args.exam_reminder_hour && now.minute() <= 14 {
+    info!("Send exam reminders");
+    send_exam_reminders(args, telegram, davinci, admin, subscribers).await;
+  }
+
+  if now.day() == 1 && Some(now.hour()) == args.monthly_stats_hour && now.minute() <= 14 {
+    info!("Send monthly stats");
+    send_monthly_stats(args, telegram, admin, subscribers, stats).await;
+  }
+
+  result
+}
+
+async fn announce(
+  args: &Args,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  admin: &Admin,
+  state: &mut StateStore,
+  subscribers: &SubscriberStore,
+) -> anyhow::Result<()> {
+  let chat_ids = subscribers.unmuted(&args.chat_ids).await;
+  let mut full = Vec::new();
+  let mut short = Vec::new();
+
+  match davinci.data().await.as_ref() {
+    None => return Ok(()),
+    Some(data) if state.is_notified(data) => {
+      info!("Detected changes, but they were already announced before the last restart");
+      return Ok(());
+    }
+    Some(data) => match state.notified_rows() {
+      None => full.clone_from(&chat_ids),
+      Some(notified_rows) => {
+        let changes = diff(notified_rows, &data.rows, CLASSES);
+
+        for id in &chat_ids {
+          let changes = changes.without(&ignored(args, subscribers, *id).await);
+
+          // rows that were only added are announced on their own
+          let language = subscribers.language(*id, args.language).await;
+
+          match changes.to_markdown(language) {
+            None => {}
+            Some(text) if changes.removed.is_empty() => short.push((*id, text)),
+            Some(_) => full.push(*id),
+          }
+        }
+      }
+    },
+  }
+
+  if full.is_empty() && short.is_empty() {
+    info!("Detected changes, but none of them affect the subscribed chats");
+  }
+
+  for (id, text) in &short {
+    info!("Sending short notification to chat {}...", id);
+
+    if let Err(err) = telegram.send_text(*id, text).await {
+      admin.send_failed(telegram, *id, &err).await;
+    }
+  }
+
+  if !full.is_empty() {
+    info!("Detected changes, sending notifications...");
+    send_notifications(
+      args,
+      telegram,
+      davinci,
+      admin,
+      subscribers,
+      state.notified_rows(),
+      &full,
+    )
+    .await?;
+  }
+
+  mark_notified(davinci, state).await;
+
+  Ok(())
+}
+
+async fn send_weekly_digest(
+  args: &Args,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  admin: &Admin,
+  subscribers: &SubscriberStore,
+) {
+  let monday = OffsetDateTime::now_utc().date().next_day().unwrap();
+
+  let data = davinci.data().await;
+  let Some(data) = data.as_ref() else {
+    return;
+  };
+
+  // each theme is only rendered if a chat asked for it
+  let mut rendered = HashMap::new();
+
+  for id in subscribers.unmuted(&args.chat_ids).await {
+    let ignored = ignored(args, subscribers, id).await;
+    let language = subscribers.language(id, args.language).await;
+    let text = weekly_digest(&data.rows, monday, CLASSES, &ignored, language);
 
-      send_notifications(args, telegram, davinci).await
+    // screen readers can't read the images
+    let images = match args.weekly_digest_image && !subscribers.is_plain(id).await {
+      true => {
+        let dark = subscribers.is_dark(id).await;
+        if let Entry::Vacant(entry) = rendered.entry(dark) {
+          entry.insert(match render_week(args, monday, dark).await {
+            Ok(images) => Some(images),
+            Err(err) => {
+              error!("Unable to render the week view: {}", err);
+              None
+            }
+          });
+        }
+        rendered[&dark].as_ref()
+      }
+      false => None,
+    };
+
+    let result = match images {
+      Some(images) => send_images(telegram, id, &text, images).await,
+      None => telegram.send_text(id, &text).await,
+    };
+
+    if let Err(err) = result {
+      admin.send_failed(telegram, id, &err).await;
     }
+  }
+}
+
+async fn render_week(args: &Args, monday: Date, dark: bool) -> anyhow::Result<Vec<Vec<u8>>> {
+  let url = week_url(&args.internal_url, monday, CLASS, theme(dark))?;
+
+  let image = pool(args)
+    .create_image(url.as_str(), &Capture::default())
+    .await?;
+  let image = convert(&image, args.image_format.into())?;
+
+  fit_limits(&image, &ImageLimits::TELEGRAM_PHOTO)
+}
+
+async fn send_exam_reminders(
+  args: &Args,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  admin: &Admin,
+  subscribers: &SubscriberStore,
+) {
+  let tomorrow = OffsetDateTime::now_utc().date().next_day().unwrap();
+
+  let data = davinci.data().await;
+  let Some(data) = data.as_ref() else {
+    return;
   };
 
-  if let Err(err) = result {
-    error!("Unable to execute iteration: {:?}", err);
+  let mut exams = data
+    .rows
+    .iter()
+    .filter(|row| {
+      row.date == tomorrow
+        && matches!(row.change, Change::Exam { .. })
+        && CLASSES.iter().any(|class| row.is_for_class(class))
+    })
+    .collect::<Vec<&Row>>();
+
+  if exams.is_empty() {
+    return;
   }
 
-  await_next_execution().await;
+  exams.sort_by_key(|row| row.change.lesson());
+
+  for id in subscribers.unmuted(&args.chat_ids).await {
+    let ignored = ignored(args, subscribers, id).await;
+
+    let language = subscribers.language(id, args.language).await;
+
+    let mut text = format!("📝 *{}*", language.text(Text::Exams));
+    for row in exams.iter().filter(|row| !is_ignored(row, &ignored)) {
+      write!(text, "\n- {}", escape_markdown(&row.change.to_string())).unwrap();
+    }
+
+    if text.contains('\n') {
+      if let Err(err) = telegram.send_text(id, &text).await {
+        admin.send_failed(telegram, id, &err).await;
+      }
+    }
+  }
+}
+
+async fn send_monthly_stats(
+  args: &Args,
+  telegram: &Telegram,
+  admin: &Admin,
+  subscribers: &SubscriberStore,
+  stats: &StatsStore,
+) {
+  let to = OffsetDateTime::now_utc().date().previous_day().unwrap();
+  let from = to.replace_day(1).unwrap();
+
+  let stats = stats.statistics(from, to).await;
+
+  for id in subscribers.unmuted(&args.chat_ids).await {
+    let language = subscribers.language(id, args.language).await;
+    let text = monthly_summary(&stats, to.month(), to.year(), language);
+
+    if let Err(err) = telegram.send_text(id, &text).await {
+      admin.send_failed(telegram, id, &err).await;
+    }
+  }
+}
+
+async fn mark_notified(davinci: &Davinci, state: &mut StateStore) {
+  if let Some(data) = davinci.data().await.as_ref() {
+    if let Err(err) = state.notified(data).await {
+      error!("Unable to persist notification state: {}", err);
+    }
+  }
+}
+
+async fn notify(
+  args: &Args,
+  telegram: &Telegram,
+  davinci: &Davinci,
+  admin: &Admin,
+  state: &mut StateStore,
+  subscribers: &SubscriberStore,
+) -> anyhow::Result<()> {
+  let chat_ids = subscribers.unmuted(&args.chat_ids).await;
+
+  send_notifications(
+    args,
+    telegram,
+    davinci,
+    admin,
+    subscribers,
+    state.notified_rows(),
+    &chat_ids,
+  )
+  .await?;
+  mark_notified(davinci, state).await;
 
   Ok(())
 }
 
+/// Routes answered from the plan and the timetables alone, so their etags stay
+/// valid until either of them changes.
+fn plan_routes() -> Router {
+  Router::new()
+    .route("/davinci/dates", get(dates))
+    .route("/davinci/classes", get(classes))
+    .route("/davinci/rows", get(api::rows::rows))
+    .route("/davinci/:date/:class", get(timetable))
+    .route("/davinci/:date/:class/unapplied", get(unapplied))
+    .route("/davinci/:date/:class/applied", get(applied))
+    .route("/davinci/week/:week/:class", get(week))
+    .route_layer(middleware::from_fn(api::etag::etag))
+}
+
+/// Html pages screenshotted by the renderer, see [`plan_routes`].
+fn internal_plan_routes() -> Router {
+  Router::new()
+    .route("/davinci/:date", get(html_plan))
+    .route("/davinci/week/:week/:class", get(week))
+    .route_layer(middleware::from_fn(api::etag::etag))
+}
+
+/// Routes of the api behind the bearer authentication, class scoped tokens are
+/// limited to the routes of their class.
+fn api_routes(tenant_router: Router) -> Router {
+  plan_routes()
+    .route("/davinci/changes", get(api::davinci::changes))
+    .route("/davinci/now/:class", get(api::davinci::now))
+    .route("/davinci/week/:class", get(api::davinci::week_json))
+    .route("/davinci/history/:date/:class", get(api::davinci::history))
+    .route("/stats", get(api::stats::stats))
+    .route("/log-level", get(log_level).put(set_log_level))
+    .route("/subscribers", get(api::subscribers::subscribers))
+    .route(
+      "/subscribers/:chat_id",
+      get(api::subscribers::subscriber).put(api::subscribers::set_subscriber),
+    )
+    .nest("/:tenant", tenant_router)
+    .route_layer(middleware::from_fn(api::auth::scoped))
+}
+
+/// Subjects the chat doesn't want to hear about, configured or picked itself.
+async fn ignored(args: &Args, subscribers: &SubscriberStore, chat_id: i64) -> Vec<Subject> {
+  let mut ignored = ignored_subjects(&args.subject_filters, chat_id).to_vec();
+  ignored.extend(subscribers.ignored(chat_id).await);
+  ignored
+}
+
 async fn send_notifications(
   args: &Args,
   telegram: &Telegram,
   davinci: &Davinci,
+  admin: &Admin,
+  subscribers: &SubscriberStore,
+  notified_rows: Option<&HashSet<Row>>,
+  chat_ids: &[i64],
 ) -> anyhow::Result<()> {
   let mut now = OffsetDateTime::now_utc();
 
@@ -305,95 +1347,275 @@ async fn send_notifications(
     _ => {}
   }
 
-  let (last_modified, day, unknown_changes, iteration) =
-    davinci.get_applied_timetable(now.date()).await?;
+  let (last_modified, day, unknown_changes, iteration) = davinci
+    .get_applied_timetable(now.date(), CLASS)
+    .await?
+    .ok_or_else(|| anyhow!("No timetable for class {CLASS}"))?;
 
-  let table = table(day);
+  let data = davinci.data().await;
+  let changes = match (notified_rows, data.as_ref()) {
+    (Some(notified_rows), Some(data)) => Some(diff(notified_rows, &data.rows, CLASSES)),
+    _ => None,
+  };
 
-  let image_result = render_images(&args.gecko_driver_url, &args.internal_url, davinci)
-    .await
-    .unwrap_or_else(|err| {
-      error!("Error while rendering images: {}", err);
-      None
-    });
+  let mut supervisions = data
+    .as_ref()
+    .map(|data| {
+      data
+        .rows
+        .iter()
+        .filter(|row| {
+          row.date == now.date()
+            && matches!(row.change, Change::Supervision { .. })
+            && (row.class.is_empty() || CLASSES.iter().any(|class| row.is_for_class(class)))
+        })
+        .collect::<Vec<&Row>>()
+    })
+    .unwrap_or_default();
+  supervisions.sort_by_key(|row| row.index);
+
+  // each theme is only rendered if a chat asked for it
+  let mut render_results = HashMap::new();
+
+  for id in chat_ids {
+    let plain = subscribers.is_plain(*id).await;
+    let dark = subscribers.is_dark(*id).await;
+    if plain {
+      // screen readers can't read the images
+    } else if let Entry::Vacant(entry) = render_results.entry(dark) {
+      let render_result = match render(args, davinci, dark).await {
+        Ok(render_result) => render_result,
+        Err(err) => {
+          error!("Error while rendering plans: {}", err);
+          admin
+            .alert(
+              telegram,
+              &format!("⚠️ Unable to render plans, is the WebDriver down?\n```\n{err}\n```"),
+            )
+            .await;
+          None
+        }
+      };
+      entry.insert(render_result);
+    }
+    let render_result = match plain {
+      true => None,
+      false => render_results[&dark].as_ref(),
+    };
+
+    let ignored = ignored(args, subscribers, *id).await;
+    let language = subscribers.language(*id, args.language).await;
 
-  for id in &args.chat_ids {
     let age = last_modified
       .map(|last_modified| (OffsetDateTime::now_utc() - last_modified).unsigned_abs())
       .unwrap_or_else(|| Duration::from_secs(0));
 
-    let mut text = format!(
-      "Vertretungsplan für {} den {}. {} {}, Turnus {}. Zuletzt vor {} aktualisiert.\n```\n{}```",
-      now.weekday(),
-      now.day(),
-      now.month(),
-      now.year(),
-      iteration,
-      format_duration(age),
-      table,
-    );
+    let text = NotificationTemplate {
+      sections: args.notification_sections.clone(),
+      language,
+      changes: changes
+        .as_ref()
+        .and_then(|changes| changes.without(&ignored).to_markdown(language)),
+      title: language.plan_title(now.date(), iteration, &format_duration(age, language)),
+      table: match plain {
+        true => sentences(filter_lessons(&day, &ignored), language),
+        false => subscribers
+          .table_format(*id, args.telegram_table_format)
+          .await
+          .format(filter_lessons(&day, &ignored), args.table_width, language),
+      },
+      supervisions: &supervisions,
+      messages: data
+        .as_ref()
+        .and_then(|data| data.messages.get(&now.date()))
+        .map(Vec::as_slice)
+        .unwrap_or_default(),
+      unapplied: &unknown_changes,
+    }
+    .render()?;
 
-    if !unknown_changes.is_empty() {
-      writeln!(text, "\n\nÄnderungen, die nicht angewendet werden konnten:").unwrap();
-      for row in &unknown_changes {
-        writeln!(text, "- {row:?}").unwrap();
-      }
+    if let Err(err) = send_to_chat(telegram, *id, &text, render_result).await {
+      admin.send_failed(telegram, *id, &err).await;
     }
+  }
 
-    match &image_result {
-      Some(images) => {
-        telegram.send_images(*id, text.as_str(), images).await?;
+  Ok(())
+}
+
+async fn send_to_chat(
+  telegram: &Telegram,
+  id: i64,
+  text: &str,
+  render_result: Option<&Rendered>,
+) -> anyhow::Result<()> {
+  match render_result {
+    Some(Rendered::Images(images)) => {
+      if let Err(err) = send_images(telegram, id, text, images).await {
+        error!(
+          "Unable to send images to chat {}, falling back to text: {}",
+          id, err
+        );
+        telegram.send_text(id, text).await?;
       }
-      None => {
-        telegram.send_text(*id, text.as_str()).await?;
+    }
+    Some(Rendered::Documents(documents)) => {
+      for (index, (date, document)) in documents.iter().enumerate() {
+        let caption = if index == 0 { Some(text) } else { None };
+        let file_name = format!(
+          "{}-{:0>2}-{:0>2}.pdf",
+          date.year(),
+          date.month() as u8,
+          date.day()
+        );
+        telegram
+          .send_document(id, caption, &file_name, document)
+          .await?;
       }
     }
+    None => {
+      telegram.send_text(id, text).await?;
+    }
   }
 
   Ok(())
 }
 
-async fn render_images(
-  gecko_driver_url: &Url,
-  base_url: &Url,
-  davinci: &Davinci,
-) -> anyhow::Result<Option<Vec<Vec<u8>>>> {
-  let web_img_conv = WebToImageConverter::new(gecko_driver_url.as_str()).await?;
+/// Retries images Telegram didn't accept, e.g. webp, as png.
+async fn send_images(
+  telegram: &Telegram,
+  id: i64,
+  text: &str,
+  images: &[Vec<u8>],
+) -> anyhow::Result<()> {
+  let Err(err) = telegram.send_images(id, text, images).await else {
+    return Ok(());
+  };
 
-  match davinci.data().await.as_ref() {
-    Some(data) => {
-      let mut images = Vec::new();
+  if images
+    .iter()
+    .all(|image| OutputFormat::guess(image).ok() == Some(OutputFormat::Png))
+  {
+    return Err(err);
+  }
 
-      let dates = data
-        .rows
-        .iter()
-        .map(|row| row.date)
-        .collect::<HashSet<Date>>();
-      let mut dates = dates.into_iter().collect::<Vec<Date>>();
-      dates.sort();
-
-      for date in dates {
-        images.push(
-          web_img_conv
-            .create_image(
-              base_url
-                .join(&format!(
-                  "davinci/{}-{:0>2}-{:0>2}?class=IGD21,IGD 21",
-                  date.year(),
-                  date.month() as u8,
-                  date.day()
-                ))?
-                .as_str(),
-            )
-            .await?,
-        )
+  warn!(
+    "Unable to send images to chat {}, retrying as png: {}",
+    id, err
+  );
+  let pngs = images
+    .iter()
+    .map(|image| convert(image, OutputFormat::Png))
+    .collect::<anyhow::Result<Vec<_>>>()?;
+
+  telegram.send_images(id, text, &pngs).await
+}
+
+enum Rendered {
+  Images(Vec<Vec<u8>>),
+  Documents(Vec<(Date, Vec<u8>)>),
+}
+
+async fn render(args: &Args, davinci: &Davinci, dark: bool) -> anyhow::Result<Option<Rendered>> {
+  // dates without rows of the classes would only clutter the chat
+  let Some(dates) = davinci
+    .data()
+    .await
+    .as_ref()
+    .map(|data| data.dates_for(CLASSES))
+  else {
+    return Ok(None);
+  };
+
+  // the dark variant is only for reading on the phone
+  let archive_dir = args.pdf_archive_dir.as_ref().filter(|_| !dark);
+
+  if args.pdf || archive_dir.is_some() {
+    let documents = render_documents(args, &dates, dark).await?;
+
+    if let Some(dir) = archive_dir {
+      if let Err(err) = archive_documents(dir, &documents).await {
+        error!("Unable to archive pdfs in {}: {}", dir.display(), err);
       }
+    }
 
-      Ok(Some(images))
+    if args.pdf {
+      return Ok(Some(Rendered::Documents(documents)));
     }
+  }
+
+  let renderer = PlanRenderer::new(args).await?;
+
+  // the pool limits how many dates are rendered at once
+  let rendered = try_join_all(
+    dates
+      .into_iter()
+      .map(|date| renderer.image(davinci, date, CLASSES, options(args, dark))),
+  )
+  .await?;
+
+  let mut rendered = rendered.into_iter().flatten().collect::<Vec<_>>();
+  if args.stitch && rendered.len() > 1 {
+    rendered = vec![stitch(&rendered, STITCH_GAP)?];
+  }
 
-    None => Ok(None),
+  let mut images = Vec::new();
+  for image in rendered {
+    let image = convert(&image, args.image_format.into())?;
+    images.extend(fit_limits(&image, &ImageLimits::TELEGRAM_PHOTO)?);
   }
+
+  Ok(Some(Rendered::Images(images)))
+}
+
+fn options(args: &Args, dark: bool) -> PlanOptions {
+  PlanOptions {
+    theme: theme(dark),
+    navigation: false,
+    collapse: args.collapse_rows,
+  }
+}
+
+fn theme(dark: bool) -> Theme {
+  match dark {
+    true => Theme::Dark,
+    false => Theme::Light,
+  }
+}
+
+async fn render_documents(
+  args: &Args,
+  dates: &[Date],
+  dark: bool,
+) -> anyhow::Result<Vec<(Date, Vec<u8>)>> {
+  let pool = pool(args);
+
+  try_join_all(dates.iter().map(|date| async move {
+    let url = plan_url(&args.internal_url, *date, CLASSES, options(args, dark))?;
+    Ok((*date, pool.create_pdf(url.as_str()).await?))
+  }))
+  .await
+}
+
+async fn archive_documents(
+  dir: &std::path::Path,
+  documents: &[(Date, Vec<u8>)],
+) -> anyhow::Result<()> {
+  let now = OffsetDateTime::now_utc();
+
+  for (date, document) in documents {
+    let dir = dir.join(date.to_string());
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let path = dir.join(format!(
+      "{:0>2}-{:0>2}-{:0>2}.pdf",
+      now.hour(),
+      now.minute(),
+      now.second()
+    ));
+    tokio::fs::write(&path, document).await?;
+  }
+
+  Ok(())
 }
 
 async fn await_next_execution() {
@@ -415,17 +1637,10 @@ async fn await_next_execution() {
   tokio::time::sleep_until(sleep_until).await;
 }
 
-fn format_duration(duration: Duration) -> String {
+fn format_duration(duration: Duration, language: Language) -> String {
   let secs = duration.as_secs();
 
-  let units = [
-    ("einem Jahr", "Jahren", 31_557_600),
-    ("einem Monat", "Monaten", 2_630_016),
-    ("einem Tag", "Tagen", 86400),
-    ("einer Stunde", "Stunden", 3600),
-    ("einer Minute", "Minuten", 60),
-    ("einer Sekunde", "Sekunden", 1),
-  ];
+  let units = language.duration_units();
 
   let mut last = None;
   let mut last_remaining = secs;
@@ -437,8 +1652,9 @@ fn format_duration(duration: Duration) -> String {
     if value != 0 {
       if let Some(last) = last {
         return format!(
-          "{} und {}",
+          "{} {} {}",
           last,
+          language.and(),
           match value {
             1 => one.to_string(),
             value => format!("{value} {many}"),
@@ -0,0 +1,50 @@
+use clap::ValueEnum;
+use sailfish::TemplateOnce;
+
+use bszet_davinci::Row;
+
+use crate::diff::escape_markdown;
+use crate::i18n::{Language, Text};
+
+/// Parts of the notification text, in the order they are configured.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum Section {
+  /// Rows added or removed since the last notification.
+  Changes,
+  /// Title and table of the day.
+  Plan,
+  Supervisions,
+  /// Messages of the day of the plan.
+  Messages,
+  /// Rows that couldn't be applied to the timetable.
+  Unapplied,
+}
+
+/// Text sent along the plan, see `templates/notification.stpl`.
+#[derive(TemplateOnce)]
+#[template(path = "notification.stpl")]
+pub(crate) struct NotificationTemplate<'a> {
+  pub(crate) sections: Vec<Section>,
+  pub(crate) language: Language,
+  pub(crate) changes: Option<String>,
+  pub(crate) title: String,
+  pub(crate) table: String,
+  pub(crate) supervisions: &'a [&'a Row],
+  pub(crate) messages: &'a [String],
+  pub(crate) unapplied: &'a [Row],
+}
+
+impl NotificationTemplate<'_> {
+  /// Renders the sections with content.
+  pub(crate) fn render(mut self) -> anyhow::Result<String> {
+    self.sections.retain(|section| match section {
+      Section::Changes => self.changes.is_some(),
+      Section::Plan => true,
+      Section::Supervisions => !self.supervisions.is_empty(),
+      Section::Messages => !self.messages.is_empty(),
+      Section::Unapplied => !self.unapplied.is_empty(),
+    });
+
+    Ok(self.render_once()?.trim().to_string())
+  }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::warn;
+
+/// Buckets of clients that didn't show up for this long are dropped.
+const IDLE: Duration = Duration::from_secs(10 * 60);
+
+/// Token bucket per client address, allowing bursts of `per_minute` requests
+/// which are refilled evenly over a minute.
+pub(crate) struct RateLimiter {
+  per_minute: u32,
+  buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+}
+
+impl RateLimiter {
+  pub(crate) fn new(per_minute: u32) -> Self {
+    Self {
+      per_minute,
+      buckets: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Takes a token for the client, returns `false` if it has to wait.
+  pub(crate) fn check(&self, ip: IpAddr, now: Instant) -> bool {
+    let mut buckets = self.buckets.lock().unwrap();
+    let capacity = self.per_minute as f64;
+
+    if buckets.len() > 1024 {
+      buckets.retain(|_, bucket| now.duration_since(bucket.updated) < IDLE);
+    }
+
+    let bucket = buckets.entry(ip).or_insert(Bucket {
+      tokens: capacity,
+      updated: now,
+    });
+
+    let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * capacity / 60.0).min(capacity);
+    bucket.updated = now;
+
+    if bucket.tokens < 1.0 {
+      return false;
+    }
+
+    bucket.tokens -= 1.0;
+    true
+  }
+}
+
+pub(crate) async fn rate_limit(
+  State(limiter): State<Arc<RateLimiter>>,
+  ConnectInfo(addr): ConnectInfo<SocketAddr>,
+  request: Request,
+  next: Next,
+) -> Response {
+  if !limiter.check(addr.ip(), Instant::now()) {
+    warn!("Rate limit exceeded by {}", addr.ip());
+    return (
+      StatusCode::TOO_MANY_REQUESTS,
+      [(RETRY_AFTER, "60")],
+      "too many requests",
+    )
+      .into_response();
+  }
+
+  next.run(request).await
+}
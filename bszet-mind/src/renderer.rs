@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use clap::ValueEnum;
+use once_cell::sync::OnceCell;
+use reqwest::Url;
+use time::Date;
+
+use bszet_davinci::{Davinci, PlanOptions, PlanView, Theme};
+use bszet_image::{
+  Capture, NativeRenderer, OutputFormat, Table, TableRow, WebDriverPool, WebDriverSession,
+};
+
+use crate::Args;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum Renderer {
+  /// Screenshots the html plan through the WebDriver.
+  Webdriver,
+  /// Draws the plan without a browser.
+  Native,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub(crate) enum ImageFormat {
+  Png,
+  /// Smaller uploads, chats are sent png if Telegram rejects it.
+  Webp,
+}
+
+impl From<ImageFormat> for OutputFormat {
+  fn from(format: ImageFormat) -> Self {
+    match format {
+      ImageFormat::Png => Self::Png,
+      ImageFormat::Webp => Self::WebP,
+    }
+  }
+}
+
+static POOL: OnceCell<WebDriverPool> = OnceCell::new();
+
+/// The WebDriver sessions of the process, each opened on first use.
+pub(crate) fn pool(args: &Args) -> &'static WebDriverPool {
+  POOL.get_or_init(|| {
+    let sessions = (0..args.webdriver_sessions.max(1))
+      .map(|_| {
+        WebDriverSession::new(
+          args.gecko_driver_url.as_str(),
+          args.webdriver_scale,
+          Duration::from_secs(args.webdriver_timeout),
+          args.webdriver_retries,
+        )
+      })
+      .collect();
+
+    WebDriverPool::new(sessions, Duration::from_secs(args.render_timeout))
+  })
+}
+
+/// Internal url of the html plan, the classes may contain spaces.
+pub(crate) fn plan_url(
+  base_url: &Url,
+  date: Date,
+  classes: &[&str],
+  options: PlanOptions,
+) -> anyhow::Result<Url> {
+  let mut url = base_url.join(&format!(
+    "davinci/{}-{:0>2}-{:0>2}",
+    date.year(),
+    date.month() as u8,
+    date.day()
+  ))?;
+
+  url
+    .query_pairs_mut()
+    .append_pair("class", &classes.join(","))
+    .append_pair("theme", options.theme.as_str())
+    .append_pair("collapse", &options.collapse.to_string())
+    .append_pair("nav", &options.navigation.to_string());
+
+  Ok(url)
+}
+
+/// Internal url of the week view of the class, `monday` names the iso week.
+pub(crate) fn week_url(
+  base_url: &Url,
+  monday: Date,
+  class: &str,
+  theme: Theme,
+) -> anyhow::Result<Url> {
+  let (year, week, _) = monday.to_iso_week_date();
+
+  let mut url = base_url.join("davinci/week/")?;
+  url
+    .path_segments_mut()
+    .map_err(|_| anyhow::anyhow!("{base_url} can't be a base"))?
+    .pop_if_empty()
+    .push(&format!("{year}-W{week:0>2}"))
+    .push(class);
+  url.query_pairs_mut().append_pair("theme", theme.as_str());
+
+  Ok(url)
+}
+
+/// Draws plan images, either through the browser or natively.
+pub(crate) enum PlanRenderer {
+  WebDriver {
+    pool: &'static WebDriverPool,
+    base_url: Url,
+  },
+  Native(NativeRenderer),
+}
+
+impl PlanRenderer {
+  pub(crate) async fn new(args: &Args) -> anyhow::Result<Self> {
+    Ok(match args.renderer {
+      Renderer::Webdriver => Self::WebDriver {
+        pool: pool(args),
+        base_url: args.internal_url.clone(),
+      },
+      Renderer::Native => Self::Native(NativeRenderer::new(tokio::fs::read(&args.font).await?)?),
+    })
+  }
+
+  /// Png of the plan of the day, rows of the classes are highlighted.
+  pub(crate) async fn image(
+    &self,
+    davinci: &Davinci,
+    date: Date,
+    classes: &[&str],
+    options: PlanOptions,
+  ) -> anyhow::Result<Option<Vec<u8>>> {
+    match self {
+      Self::WebDriver { pool, base_url } => {
+        let url = plan_url(base_url, date, classes, options)?;
+        Ok(Some(
+          pool.create_image(url.as_str(), &Capture::default()).await?,
+        ))
+      }
+      Self::Native(renderer) => match davinci
+        .get_plan_view(&date, classes, options.collapse)
+        .await
+      {
+        Some(plan) => Ok(Some(
+          renderer.render(&table(plan, options.theme == Theme::Dark))?,
+        )),
+        None => Ok(None),
+      },
+    }
+  }
+
+  /// Ends the WebDriver session, only needed before exiting.
+  pub(crate) async fn quit(&self) {
+    if let Self::WebDriver { pool, .. } = self {
+      pool.quit().await;
+    }
+  }
+}
+
+fn table(plan: PlanView, dark: bool) -> Table {
+  Table {
+    title: plan.title,
+    subtitle: Some(plan.subtitle),
+    header: plan.header,
+    rows: plan
+      .rows
+      .into_iter()
+      .map(|row| TableRow {
+        cells: row.cells,
+        selected: row.selected,
+        emphasized: row.exam.then_some(5),
+      })
+      .collect(),
+    notes_title: "Nachrichten zum Tag".to_string(),
+    notes: plan.messages,
+    footer: (plan.hidden_rows > 0)
+      .then(|| format!("{} weitere Einträge ausgeblendet", plan.hidden_rows)),
+    dark,
+  }
+}
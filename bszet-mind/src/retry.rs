@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use tracing::warn;
+
+use bszet_davinci::{CredentialsRejected, Davinci};
+
+const RETRIES: u32 = 3;
+const BASE_DELAY: Duration = Duration::from_secs(10);
+const MAX_DELAY: Duration = Duration::from_secs(2 * 60);
+
+/// Failed iterations in a row after which crawling slows down.
+const COOL_DOWN_AFTER: u32 = 3;
+
+/// Exponential backoff with up to 50% jitter.
+pub(crate) fn backoff(attempt: u32) -> Duration {
+  let delay = BASE_DELAY
+    .saturating_mul(2u32.saturating_pow(attempt))
+    .min(MAX_DELAY);
+
+  delay + delay.mul_f64(rand::random::<f64>() * 0.5)
+}
+
+/// Number of 15 minute slots to wait until the next iteration, doubled for
+/// each failed iteration past the threshold up to an hour.
+pub(crate) fn cool_down_slots(failures: u32) -> u32 {
+  2u32.pow(failures.saturating_sub(COOL_DOWN_AFTER).min(2))
+}
+
+/// Retries transient failures right away, rejected credentials won't recover
+/// by retrying.
+pub(crate) async fn update(davinci: &Davinci) -> anyhow::Result<bool> {
+  let mut attempt = 0;
+
+  loop {
+    match davinci.update().await {
+      Err(err) if attempt < RETRIES && !err.is::<CredentialsRejected>() => {
+        let delay = backoff(attempt);
+        warn!(
+          "Unable to update substitution plan, retrying in {}s: {}",
+          delay.as_secs(),
+          err
+        );
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+      }
+      result => return result,
+    }
+  }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+type Apply = Box<dyn Fn(String) -> anyhow::Result<()> + Send + Sync>;
+
+/// Credential read from a `*_FILE` path, swapped at runtime when the file is
+/// rotated.
+pub(crate) struct Secret {
+  path: PathBuf,
+  value: String,
+  apply: Apply,
+}
+
+impl Secret {
+  pub(crate) fn new(
+    path: PathBuf,
+    value: String,
+    apply: impl Fn(String) -> anyhow::Result<()> + Send + Sync + 'static,
+  ) -> Self {
+    Self {
+      path,
+      value,
+      apply: Box::new(apply),
+    }
+  }
+}
+
+/// Watches the directories of the secrets instead of the files themselves, as
+/// Docker and Kubernetes rotate secrets by swapping symlinks.
+pub(crate) async fn watch(mut secrets: Vec<Secret>) -> anyhow::Result<()> {
+  let (tx, mut rx) = mpsc::channel(1);
+
+  let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+    match event {
+      Ok(event) if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() => {
+        // a reload is already pending if the channel is full
+        let _ = tx.try_send(());
+      }
+      Ok(_) => {}
+      Err(err) => error!("Unable to watch secrets: {}", err),
+    }
+  })?;
+
+  let dirs = secrets
+    .iter()
+    .filter_map(|secret| secret.path.parent())
+    .map(|dir| match dir.as_os_str().is_empty() {
+      true => PathBuf::from("."),
+      false => dir.to_path_buf(),
+    })
+    .collect::<HashSet<PathBuf>>();
+
+  for dir in &dirs {
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+  }
+
+  while rx.recv().await.is_some() {
+    // secrets are often written in multiple steps
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    while rx.try_recv().is_ok() {}
+
+    for secret in &mut secrets {
+      let value = match tokio::fs::read_to_string(&secret.path).await {
+        Ok(value) => value,
+        Err(err) => {
+          error!("Unable to read secret {}: {}", secret.path.display(), err);
+          continue;
+        }
+      };
+
+      if value == secret.value {
+        continue;
+      }
+
+      info!("Secret {} changed, reloading...", secret.path.display());
+      match (secret.apply)(value.clone()) {
+        Ok(()) => secret.value = value,
+        Err(err) => error!("Unable to apply secret {}: {}", secret.path.display(), err),
+      }
+    }
+  }
+
+  Ok(())
+}
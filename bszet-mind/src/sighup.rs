@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use bszet_davinci::Davinci;
+
+use crate::config::parse_args;
+use crate::subscribers::SubscriberStore;
+use crate::{install_settings, Args};
+
+/// Reloads the configuration, the files it refers to, subscribers and
+/// timetables on `SIGHUP`. The crawl cache and notification state are kept, so
+/// nothing is announced again.
+pub(crate) async fn on_sighup(
+  args: watch::Sender<Args>,
+  davinci: Arc<Davinci>,
+  subscribers: Arc<SubscriberStore>,
+) -> anyhow::Result<()> {
+  let mut hangup = signal(SignalKind::hangup())?;
+
+  while hangup.recv().await.is_some() {
+    info!("Received SIGHUP, reloading...");
+
+    if let Err(err) = reload(&args, &davinci, &subscribers).await {
+      error!("Unable to reload configuration: {}", err);
+    }
+  }
+
+  Ok(())
+}
+
+async fn reload(
+  args: &watch::Sender<Args>,
+  davinci: &Davinci,
+  subscribers: &SubscriberStore,
+) -> anyhow::Result<()> {
+  let new = parse_args()?;
+
+  install_settings(&new)?;
+  if let Some(dir) = &new.timetable_dir {
+    davinci.timetables().load_dir(dir)?;
+  }
+  subscribers.reload().await;
+
+  args.send_replace(new);
+  info!("Reloaded configuration");
+
+  Ok(())
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use time::serde::format_description;
+use time::{Date, OffsetDateTime};
+use tracing::{info, warn};
+
+use bszet_davinci::{Data, Row};
+
+format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+/// Notification state that survives restarts, so an unchanged plan isn't
+/// broadcasted again after the process comes back up.
+#[derive(Default, Serialize, Deserialize)]
+struct NotificationState {
+  rows_hash: Option<u64>,
+  last_modified: Vec<PageState>,
+  #[serde(default)]
+  rows: HashSet<Row>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PageState {
+  #[serde(with = "iso_date")]
+  date: Date,
+  #[serde(with = "time::serde::rfc3339")]
+  last_modified: OffsetDateTime,
+}
+
+pub(crate) struct StateStore {
+  path: Option<PathBuf>,
+  state: NotificationState,
+}
+
+impl StateStore {
+  pub(crate) async fn load(path: Option<PathBuf>) -> Self {
+    let state = match &path {
+      None => NotificationState::default(),
+      Some(path) => match tokio::fs::read(path).await {
+        Ok(raw) => serde_json::from_slice(&raw).unwrap_or_else(|err| {
+          warn!("Unable to parse state file {}: {}", path.display(), err);
+          NotificationState::default()
+        }),
+        Err(err) => {
+          info!("No previous state loaded from {}: {}", path.display(), err);
+          NotificationState::default()
+        }
+      },
+    };
+
+    Self { path, state }
+  }
+
+  /// Rows of the last announced plan, `None` if nothing was announced yet.
+  pub(crate) fn notified_rows(&self) -> Option<&HashSet<Row>> {
+    self.state.rows_hash.map(|_| &self.state.rows)
+  }
+
+  /// Returns `true` if exactly this plan was already announced.
+  pub(crate) fn is_notified(&self, data: &Data) -> bool {
+    self.state.rows_hash == Some(data.rows_hash())
+  }
+
+  pub(crate) async fn notified(&mut self, data: &Data) -> anyhow::Result<()> {
+    self.state = NotificationState {
+      rows_hash: Some(data.rows_hash()),
+      last_modified: data
+        .last_modified_by_date
+        .iter()
+        .map(|(date, last_modified)| PageState {
+          date: *date,
+          last_modified: *last_modified,
+        })
+        .collect(),
+      rows: data.rows.clone(),
+    };
+
+    self.save().await
+  }
+
+  async fn save(&self) -> anyhow::Result<()> {
+    match &self.path {
+      None => Ok(()),
+      Some(path) => write_json(path, &self.state).await,
+    }
+  }
+}
+
+/// Writes to a temporary file first, so a crash never leaves a truncated file behind.
+pub(crate) async fn write_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+  let tmp = path.with_extension("tmp");
+  tokio::fs::write(&tmp, serde_json::to_vec(value)?).await?;
+  tokio::fs::rename(&tmp, path).await?;
+
+  Ok(())
+}
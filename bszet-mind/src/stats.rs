@@ -0,0 +1,175 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use time::{Date, Month};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use bszet_davinci::change::Change;
+use bszet_davinci::teachers::teacher_name;
+use bszet_davinci::Row;
+
+use crate::diff::escape_markdown;
+use crate::i18n::{Language, Text};
+use crate::state::write_json;
+
+/// Archive of all changes ever seen for the tracked classes, the plan itself
+/// only covers the next few days.
+pub(crate) struct StatsStore {
+  path: Option<PathBuf>,
+  rows: RwLock<HashSet<Row>>,
+}
+
+impl StatsStore {
+  pub(crate) async fn load(path: Option<PathBuf>) -> Self {
+    let rows = match &path {
+      None => HashSet::new(),
+      Some(path) => match tokio::fs::read(path).await {
+        Ok(raw) => serde_json::from_slice(&raw).unwrap_or_else(|err| {
+          warn!("Unable to parse stats file {}: {}", path.display(), err);
+          HashSet::new()
+        }),
+        Err(err) => {
+          info!("No stats loaded from {}: {}", path.display(), err);
+          HashSet::new()
+        }
+      },
+    };
+
+    Self {
+      path,
+      rows: RwLock::new(rows),
+    }
+  }
+
+  pub(crate) async fn record(&self, rows: &HashSet<Row>, classes: &[&str]) -> anyhow::Result<()> {
+    let mut archive = self.rows.write().await;
+    let len = archive.len();
+
+    archive.extend(
+      rows
+        .iter()
+        .filter(|row| classes.iter().any(|class| row.is_for_class(class)))
+        .cloned(),
+    );
+
+    match &self.path {
+      Some(path) if archive.len() != len => write_json(path, &*archive).await,
+      _ => Ok(()),
+    }
+  }
+
+  /// Archived rows of the date, including those withdrawn later.
+  pub(crate) async fn rows_of(&self, date: Date) -> HashSet<Row> {
+    self
+      .rows
+      .read()
+      .await
+      .iter()
+      .filter(|row| row.date == date)
+      .cloned()
+      .collect()
+  }
+
+  pub(crate) async fn statistics(&self, from: Date, to: Date) -> Statistics {
+    statistics(&*self.rows.read().await, from, to)
+  }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub(crate) struct Statistics {
+  pub(crate) cancellations: u32,
+  pub(crate) by_subject: BTreeMap<String, u32>,
+  pub(crate) by_teacher: BTreeMap<String, u32>,
+  pub(crate) by_weekday: BTreeMap<String, u32>,
+  /// Cancellations per month (`YYYY-MM`).
+  pub(crate) by_month: BTreeMap<String, u32>,
+}
+
+/// Counts the cancellations between `from` and `to` (both inclusive).
+pub(crate) fn statistics(rows: &HashSet<Row>, from: Date, to: Date) -> Statistics {
+  let mut stats = Statistics::default();
+
+  for row in rows {
+    let Change::Cancel {
+      subject, teachers, ..
+    } = &row.change
+    else {
+      continue;
+    };
+
+    if row.date < from || row.date > to {
+      continue;
+    }
+
+    stats.cancellations += 1;
+    *stats.by_subject.entry(subject.to_string()).or_default() += 1;
+    *stats
+      .by_weekday
+      .entry(row.date.weekday().to_string())
+      .or_default() += 1;
+    *stats
+      .by_month
+      .entry(format!(
+        "{}-{:0>2}",
+        row.date.year(),
+        row.date.month() as u8
+      ))
+      .or_default() += 1;
+
+    for teacher in teachers.iter().filter(|teacher| !teacher.is_empty()) {
+      *stats.by_teacher.entry(teacher.clone()).or_default() += 1;
+    }
+  }
+
+  stats
+}
+
+/// The school year starts on the first of august.
+pub(crate) fn school_year_start(date: Date) -> Date {
+  let year = if date.month() as u8 >= Month::August as u8 {
+    date.year()
+  } else {
+    date.year() - 1
+  };
+
+  Date::from_calendar_date(year, Month::August, 1).unwrap()
+}
+
+pub(crate) fn monthly_summary(
+  stats: &Statistics,
+  month: Month,
+  year: i32,
+  language: Language,
+) -> String {
+  let mut out = format!("{}\n\n", language.stats_title(month, year));
+
+  if stats.cancellations == 0 {
+    out.push_str(language.text(Text::NoCancellations));
+    return out;
+  }
+
+  out.push_str(&language.cancellations(stats.cancellations));
+
+  for (title, counts, teachers) in [
+    (language.text(Text::Subjects), &stats.by_subject, false),
+    (language.text(Text::Teachers), &stats.by_teacher, true),
+  ] {
+    let mut counts = counts.iter().collect::<Vec<_>>();
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    write!(out, "\n\n*{title}:*").unwrap();
+    for (name, count) in counts.into_iter().take(5) {
+      let name = if teachers {
+        teacher_name(name)
+      } else {
+        name.clone()
+      };
+      write!(out, "\n- {}: {}", escape_markdown(&name), count).unwrap();
+    }
+  }
+
+  out
+}
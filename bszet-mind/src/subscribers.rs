@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use bszet_davinci::timetable::Subject;
+
+use crate::ascii::TableFormat;
+use crate::i18n::Language;
+use crate::state::write_json;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Mute {
+  Until(#[serde(with = "time::serde::rfc3339")] OffsetDateTime),
+  Indefinitely,
+}
+
+/// Hours (UTC) without notifications, `from` 22 `to` 6 spans midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct QuietHours {
+  pub(crate) from: u8,
+  pub(crate) to: u8,
+}
+
+impl QuietHours {
+  pub(crate) fn contains(&self, hour: u8) -> bool {
+    match self.from <= self.to {
+      true => self.from <= hour && hour < self.to,
+      false => hour >= self.from || hour < self.to,
+    }
+  }
+}
+
+/// Preferences of a chat, used when composing its messages.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Subscriber {
+  #[serde(default)]
+  pub(crate) mute: Option<Mute>,
+  /// Receives the plan images with a dark background.
+  #[serde(default)]
+  pub(crate) dark: bool,
+  /// Overrides the language of the deployment.
+  #[serde(default)]
+  pub(crate) language: Option<Language>,
+  /// Receives the lessons as sentences without images, for screen readers.
+  #[serde(default)]
+  pub(crate) plain: bool,
+  /// Overrides the table format of the deployment.
+  #[serde(default)]
+  pub(crate) table_format: Option<TableFormat>,
+  /// Ignored in addition to the configured subject filters.
+  #[serde(default)]
+  pub(crate) ignored: Vec<Subject>,
+  #[serde(default)]
+  pub(crate) quiet_hours: Option<QuietHours>,
+}
+
+/// Per-chat preferences managed through bot commands and the api.
+pub(crate) struct SubscriberStore {
+  path: Option<PathBuf>,
+  chats: RwLock<HashMap<i64, Subscriber>>,
+}
+
+impl SubscriberStore {
+  pub(crate) async fn load(path: Option<PathBuf>) -> Self {
+    Self {
+      chats: RwLock::new(read(&path).await),
+      path,
+    }
+  }
+
+  /// Picks up changes to the file made while running.
+  pub(crate) async fn reload(&self) {
+    if self.path.is_some() {
+      *self.chats.write().await = read(&self.path).await;
+    }
+  }
+
+  /// Changes the preferences of the chat and persists them.
+  pub(crate) async fn update(
+    &self,
+    chat_id: i64,
+    update: impl FnOnce(&mut Subscriber),
+  ) -> anyhow::Result<()> {
+    let mut chats = self.chats.write().await;
+    update(chats.entry(chat_id).or_default());
+
+    match &self.path {
+      None => Ok(()),
+      Some(path) => write_json(path, &*chats).await,
+    }
+  }
+
+  /// Preferences of the chat, the defaults if it never changed any.
+  pub(crate) async fn get(&self, chat_id: i64) -> Subscriber {
+    self
+      .chats
+      .read()
+      .await
+      .get(&chat_id)
+      .cloned()
+      .unwrap_or_default()
+  }
+
+  pub(crate) async fn all(&self) -> HashMap<i64, Subscriber> {
+    self.chats.read().await.clone()
+  }
+
+  pub(crate) async fn set_mute(&self, chat_id: i64, mute: Option<Mute>) -> anyhow::Result<()> {
+    self.update(chat_id, |chat| chat.mute = mute).await
+  }
+
+  pub(crate) async fn set_dark(&self, chat_id: i64, dark: bool) -> anyhow::Result<()> {
+    self.update(chat_id, |chat| chat.dark = dark).await
+  }
+
+  pub(crate) async fn is_dark(&self, chat_id: i64) -> bool {
+    self.get(chat_id).await.dark
+  }
+
+  pub(crate) async fn set_plain(&self, chat_id: i64, plain: bool) -> anyhow::Result<()> {
+    self.update(chat_id, |chat| chat.plain = plain).await
+  }
+
+  pub(crate) async fn is_plain(&self, chat_id: i64) -> bool {
+    self.get(chat_id).await.plain
+  }
+
+  pub(crate) async fn set_language(&self, chat_id: i64, language: Language) -> anyhow::Result<()> {
+    self
+      .update(chat_id, |chat| chat.language = Some(language))
+      .await
+  }
+
+  /// Language of the chat, `default` unless it picked one.
+  pub(crate) async fn language(&self, chat_id: i64, default: Language) -> Language {
+    self.get(chat_id).await.language.unwrap_or(default)
+  }
+
+  /// Table format of the chat, `default` unless it picked one.
+  pub(crate) async fn table_format(&self, chat_id: i64, default: TableFormat) -> TableFormat {
+    self.get(chat_id).await.table_format.unwrap_or(default)
+  }
+
+  pub(crate) async fn ignored(&self, chat_id: i64) -> Vec<Subject> {
+    self.get(chat_id).await.ignored
+  }
+
+  /// Returns the chats that currently want to receive notifications.
+  pub(crate) async fn unmuted(&self, chat_ids: &[i64]) -> Vec<i64> {
+    let now = OffsetDateTime::now_utc();
+    let chats = self.chats.read().await;
+
+    chat_ids
+      .iter()
+      .filter(|id| {
+        let Some(chat) = chats.get(id) else {
+          return true;
+        };
+
+        let muted = match chat.mute {
+          None => false,
+          Some(Mute::Until(until)) => until > now,
+          Some(Mute::Indefinitely) => true,
+        };
+        let quiet = chat
+          .quiet_hours
+          .is_some_and(|quiet_hours| quiet_hours.contains(now.hour()));
+
+        !muted && !quiet
+      })
+      .copied()
+      .collect()
+  }
+}
+
+async fn read(path: &Option<PathBuf>) -> HashMap<i64, Subscriber> {
+  match path {
+    None => HashMap::new(),
+    Some(path) => match tokio::fs::read(path).await {
+      Ok(raw) => serde_json::from_slice(&raw).unwrap_or_else(|err| {
+        warn!(
+          "Unable to parse subscribers file {}: {}",
+          path.display(),
+          err
+        );
+        HashMap::new()
+      }),
+      Err(err) => {
+        info!("No subscribers loaded from {}: {}", path.display(), err);
+        HashMap::new()
+      }
+    },
+  }
+}
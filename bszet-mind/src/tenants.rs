@@ -0,0 +1,81 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::extract::{Path as UrlPath, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use reqwest::Client;
+use serde::Deserialize;
+
+use bszet_davinci::{Davinci, Entrypoint};
+
+use crate::api::AppError;
+
+/// Another school served by the same process, loaded from a TOML file with one
+/// table per tenant:
+///
+/// ```toml
+/// [other-school]
+/// entrypoints = ["https://example.org/V_PlanBGy/V_DC_001.html"]
+/// username = "user"
+/// password_file = "/run/secrets/other-school"
+/// chat_ids = [-100123]
+/// ```
+#[derive(Debug, Deserialize)]
+pub(crate) struct Tenant {
+  entrypoints: Vec<String>,
+  username: String,
+  password: Option<String>,
+  password_file: Option<PathBuf>,
+  pub(crate) timetable_dir: Option<PathBuf>,
+  #[serde(default)]
+  pub(crate) chat_ids: Vec<i64>,
+  pub(crate) state_file: Option<PathBuf>,
+}
+
+impl Tenant {
+  pub(crate) fn load(path: &Path) -> anyhow::Result<BTreeMap<String, Self>> {
+    Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+  }
+
+  pub(crate) async fn davinci(&self, client: Client) -> anyhow::Result<Davinci> {
+    let password = match (&self.password, &self.password_file) {
+      (Some(password), _) => password.clone(),
+      (None, Some(path)) => tokio::fs::read_to_string(path).await?,
+      (None, None) => return Err(anyhow!("Either password or password_file is required")),
+    };
+
+    let mut builder = Davinci::builder(self.username.clone(), password).client(client);
+    for entrypoint in &self.entrypoints {
+      builder = builder.entrypoint(entrypoint.parse::<Entrypoint>()?);
+    }
+
+    Ok(builder.build())
+  }
+}
+
+pub(crate) type Tenants = Arc<HashMap<String, Arc<Davinci>>>;
+
+/// Replaces the default instance with the one of the tenant in the path, the
+/// handlers stay unaware of tenants.
+pub(crate) async fn select(
+  State(tenants): State<Tenants>,
+  UrlPath(params): UrlPath<HashMap<String, String>>,
+  mut request: Request,
+  next: Next,
+) -> Response {
+  let davinci = params
+    .get("tenant")
+    .and_then(|tenant| tenants.get(tenant))
+    .cloned();
+
+  match davinci {
+    Some(davinci) => {
+      request.extensions_mut().insert(davinci);
+      next.run(request).await
+    }
+    None => AppError::UnknownTenant.into_response(),
+  }
+}
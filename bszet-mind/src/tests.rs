@@ -1,15 +1,707 @@
+use std::collections::HashSet;
 use std::time::Duration;
 
-use crate::format_duration;
+use bszet_davinci::change::Change;
+use bszet_davinci::timetable::{Lesson, LessonChange, Subject};
+use bszet_davinci::{PlanOptions, Row, Theme};
+use time::macros::{datetime, time};
+use time::{Date, Month};
+
+use crate::allowlist::Network;
+use crate::api::auth::{bearer, Auth, ClassToken, Jwt, Scope};
+use crate::api::davinci::{current_and_next, lessons_csv, lessons_ics, parse_iso_week, Format};
+use crate::api::health::is_alive;
+use crate::api::rows::RowQuery;
+use crate::api::AppError;
+use crate::ascii::{markdown_table, sentences, table, TableFormat};
+use crate::bot::{parse_command, Command};
+use crate::config::{config_args, ConfigFile};
+use crate::diff::diff;
+use crate::digest::weekly_digest;
+use crate::filter::{is_ignored, SubjectFilter};
+use crate::i18n::Language;
+use crate::notification::{NotificationTemplate, Section};
+use crate::rate_limit::RateLimiter;
+use crate::renderer::{plan_url, week_url};
+use crate::retry::{backoff, cool_down_slots};
+use crate::stats::{school_year_start, statistics};
+use crate::subscribers::{Mute, QuietHours};
+use crate::{api_routes, format_duration, Args};
+
+#[test]
+fn test_args() {
+  use clap::CommandFactory;
+
+  Args::command().debug_assert();
+}
 
 #[test]
 fn test_format_duration() {
   assert_eq!(
     "einer Stunde und 2 Minuten",
-    format_duration(Duration::from_secs(60 * 60 + 60 * 2))
+    format_duration(Duration::from_secs(60 * 60 + 60 * 2), Language::De)
   );
   assert_eq!(
     "einer Stunde",
-    format_duration(Duration::from_secs(60 * 60))
+    format_duration(Duration::from_secs(60 * 60), Language::De)
+  );
+  assert_eq!(
+    "a day and 3 hours",
+    format_duration(Duration::from_secs(27 * 60 * 60), Language::En)
+  );
+}
+
+fn cancel(class: &str, lesson: u8, subject: Subject) -> Row {
+  Row {
+    index: 0,
+    date: Date::from_calendar_date(2024, Month::March, 4).unwrap(),
+    class: vec![class.to_string()],
+    change: Change::Cancel {
+      lesson,
+      subject,
+      teachers: vec![],
+      place: "B6".to_string(),
+      notice: "Fällt aus".to_string(),
+    },
+    raw: vec![],
+    group: None,
+  }
+}
+
+#[test]
+fn test_diff() {
+  let old = HashSet::from([
+    cancel("IGD21", 1, Subject::GermanBasic),
+    cancel("IGD21", 2, Subject::Chemistry),
+  ]);
+  let new = HashSet::from([
+    cancel("IGD21", 1, Subject::GermanBasic),
+    cancel("IGD21", 3, Subject::MathBasic),
+    cancel("IGD22", 4, Subject::History),
+  ]);
+
+  assert_eq!(
+    Some(
+      "*Was hat sich geändert:*\n+ 🚫 04.03.: 3. Block Ma entfällt\n− 🚫 04.03.: 2. Block Ch entfällt (zurückgenommen)"
+        .to_string()
+    ),
+    diff(&old, &new, &["IGD21"]).to_markdown(Language::De)
+  );
+  assert!(diff(&old, &old, &["IGD21"]).is_empty());
+}
+
+#[test]
+fn test_subject_filter() {
+  let filter = "-734603836=LK-MA; ETH".parse::<SubjectFilter>().unwrap();
+
+  assert_eq!(-734603836, filter.chat_id);
+  assert_eq!(vec![Subject::MathAdvanced, Subject::Ethics], filter.ignored);
+  assert!(is_ignored(
+    &cancel("IGD21", 1, Subject::Ethics),
+    &filter.ignored
+  ));
+  assert!(!is_ignored(
+    &cancel("IGD21", 1, Subject::GermanBasic),
+    &filter.ignored
+  ));
+  assert!("LK-MA".parse::<SubjectFilter>().is_err());
+}
+
+#[test]
+fn test_quiet_hours() {
+  let night = QuietHours { from: 22, to: 6 };
+  assert!(night.contains(23));
+  assert!(night.contains(0));
+  assert!(!night.contains(6));
+  assert!(!night.contains(12));
+
+  let lunch = QuietHours { from: 12, to: 13 };
+  assert!(lunch.contains(12));
+  assert!(!lunch.contains(13));
+}
+
+#[test]
+fn test_parse_command() {
+  // a wednesday
+  let now = datetime!(2024-03-06 10:00 UTC);
+
+  assert_eq!(Some(Command::Plan), parse_command("/plan@bszet_bot", now));
+  assert_eq!(
+    Some(Command::Mute(Mute::Until(datetime!(2024-03-06 12:00 UTC)))),
+    parse_command("/snooze 2h", now)
+  );
+  assert_eq!(
+    Some(Command::Mute(Mute::Until(datetime!(2024-03-11 00:00 UTC)))),
+    parse_command("/mute until Monday", now)
+  );
+  assert_eq!(
+    Some(Command::Mute(Mute::Until(datetime!(2024-03-13 00:00 UTC)))),
+    parse_command("/mute bis Mittwoch", now)
+  );
+  assert_eq!(
+    Some(Command::Mute(Mute::Indefinitely)),
+    parse_command("/mute", now)
+  );
+  assert_eq!(Some(Command::Dark(true)), parse_command("/dark", now));
+  assert_eq!(Some(Command::Dark(false)), parse_command("/light", now));
+  assert_eq!(Some(Command::Plain(true)), parse_command("/plain", now));
+  assert_eq!(
+    Some(Command::Language(Language::En)),
+    parse_command("/language en", now)
+  );
+  assert_eq!(
+    Some(Command::Language(Language::De)),
+    parse_command("/sprache Deutsch", now)
+  );
+  assert_eq!(
+    Some(Command::Format(TableFormat::Markdown)),
+    parse_command("/format markdown", now)
+  );
+  assert_eq!(
+    Some(Command::Ignore(Subject::MathAdvanced)),
+    parse_command("/ignore LK-MA", now)
+  );
+  assert_eq!(
+    Some(Command::Quiet(Some(QuietHours { from: 22, to: 6 }))),
+    parse_command("/quiet 22-6", now)
+  );
+  assert_eq!(Some(Command::Quiet(None)), parse_command("/quiet off", now));
+  assert_eq!(Some(Command::Help), parse_command("/quiet 25-6", now));
+  assert_eq!(Some(Command::Help), parse_command("/snooze soon", now));
+  assert_eq!(None, parse_command("hello", now));
+}
+
+#[test]
+fn test_weekly_digest() {
+  let rows = HashSet::from([
+    cancel("IGD21", 3, Subject::MathBasic),
+    cancel("IGD21", 1, Subject::GermanBasic),
+    cancel("IGD21", 2, Subject::Ethics),
+  ]);
+  let monday = Date::from_calendar_date(2024, Month::March, 4).unwrap();
+
+  assert_eq!(
+    "*Wochenübersicht 04.03. – 08.03.*\n\n*Montag 04.03.*\n- 1. Block D entfällt\n- 3. Block Ma entfällt",
+    weekly_digest(&rows, monday, &["IGD21"], &[Subject::Ethics], Language::De)
+  );
+  assert_eq!(
+    "*Week overview 11.03. – 15.03.*\n\nNo known changes for the coming week.",
+    weekly_digest(
+      &rows,
+      monday + time::Duration::weeks(1),
+      &["IGD21"],
+      &[],
+      Language::En
+    )
+  );
+}
+
+#[test]
+fn test_statistics() {
+  let rows = HashSet::from([
+    cancel("IGD21", 1, Subject::MathBasic),
+    cancel("IGD21", 2, Subject::MathBasic),
+    cancel("IGD21", 3, Subject::Ethics),
+  ]);
+  let monday = Date::from_calendar_date(2024, Month::March, 4).unwrap();
+
+  let stats = statistics(&rows, school_year_start(monday), monday);
+  assert_eq!(3, stats.cancellations);
+  assert_eq!(Some(&2), stats.by_subject.get("Ma"));
+  assert_eq!(Some(&3), stats.by_weekday.get("Monday"));
+  assert_eq!(Some(&3), stats.by_month.get("2024-03"));
+
+  assert_eq!(
+    0,
+    statistics(&rows, monday.next_day().unwrap(), Date::MAX).cancellations
+  );
+  assert_eq!(
+    Date::from_calendar_date(2023, Month::August, 1).unwrap(),
+    school_year_start(monday)
+  );
+}
+
+#[test]
+fn test_table() {
+  let mut cancelled = Lesson::new(2, None, Subject::Chemistry, "B9");
+  cancelled.subject = Subject::Cancel(Box::new(Subject::Chemistry));
+  cancelled.notice = Some("Fällt aus".to_string());
+  cancelled.change = Some(LessonChange::Cancel);
+
+  let mut moved = Lesson::new(3, None, Subject::History, "B6");
+  moved.notice = Some("Verlegt vom Montag, 4. Stunde".to_string());
+  moved.change = Some(LessonChange::Moved);
+
+  let civics = Subject::Other("Gemeinschaftskunde Rechtserziehung Wirtschaft".to_string());
+
+  assert_eq!(
+    "1    D      B6\n2  ✗ (Ch)   B9   Fällt aus\n3  ↷ Ge     B6   Verlegt vom\n                 Montag, 4.\n                 Stunde\n5    GRW    B112\n6    Infor. B6\n10   LK-D",
+    table(
+      vec![
+        Lesson::new(1, None, Subject::GermanBasic, "B6"),
+        cancelled,
+        moved,
+        Lesson::new(5, None, civics, "B 112"),
+        Lesson::new(6, None, Subject::Other("Informatik".to_string()), "B6"),
+        Lesson {
+          place: None,
+          ..Lesson::new(10, None, Subject::GermanAdvanced, "")
+        },
+      ],
+      30
+    )
+  );
+}
+
+#[test]
+fn test_sentences() {
+  let mut cancelled = Lesson::new(3, None, Subject::MathBasic, "B9");
+  cancelled.subject = Subject::Cancel(Box::new(Subject::MathBasic));
+  cancelled.notice = Some("Fällt aus".to_string());
+
+  let mut moved = Lesson::new(4, None, Subject::EnglishBasic, "B11");
+  moved.notice = Some("Raumänderung".to_string());
+
+  let lessons = || vec![cancelled.clone(), moved.clone()];
+
+  assert_eq!(
+    "3. Stunde Mathe entfällt.\n4. Stunde Englisch in Raum B11 (Raumänderung).",
+    sentences(lessons(), Language::De)
+  );
+  assert_eq!(
+    "Lesson 3: Maths is cancelled.\nLesson 4: English in room B11 (Raumänderung).",
+    sentences(lessons(), Language::En)
+  );
+}
+
+#[test]
+fn test_markdown_table() {
+  let mut cancelled = Lesson::new(2, None, Subject::Chemistry, "B9");
+  cancelled.subject = Subject::Cancel(Box::new(Subject::Chemistry));
+  cancelled.notice = Some("Fällt aus | siehe Moodle".to_string());
+  cancelled.change = Some(LessonChange::Cancel);
+
+  assert_eq!(
+    "| Std. |  | Fach | Raum | Mitteilung |\n|---|---|---|---|---|\n| 1 |  | D | B6 |  |\n| 2 | ✗ | (Ch) | B9 | Fällt aus \\| siehe Moodle |",
+    markdown_table(
+      vec![Lesson::new(1, None, Subject::GermanBasic, "B6"), cancelled],
+      Language::De
+    )
+  );
+}
+
+#[test]
+fn test_lessons_csv() {
+  let lesson = crate::api::davinci::Lesson {
+    lesson: 2,
+    subject: "Ch".to_string(),
+    iteration: None,
+    place: Some("B9".to_string()),
+    teachers: vec![],
+    notice: Some("Aufgaben, siehe Moodle".to_string()),
+    cancel: false,
+    exam: false,
+  };
+
+  assert_eq!(
+    "lesson,time,subject,room,change\r\n2,,Ch,B9,\"Aufgaben, siehe Moodle\"\r\n",
+    lessons_csv(&[lesson])
+  );
+}
+
+#[test]
+fn test_format_negotiate() {
+  use axum::http::header::ACCEPT;
+  use axum::http::{HeaderMap, HeaderValue};
+
+  let mut headers = HeaderMap::new();
+  headers.insert(ACCEPT, HeaderValue::from_static("text/csv"));
+
+  assert_eq!(Some(Format::Csv), Format::negotiate(None, &headers).ok());
+  assert_eq!(
+    Some(Format::Json),
+    Format::negotiate(Some("json"), &headers).ok()
+  );
+  assert_eq!(
+    Some(Format::Json),
+    Format::negotiate(None, &HeaderMap::new()).ok()
+  );
+  assert!(matches!(
+    Format::negotiate(Some("xml"), &headers),
+    Err(AppError::UnknownFormat)
+  ));
+}
+
+#[test]
+fn test_lessons_ics() {
+  let lesson = crate::api::davinci::Lesson {
+    lesson: 3,
+    subject: "Ch".to_string(),
+    iteration: None,
+    place: Some("B9".to_string()),
+    teachers: vec![],
+    notice: Some("Fällt aus; siehe Moodle".to_string()),
+    cancel: true,
+    exam: false,
+  };
+
+  assert_eq!(
+    "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//bszet-mind//timetable//DE\r\n\
+     BEGIN:VEVENT\r\nUID:20231106-igd21-3-0-0@bszet-mind\r\nDTSTAMP:20231105T120000Z\r\n\
+     DTSTART;VALUE=DATE:20231106\r\nSUMMARY:Ch\r\nLOCATION:B9\r\n\
+     DESCRIPTION:Fällt aus\\; siehe Moodle\r\nSTATUS:CANCELLED\r\nEND:VEVENT\r\n\
+     END:VCALENDAR\r\n",
+    lessons_ics(
+      Date::from_calendar_date(2023, Month::November, 6).unwrap(),
+      "IGD21",
+      &[lesson.clone()],
+      datetime!(2023-11-05 12:00 UTC),
+    )
+  );
+
+  // parallel group lessons in the same block
+  let english = crate::api::davinci::Lesson {
+    subject: "En".to_string(),
+    ..lesson.clone()
+  };
+  let ics = lessons_ics(
+    Date::from_calendar_date(2023, Month::November, 6).unwrap(),
+    "IGD21",
+    &[lesson, english],
+    datetime!(2023-11-05 12:00 UTC),
+  );
+  assert!(ics.contains("UID:20231106-igd21-3-0-0@bszet-mind"));
+  assert!(ics.contains("UID:20231106-igd21-3-0-1@bszet-mind"));
+}
+
+#[test]
+fn test_current_and_next() {
+  let lesson = |lesson, subject: &str, cancel| crate::api::davinci::Lesson {
+    lesson,
+    subject: subject.to_string(),
+    iteration: None,
+    place: None,
+    teachers: vec![],
+    notice: None,
+    cancel,
+    exam: false,
+  };
+  let times = |lesson| match lesson {
+    1 => Some((time!(7:30), time!(9:00))),
+    2 => Some((time!(9:20), time!(10:50))),
+    3 => Some((time!(11:20), time!(12:50))),
+    _ => None,
+  };
+  let lessons = vec![
+    lesson(1, "Ma", false),
+    lesson(2, "Ph", true),
+    lesson(3, "En", false),
+    lesson(4, "Ch", false),
+  ];
+
+  let now = current_and_next(lessons.clone(), time!(8:00), times);
+  assert_eq!("Ma", now.current.unwrap().lesson.subject);
+  let next = now.next.unwrap();
+  assert_eq!("En", next.lesson.subject);
+  assert_eq!("11:20", next.start);
+
+  let now = current_and_next(lessons.clone(), time!(9:10), times);
+  assert_eq!(None, now.current);
+  assert_eq!("En", now.next.unwrap().lesson.subject);
+
+  let now = current_and_next(lessons, time!(13:00), times);
+  assert_eq!(None, now.current);
+  assert_eq!(None, now.next);
+}
+
+#[test]
+fn test_row_query() {
+  let rows = [
+    cancel("IGD21", 2, Subject::Chemistry),
+    cancel("IGD22", 1, Subject::History),
+  ];
+
+  let query = RowQuery {
+    class: Some("igd 21".to_string()),
+    change_type: Some("cancel".to_string()),
+    fields: Some("lesson,subject".to_string()),
+  };
+  assert_eq!(
+    vec![serde_json::json!({ "lesson": 2, "subject": "Chemistry" })],
+    query.select(&rows)
+  );
+
+  let query = RowQuery {
+    class: None,
+    change_type: Some("room_change".to_string()),
+    fields: None,
+  };
+  assert!(query.select(&rows).is_empty());
+
+  let query = RowQuery {
+    class: None,
+    change_type: None,
+    fields: Some("class,change_type,place".to_string()),
+  };
+  assert_eq!(
+    serde_json::json!({ "class": ["IGD22"], "change_type": "cancel", "place": "B6" }),
+    query.select(&rows)[1]
+  );
+}
+
+#[test]
+fn test_rate_limiter() {
+  let limiter = RateLimiter::new(2);
+  let ip = "127.0.0.1".parse().unwrap();
+  let now = std::time::Instant::now();
+
+  assert!(limiter.check(ip, now));
+  assert!(limiter.check(ip, now));
+  assert!(!limiter.check(ip, now));
+  assert!(limiter.check("127.0.0.2".parse().unwrap(), now));
+  assert!(limiter.check(ip, now + Duration::from_secs(30)));
+}
+
+#[test]
+fn test_jwt() {
+  use jsonwebtoken::{encode, EncodingKey, Header};
+
+  let token = |iss: &str| {
+    encode(
+      &Header::default(),
+      &serde_json::json!({ "iss": iss, "exp": 4102444800u64 }),
+      &EncodingKey::from_secret(b"secret"),
+    )
+    .unwrap()
+  };
+
+  let jwt = Jwt::hs256("secret").with_claims(Some("https://idp.example"), None);
+  assert!(jwt.verify(&token("https://idp.example")));
+  assert!(!jwt.verify(&token("https://other.example")));
+  assert!(!Jwt::hs256("other").verify(&token("https://idp.example")));
+}
+
+#[test]
+fn test_class_token_scope() {
+  use axum::http::Method;
+
+  let token = "IGD 21=secret".parse::<ClassToken>().unwrap();
+  assert_eq!("IGD 21", token.class);
+  assert_eq!("secret", token.token);
+  assert!("IGD21".parse::<ClassToken>().is_err());
+  assert!("IGD21=".parse::<ClassToken>().is_err());
+
+  let route = "/davinci/:date/:class";
+  let scope = Scope::Class(token.class);
+  assert!(scope.allows(&Method::GET, route, Some("igd21")));
+  assert!(!scope.allows(&Method::GET, route, Some("IGD22")));
+  assert!(!scope.allows(&Method::GET, route, None));
+  assert!(!scope.allows(&Method::PUT, route, Some("IGD21")));
+  assert!(!scope.allows(&Method::GET, "/subscribers", Some("IGD21")));
+  assert!(Scope::All.allows(&Method::PUT, "/subscribers", None));
+}
+
+#[tokio::test]
+async fn test_class_token_routes() {
+  use std::sync::{Arc, RwLock};
+
+  use axum::body::Body;
+  use axum::http::header::AUTHORIZATION;
+  use axum::http::{Request, StatusCode};
+  use axum::{middleware, Extension, Router};
+  use bszet_davinci::Davinci;
+  use tower::ServiceExt;
+
+  let router = api_routes(Router::new())
+    .layer(Extension(Arc::new(
+      Davinci::builder("user".to_string(), "pass".to_string()).build(),
+    )))
+    .layer(middleware::from_fn_with_state(
+      Auth {
+        token: Arc::new(RwLock::new(Some("admin".to_string()))),
+        jwt: None,
+        class_tokens: Arc::new(vec!["IGD21=secret".parse().unwrap()]),
+      },
+      bearer,
+    ));
+
+  let status = |uri: &str| {
+    let request = Request::get(uri)
+      .header(AUTHORIZATION, "Bearer secret")
+      .body(Body::empty())
+      .unwrap();
+    let router = router.clone();
+    async move { router.oneshot(request).await.unwrap().status() }
+  };
+
+  assert_eq!(
+    StatusCode::FORBIDDEN,
+    status("/subscribers?class=IGD21").await
+  );
+  assert_eq!(
+    StatusCode::FORBIDDEN,
+    status("/subscribers/1?class=IGD21").await
+  );
+  assert_eq!(
+    StatusCode::FORBIDDEN,
+    status("/log-level?class=IGD21").await
+  );
+  assert_eq!(StatusCode::FORBIDDEN, status("/stats?class=IGD21").await);
+  assert_eq!(
+    StatusCode::FORBIDDEN,
+    status("/davinci/2024-03-06/IGD22").await
+  );
+  assert_ne!(
+    StatusCode::FORBIDDEN,
+    status("/davinci/2024-03-06/IGD21").await
+  );
+}
+
+#[test]
+fn test_network() {
+  let network = "10.0.0.0/8".parse::<Network>().unwrap();
+  assert!(network.contains("10.1.2.3".parse().unwrap()));
+  assert!(!network.contains("192.168.0.1".parse().unwrap()));
+
+  let single = "::1".parse::<Network>().unwrap();
+  assert!(single.contains("::1".parse().unwrap()));
+  assert!("localhost".parse::<Network>().is_err());
+}
+
+#[test]
+fn test_backoff() {
+  let delay = backoff(1);
+  assert!(delay >= Duration::from_secs(20) && delay <= Duration::from_secs(30));
+  assert!(backoff(10) <= Duration::from_secs(180));
+
+  assert_eq!(1, cool_down_slots(0));
+  assert_eq!(1, cool_down_slots(3));
+  assert_eq!(2, cool_down_slots(4));
+  assert_eq!(4, cool_down_slots(100));
+}
+
+#[test]
+fn test_config_args() {
+  use clap::{CommandFactory, FromArgMatches};
+
+  let config: ConfigFile = toml::from_str(
+    r#"
+      timeout = 30
+      connect-timeout = 5
+      bot = true
+      once = false
+
+      [channels]
+      chat_ids = [-100123, -100456]
+    "#,
+  )
+  .unwrap();
+
+  let args = [
+    "bszet-mind",
+    "--username=user",
+    "--password=secret",
+    "--telegram-token=token",
+    "--api-token=token",
+    "--timeout",
+    "90",
+  ];
+  let matches = Args::command()
+    .ignore_errors(true)
+    .try_get_matches_from(args)
+    .unwrap();
+
+  let extra = config_args(&Args::command(), &matches, &config).unwrap();
+  assert_eq!(
+    vec![
+      "--bot",
+      "--connect-timeout=5",
+      "--chat-ids=-100123",
+      "--chat-ids=-100456",
+    ],
+    extra
+  );
+
+  // the command line takes precedence over the config
+  let matches = Args::command()
+    .try_get_matches_from(args.into_iter().chain(extra.iter().map(String::as_str)))
+    .unwrap();
+  let merged = Args::from_arg_matches(&matches).unwrap();
+  assert_eq!(90, merged.timeout);
+  assert_eq!(5, merged.connect_timeout);
+  assert_eq!(vec![-100123, -100456], merged.chat_ids);
+  assert!(merged.bot);
+
+  let unknown: ConfigFile = toml::from_str("[admin]\nchat_id = 1").unwrap();
+  assert!(config_args(&Args::command(), &matches, &unknown).is_err());
+}
+
+#[test]
+fn test_is_alive() {
+  assert!(is_alive(1_000, 1_000 + 15 * 60));
+  assert!(!is_alive(1_000, 1_000 + 31 * 60));
+  assert!(!is_alive(0, 1_700_000_000));
+}
+
+#[test]
+fn test_plan_url() {
+  let base_url = "http://127.0.0.1:8081/tenant/".parse().unwrap();
+  let date = Date::from_calendar_date(2024, Month::March, 4).unwrap();
+
+  assert_eq!(
+    "http://127.0.0.1:8081/tenant/davinci/2024-03-04?class=IGD+21%2CIGD21&theme=light&collapse=false&nav=false",
+    plan_url(&base_url, date, &["IGD 21", "IGD21"], PlanOptions::default())
+      .unwrap()
+      .as_str()
+  );
+}
+
+#[test]
+fn test_week_url() {
+  let base_url = "http://127.0.0.1:8081/tenant/".parse().unwrap();
+  let monday = Date::from_calendar_date(2023, Month::January, 30).unwrap();
+
+  assert_eq!(
+    "http://127.0.0.1:8081/tenant/davinci/week/2023-W05/IGD%2021?theme=dark",
+    week_url(&base_url, monday, "IGD 21", Theme::Dark)
+      .unwrap()
+      .as_str()
+  );
+  assert_eq!(Some(monday), parse_iso_week("2023-W05"));
+  assert_eq!(None, parse_iso_week("2023-W54"));
+  assert_eq!(None, parse_iso_week("2023-05"));
+}
+
+#[test]
+fn test_notification_template() {
+  let messages = ["Wandertag der 11. Klassen".to_string()];
+  let template = |sections| NotificationTemplate {
+    sections,
+    language: Language::De,
+    changes: Some("*Was hat sich geändert:*\n+ 🚫 04.03.: 3. Block Ma entfällt".to_string()),
+    title: "Vertretungsplan".to_string(),
+    table: "```\n1 D B6```".to_string(),
+    supervisions: &[],
+    messages: &messages,
+    unapplied: &[],
+  };
+
+  assert_eq!(
+    "*Was hat sich geändert:*\n+ 🚫 04.03.: 3. Block Ma entfällt\n\nVertretungsplan\n```\n1 D B6```\n\nNachrichten zum Tag:\n- Wandertag der 11. Klassen",
+    template(vec![
+      Section::Changes,
+      Section::Plan,
+      Section::Supervisions,
+      Section::Messages,
+      Section::Unapplied,
+    ])
+    .render()
+    .unwrap()
+  );
+  assert_eq!(
+    "Nachrichten zum Tag:\n- Wandertag der 11. Klassen\n\nVertretungsplan\n```\n1 D B6```",
+    template(vec![Section::Messages, Section::Plan])
+      .render()
+      .unwrap()
   );
 }
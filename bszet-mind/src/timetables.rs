@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+use bszet_davinci::Davinci;
+
+/// Loads the base timetables of the directory and keeps them up to date.
+pub(crate) fn load(davinci: Arc<Davinci>, dir: PathBuf) -> anyhow::Result<()> {
+  davinci.timetables().load_dir(&dir)?;
+
+  tokio::spawn(async move {
+    if let Err(err) = watch(davinci, dir).await {
+      error!("Unable to watch timetables: {}", err);
+    }
+  });
+
+  Ok(())
+}
+
+/// Reloads the base timetables whenever a file in the directory changes.
+async fn watch(davinci: Arc<Davinci>, dir: PathBuf) -> anyhow::Result<()> {
+  let (tx, mut rx) = mpsc::channel(1);
+
+  let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+    match event {
+      Ok(event) if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() => {
+        // a reload is already pending if the channel is full
+        let _ = tx.try_send(());
+      }
+      Ok(_) => {}
+      Err(err) => error!("Unable to watch timetables: {}", err),
+    }
+  })?;
+  watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+  while rx.recv().await.is_some() {
+    // editors tend to write files in multiple steps
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    while rx.try_recv().is_ok() {}
+
+    info!("Timetables in {} changed, reloading...", dir.display());
+    if let Err(err) = davinci.timetables().load_dir(&dir) {
+      error!("Unable to reload timetables: {}", err);
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,125 @@
+// Browser frontend of the api, the token is kept in the local storage.
+const TOKEN_KEY = 'bszet-mind-token';
+
+const login = document.getElementById('login');
+const select = document.getElementById('select');
+const status = document.getElementById('status');
+const timetable = document.getElementById('timetable');
+
+async function api(path) {
+    const response = await fetch(path, {
+        headers: {Authorization: `Bearer ${localStorage.getItem(TOKEN_KEY)}`},
+    });
+
+    if (response.status === 401) {
+        logout();
+        throw new Error('Der Token wurde abgelehnt.');
+    }
+    if (!response.ok) {
+        throw new Error(await response.text());
+    }
+
+    return response.json();
+}
+
+function options(element, values, label) {
+    element.replaceChildren(...values.map((value) => new Option(label(value), value)));
+}
+
+function formatDate(value) {
+    return new Date(`${value}T00:00:00`).toLocaleDateString('de-DE', {
+        weekday: 'long',
+        day: '2-digit',
+        month: '2-digit',
+        year: 'numeric',
+    });
+}
+
+function cell(text, className) {
+    const td = document.createElement('td');
+    td.textContent = text ?? '';
+    if (className) {
+        td.className = className;
+    }
+    return td;
+}
+
+async function showTimetable() {
+    const date = select.elements.date.value;
+    const className = select.elements.class.value;
+    if (!date || !className) {
+        return;
+    }
+
+    status.textContent = 'Lädt…';
+
+    try {
+        const lessons = await api(`/davinci/${date}/${encodeURIComponent(className)}`);
+
+        timetable.tBodies[0].replaceChildren(...lessons.map((lesson) => {
+            const tr = document.createElement('tr');
+            if (lesson.cancel) {
+                tr.className = 'cancel';
+            }
+
+            tr.append(
+                cell(lesson.lesson),
+                cell(lesson.subject, lesson.exam ? 'exam' : undefined),
+                cell(lesson.place),
+                cell(lesson.teachers.join(', ')),
+                cell(lesson.notice),
+            );
+            return tr;
+        }));
+
+        timetable.hidden = false;
+        status.textContent = '';
+    } catch (err) {
+        timetable.hidden = true;
+        status.textContent = err.message;
+    }
+}
+
+async function start() {
+    if (!localStorage.getItem(TOKEN_KEY)) {
+        login.hidden = false;
+        return;
+    }
+
+    try {
+        const [classes, dates] = await Promise.all([api('/davinci/classes'), api('/davinci/dates')]);
+
+        options(select.elements.class, classes, (value) => value);
+        options(select.elements.date, dates, formatDate);
+        select.elements.class.value = localStorage.getItem(`${TOKEN_KEY}-class`) ?? classes[0];
+
+        select.hidden = false;
+        await showTimetable();
+    } catch (err) {
+        status.textContent = err.message;
+    }
+}
+
+function logout() {
+    localStorage.removeItem(TOKEN_KEY);
+    select.hidden = true;
+    timetable.hidden = true;
+    login.hidden = false;
+}
+
+login.addEventListener('submit', (event) => {
+    event.preventDefault();
+    localStorage.setItem(TOKEN_KEY, login.elements.token.value);
+    login.reset();
+    login.hidden = true;
+    start();
+});
+
+select.addEventListener('change', () => {
+    localStorage.setItem(`${TOKEN_KEY}-class`, select.elements.class.value);
+    showTimetable();
+});
+
+document.getElementById('logout').addEventListener('click', logout);
+
+start();
@@ -1,11 +1,14 @@
+use std::sync::RwLock;
+
+use anyhow::anyhow;
 use reqwest::header::{HeaderValue, CONTENT_TYPE};
 use reqwest::multipart::{Form, Part};
-use reqwest::{Client, Url};
-use serde::Serialize;
+use reqwest::{Client, Response, Url};
+use serde::{Deserialize, Serialize};
 
 pub struct Telegram {
   client: Client,
-  base: Url,
+  base: RwLock<Url>,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,6 +37,35 @@ struct SendMediaGroupData {
   allow_sending_without_reply: Option<bool>,
 }
 
+#[derive(Debug, Serialize)]
+struct GetUpdatesData {
+  offset: Option<i64>,
+  timeout: u32,
+  allowed_updates: Vec<&'static str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+  result: T,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Update {
+  pub update_id: i64,
+  pub message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Message {
+  pub chat: Chat,
+  pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Chat {
+  pub id: i64,
+}
+
 #[derive(Debug, Serialize)]
 struct SendMessageData {
   chat_id: i64,
@@ -43,15 +75,46 @@ struct SendMessageData {
 
 impl Telegram {
   pub fn new(token: &str) -> anyhow::Result<Self> {
-    let raw = format!("https://api.telegram.org/bot{token}/");
-    let base = Url::parse(&raw)?;
-
     Ok(Self {
       client: Client::new(),
-      base,
+      base: RwLock::new(base_url(token)?),
     })
   }
 
+  /// Swaps the bot token, e.g. after the secret was rotated.
+  pub fn set_token(&self, token: &str) -> anyhow::Result<()> {
+    *self.base.write().unwrap() = base_url(token)?;
+    Ok(())
+  }
+
+  fn url(&self, method: &str) -> anyhow::Result<Url> {
+    Ok(self.base.read().unwrap().join(method)?)
+  }
+
+  /// Long polls for new messages, `timeout` is given in seconds.
+  pub async fn get_updates(
+    &self,
+    offset: Option<i64>,
+    timeout: u32,
+  ) -> anyhow::Result<Vec<Update>> {
+    let data = GetUpdatesData {
+      offset,
+      timeout,
+      allowed_updates: vec!["message"],
+    };
+
+    let response = self
+      .client
+      .post(self.url("getUpdates")?)
+      .json(&data)
+      .send()
+      .await?;
+
+    let response = check_response(response).await?;
+
+    Ok(response.json::<ApiResponse<Vec<Update>>>().await?.result)
+  }
+
   pub async fn send_text(&self, chat_id: i64, text: &str) -> anyhow::Result<()> {
     let data = SendMessageData {
       chat_id,
@@ -59,13 +122,14 @@ impl Telegram {
       parse_mode: ParseMode::Markdown,
     };
 
-    self
+    let response = self
       .client
-      .post(self.base.join("sendMessage")?)
+      .post(self.url("sendMessage")?)
       .json(&data)
       .send()
-      .await?
-      .error_for_status()?;
+      .await?;
+
+    check_response(response).await?;
 
     Ok(())
   }
@@ -80,14 +144,15 @@ impl Telegram {
     let mut media = Vec::new();
 
     for (index, image) in images.iter().enumerate() {
-      let file_name = format!("{index}.png");
+      let (extension, mime) = image_type(image);
+      let file_name = format!("{index}.{extension}");
       let field_name = format!("file{}", index + 1);
 
       form = form.part(
         field_name.clone(),
         Part::bytes(image.clone())
           .file_name(file_name.clone())
-          .mime_str("image/png")?,
+          .mime_str(mime)?,
       );
 
       media.push(InputMediaPhoto {
@@ -106,15 +171,76 @@ impl Telegram {
     let media_str = serde_json::to_string(&media)?;
     form = form.part("media", Part::text(media_str).mime_str("application/json")?);
 
-    self
+    let response = self
       .client
-      .post(self.base.join("sendMediaGroup")?)
+      .post(self.url("sendMediaGroup")?)
       .header(CONTENT_TYPE, HeaderValue::from_str("application/json")?)
       .multipart(form)
       .send()
-      .await?
-      .error_for_status()?;
+      .await?;
+
+    check_response(response).await?;
+
+    Ok(())
+  }
+
+  pub async fn send_document(
+    &self,
+    chat_id: i64,
+    caption: Option<&str>,
+    file_name: &str,
+    document: &[u8],
+  ) -> anyhow::Result<()> {
+    let mut form = Form::new()
+      .part("chat_id", Part::text(chat_id.to_string()))
+      .part(
+        "document",
+        Part::bytes(document.to_vec()).file_name(file_name.to_string()),
+      );
+
+    if let Some(caption) = caption {
+      form = form
+        .part("caption", Part::text(caption.to_string()))
+        .part("parse_mode", Part::text("Markdown"));
+    }
+
+    let response = self
+      .client
+      .post(self.url("sendDocument")?)
+      .multipart(form)
+      .send()
+      .await?;
+
+    check_response(response).await?;
 
     Ok(())
   }
 }
+
+/// Extension and mime type of a png or webp image.
+fn image_type(image: &[u8]) -> (&'static str, &'static str) {
+  match image.get(8..12) {
+    Some(b"WEBP") if image.starts_with(b"RIFF") => ("webp", "image/webp"),
+    _ => ("png", "image/png"),
+  }
+}
+
+fn base_url(token: &str) -> anyhow::Result<Url> {
+  Ok(Url::parse(&format!(
+    "https://api.telegram.org/bot{token}/"
+  ))?)
+}
+
+/// Like [`Response::error_for_status`], but keeps the description Telegram
+/// returns in the body, which usually explains why a request was rejected.
+async fn check_response(response: Response) -> anyhow::Result<Response> {
+  let status = response.status();
+
+  if status.is_success() {
+    return Ok(response);
+  }
+
+  let body = response.text().await.unwrap_or_default();
+
+  Err(anyhow!("Telegram responded with {status}: {body}"))
+}
@@ -48,11 +48,22 @@ in
       chatIds = lib.mkOption {
         type = lib.types.listOf lib.types.int;
       };
+      adminChatId = lib.mkOption {
+        type = lib.types.nullOr lib.types.int;
+        default = null;
+      };
+      bot = lib.mkEnableOption "bot commands like /plan and /mute";
     };
 
     apiTokenFile = lib.mkOption {
       type = lib.types.str;
     };
+
+    timetableDir = lib.mkOption {
+      type = lib.types.nullOr lib.types.str;
+      default = null;
+      description = "Directory with <class>.toml base timetables, reloaded on changes.";
+    };
   };
 
   config = lib.mkIf cfg.enable {
@@ -91,12 +102,19 @@ in
           BSZET_MIND_INTERNAL_LISTEN_ADDR = "${if (lib.hasInfix ":" cfg.internalListen.addr) then "[${cfg.internalListen.addr}]" else cfg.internalListen.addr}:${toString cfg.internalListen.port}";
           BSZET_MIND_INTERNAL_URL = "http://${if (lib.hasInfix ":" cfg.internalListen.addr) then "[${cfg.internalListen.addr}]" else cfg.internalListen.addr}:${toString cfg.internalListen.port}";
           BSZET_MIND_API_TOKEN_FILE = "%d/api_token";
+          BSZET_MIND_STATE_FILE = "/var/lib/bszet-mind/state.json";
+          BSZET_MIND_SUBSCRIBERS_FILE = "/var/lib/bszet-mind/subscribers.json";
+          BSZET_MIND_STATS_FILE = "/var/lib/bszet-mind/stats.json";
+          BSZET_MIND_BOT = lib.boolToString cfg.telegram.bot;
+          BSZET_MIND_ADMIN_CHAT_ID = lib.mkIf (cfg.telegram.adminChatId != null) (toString cfg.telegram.adminChatId);
+          BSZET_MIND_TIMETABLE_DIR = lib.mkIf (cfg.timetableDir != null) cfg.timetableDir;
         };
 
         serviceConfig = {
           ExecStart = "${cfg.package}/bin/bszet-mind";
           DynamicUser = true;
           User = "bszet-mind";
+          StateDirectory = "bszet-mind";
           LoadCredential = [
             "username:${cfg.usernameFile}"
             "password:${cfg.passwordFile}"